@@ -12,8 +12,14 @@ use crate::example_utils::setup_logger;
 
 mod example_utils;
 
-async fn produce(brokers: &str, topic_name: &str) {
-    let producer: &FutureProducer = &ClientConfig::new()
+async fn produce(brokers: &str, topic_name: &str, properties: Option<&str>) {
+    let mut config = ClientConfig::new();
+    if let Some(path) = properties {
+        config
+            .load_properties(path)
+            .expect("Failed to load properties file");
+    }
+    let producer: &FutureProducer = &config
         .set("bootstrap.servers", brokers)
         .set("message.timeout.ms", "5000")
         .create()
@@ -69,6 +75,12 @@ async fn main() {
                 .help("Configure the logging format (example: 'rdkafka=trace')")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("properties")
+                .long("properties")
+                .help("Java-style .properties file with additional client configuration")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("topic")
                 .short("t")
@@ -87,5 +99,5 @@ async fn main() {
     let topic = matches.value_of("topic").unwrap();
     let brokers = matches.value_of("brokers").unwrap();
 
-    produce(brokers, topic).await;
+    produce(brokers, topic, matches.value_of("properties")).await;
 }
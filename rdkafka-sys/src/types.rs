@@ -99,6 +99,12 @@ pub use bindings::rd_kafka_type_t as RDKafkaType;
 /// Configuration result.
 pub use bindings::rd_kafka_conf_res_t as RDKafkaConfRes;
 
+/// SSL certificate type.
+pub use bindings::rd_kafka_cert_type_t as RDKafkaCertType;
+
+/// SSL certificate encoding.
+pub use bindings::rd_kafka_cert_enc_t as RDKafkaCertEncoding;
+
 /// Response error.
 pub use bindings::rd_kafka_resp_err_t as RDKafkaRespErr;
 
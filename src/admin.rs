@@ -7,12 +7,14 @@
 use std::collections::HashMap;
 use std::ffi::{c_void, CStr, CString};
 use std::future::Future;
+use std::io;
+use std::marker::PhantomData;
 use std::pin::Pin;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::task::{Context, Poll};
-use std::thread::{self, JoinHandle};
-use std::time::Duration;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 
 use futures_channel::oneshot;
 use futures_util::future::{self, Either, FutureExt};
@@ -22,10 +24,14 @@ use rdkafka_sys as rdsys;
 use rdkafka_sys::types::*;
 
 use crate::client::{Client, ClientContext, DefaultClientContext, NativeQueue};
-use crate::config::{ClientConfig, FromClientConfig, FromClientConfigAndContext};
-use crate::error::{IsError, KafkaError, KafkaResult};
+use crate::config::{ClientConfig, ConfigClients, FromClientConfig, FromClientConfigAndContext};
+use crate::error::{err2str, IsError, KafkaError, KafkaResult};
 use crate::log::{trace, warn};
-use crate::util::{cstr_to_owned, AsCArray, ErrBuf, IntoOpaque, KafkaDrop, NativePtr, Timeout};
+use crate::topic_partition_list::{Offset, TopicPartitionList};
+use crate::util::{
+    self, cstr_to_owned, AsCArray, AsyncRuntime, CallbackThread, DefaultRuntime, ErrBuf,
+    IntoOpaque, KafkaDrop, NativePtr, Timeout,
+};
 
 //
 // ********** ADMIN CLIENT **********
@@ -35,14 +41,22 @@ use crate::util::{cstr_to_owned, AsCArray, ErrBuf, IntoOpaque, KafkaDrop, Native
 ///
 /// `AdminClient` provides programmatic access to managing a Kafka cluster,
 /// notably manipulating topics, partitions, and configuration paramaters.
-pub struct AdminClient<C: ClientContext> {
-    client: Client<C>,
+///
+/// The admin operations, such as [`AdminClient::create_topics`], never block:
+/// librdkafka sends their requests in the background, and the returned
+/// futures are resolved by a thread of the admin client when the responses
+/// arrive. The operations that librdkafka does not implement as admin
+/// requests, such as [`AdminClient::describe_cluster`], block on the cluster
+/// metadata instead, so they run on the [`AsyncRuntime`] `R`.
+pub struct AdminClient<C: ClientContext, R = DefaultRuntime> {
+    client: Arc<Client<C>>,
     queue: Arc<NativeQueue>,
     should_stop: Arc<AtomicBool>,
     handle: Option<JoinHandle<()>>,
+    _runtime: PhantomData<R>,
 }
 
-impl<C: ClientContext> AdminClient<C> {
+impl<C: ClientContext, R> AdminClient<C, R> {
     /// Creates new topics according to the provided `NewTopic` specifications.
     ///
     /// Note that while the API supports creating multiple topics at once, it
@@ -334,14 +348,121 @@ impl<C: ClientContext> AdminClient<C> {
     }
 }
 
-impl FromClientConfig for AdminClient<DefaultClientContext> {
-    fn from_config(config: &ClientConfig) -> KafkaResult<AdminClient<DefaultClientContext>> {
+impl<C, R> AdminClient<C, R>
+where
+    C: ClientContext + 'static,
+    R: AsyncRuntime,
+{
+    /// Describes the cluster: its ID, its controller and its brokers.
+    ///
+    /// librdkafka answers this from the cluster metadata rather than with an
+    /// admin request, so the metadata request runs with
+    /// [`AsyncRuntime::spawn_blocking`], and the timeout bounds the whole
+    /// operation. The authorized operations of the cluster are not available
+    /// from the metadata, and are not reported.
+    pub fn describe_cluster<T: Into<Timeout>>(
+        &self,
+        timeout: T,
+    ) -> impl Future<Output = KafkaResult<ClusterDescription>> {
+        let client = self.client.clone();
+        let timeout = timeout.into();
+        util::run_blocking::<R, _, _>(move || {
+            let start = Instant::now();
+            let remaining = || match timeout {
+                Timeout::After(t) => Timeout::After(t.saturating_sub(start.elapsed())),
+                Timeout::Never => Timeout::Never,
+            };
+            let metadata = client.fetch_broker_metadata(remaining())?;
+            // The cluster and controller IDs are cached from the metadata
+            // response, so these calls do not wait for the brokers again.
+            let cluster_id = client.fetch_cluster_id(remaining());
+            let controller_id = client.fetch_controller_id(remaining());
+            let brokers = metadata
+                .brokers()
+                .iter()
+                .map(|broker| ClusterBroker {
+                    id: broker.id(),
+                    host: broker.host().to_string(),
+                    port: broker.port(),
+                })
+                .collect();
+            Ok(ClusterDescription {
+                cluster_id,
+                controller_id,
+                brokers,
+            })
+        })
+    }
+
+    /// Looks up the offsets of the specified partitions: their earliest or
+    /// latest offsets, or the earliest offsets whose timestamp is at least
+    /// the specified timestamp.
+    ///
+    /// The offsets of all the partitions led by the same broker are looked up
+    /// with a single request, without the need for a consumer. Like
+    /// [`AdminClient::describe_cluster`], the request runs with
+    /// [`AsyncRuntime::spawn_blocking`].
+    ///
+    /// The offsets are returned in a [`TopicPartitionList`], in which errors
+    /// that affect a single partition are reported by
+    /// [`TopicPartitionListElem::error`]. The offset of a partition is
+    /// [`Offset::End`] if no message has a timestamp at least as large as the
+    /// requested timestamp. Negative timestamps are rejected with
+    /// [`RDKafkaErrorCode::InvalidArgument`], as the brokers would interpret
+    /// them as requests for the earliest or latest offsets.
+    ///
+    /// [`TopicPartitionListElem::error`]: crate::topic_partition_list::TopicPartitionListElem::error
+    pub fn list_offsets<'a, I, T>(
+        &self,
+        partitions: I,
+        timeout: T,
+    ) -> impl Future<Output = KafkaResult<TopicPartitionList>>
+    where
+        I: IntoIterator<Item = (&'a str, i32, OffsetSpec)>,
+        T: Into<Timeout>,
+    {
+        let mut tpl = TopicPartitionList::new();
+        for (topic, partition, spec) in partitions {
+            let res = spec
+                .to_offset()
+                .and_then(|offset| tpl.add_partition_offset(topic, partition, offset));
+            if let Err(err) = res {
+                return Either::Right(future::err(err));
+            }
+        }
+        let client = self.client.clone();
+        let timeout = timeout.into();
+        Either::Left(util::run_blocking::<R, _, _>(move || {
+            // ListOffsets requests take the special timestamps -2 and -1 to
+            // look up the earliest and latest offsets, which librdkafka
+            // passes through.
+            let ret = unsafe {
+                rdsys::rd_kafka_offsets_for_times(
+                    client.native_ptr(),
+                    tpl.ptr(),
+                    timeout.as_millis(),
+                )
+            };
+            if ret.is_error() {
+                return Err(KafkaError::OffsetFetch(ret.into()));
+            }
+            Ok(tpl)
+        }))
+    }
+}
+
+impl<R> FromClientConfig for AdminClient<DefaultClientContext, R> {
+    fn from_config(config: &ClientConfig) -> KafkaResult<AdminClient<DefaultClientContext, R>> {
         AdminClient::from_config_and_context(config, DefaultClientContext)
     }
 }
 
-impl<C: ClientContext> FromClientConfigAndContext<C> for AdminClient<C> {
-    fn from_config_and_context(config: &ClientConfig, context: C) -> KafkaResult<AdminClient<C>> {
+impl<C: ClientContext, R> FromClientConfigAndContext<C> for AdminClient<C, R> {
+    fn from_config_and_context(
+        config: &ClientConfig,
+        context: C,
+    ) -> KafkaResult<AdminClient<C, R>> {
+        config.validate_if_strict(ConfigClients::All)?;
         let native_config = config.create_native_config()?;
         // librdkafka only provides consumer and producer types. We follow the
         // example of the Python bindings in choosing to pretend to be a
@@ -356,17 +477,25 @@ impl<C: ClientContext> FromClientConfigAndContext<C> for AdminClient<C> {
         )?;
         let queue = Arc::new(client.new_native_queue());
         let should_stop = Arc::new(AtomicBool::new(false));
-        let handle = start_poll_thread(queue.clone(), should_stop.clone());
+        let polling_thread = client
+            .context()
+            .polling_thread()
+            .unwrap_or_else(|| CallbackThread::new("admin client polling thread"));
+        let handle = start_poll_thread(&polling_thread, queue.clone(), should_stop.clone())
+            .map_err(|e| {
+                KafkaError::ClientCreation(format!("failed to start polling thread: {}", e))
+            })?;
         Ok(AdminClient {
-            client,
+            client: Arc::new(client),
             queue,
             should_stop,
             handle: Some(handle),
+            _runtime: PhantomData,
         })
     }
 }
 
-impl<C: ClientContext> Drop for AdminClient<C> {
+impl<C: ClientContext, R> Drop for AdminClient<C, R> {
     fn drop(&mut self) {
         trace!("Stopping polling");
         self.should_stop.store(true, Ordering::Relaxed);
@@ -378,29 +507,30 @@ impl<C: ClientContext> Drop for AdminClient<C> {
     }
 }
 
-fn start_poll_thread(queue: Arc<NativeQueue>, should_stop: Arc<AtomicBool>) -> JoinHandle<()> {
-    thread::Builder::new()
-        .name("admin client polling thread".into())
-        .spawn(move || {
-            trace!("Admin polling thread loop started");
-            loop {
-                let event = queue.poll(Duration::from_millis(100));
-                if event.is_null() {
-                    if should_stop.load(Ordering::Relaxed) {
-                        // We received nothing and the thread should stop, so
-                        // break the loop.
-                        break;
-                    }
-                    continue;
+fn start_poll_thread(
+    polling_thread: &CallbackThread,
+    queue: Arc<NativeQueue>,
+    should_stop: Arc<AtomicBool>,
+) -> io::Result<JoinHandle<()>> {
+    polling_thread.spawn(move || {
+        trace!("Admin polling thread loop started");
+        loop {
+            let event = queue.poll(Duration::from_millis(100));
+            if event.is_null() {
+                if should_stop.load(Ordering::Relaxed) {
+                    // We received nothing and the thread should stop, so
+                    // break the loop.
+                    break;
                 }
-                let event = unsafe { NativeEvent::from_ptr(event).unwrap() };
-                let tx: Box<oneshot::Sender<NativeEvent>> =
-                    unsafe { IntoOpaque::from_ptr(rdsys::rd_kafka_event_opaque(event.ptr())) };
-                let _ = tx.send(event);
+                continue;
             }
-            trace!("Admin polling thread loop terminated");
-        })
-        .expect("Failed to start polling thread")
+            let event = unsafe { NativeEvent::from_ptr(event).unwrap() };
+            let tx: Box<oneshot::Sender<NativeEvent>> =
+                unsafe { IntoOpaque::from_ptr(rdsys::rd_kafka_event_opaque(event.ptr())) };
+            let _ = tx.send(event);
+        }
+        trace!("Admin polling thread loop terminated");
+    })
 }
 
 type NativeEvent = NativePtr<RDKafkaEvent>;
@@ -417,7 +547,8 @@ impl NativePtr<RDKafkaEvent> {
     fn check_error(&self) -> KafkaResult<()> {
         let err = unsafe { rdsys::rd_kafka_event_error(self.ptr()) };
         if err.is_error() {
-            Err(KafkaError::AdminOp(err.into()))
+            let errstr = unsafe { cstr_to_owned(rdsys::rd_kafka_event_error_string(self.ptr())) };
+            Err(KafkaError::AdminOp(err.into(), errstr))
         } else {
             Ok(())
         }
@@ -584,7 +715,10 @@ fn check_rdkafka_invalid_arg(res: RDKafkaRespErr, err_buf: &ErrBuf) -> KafkaResu
 
 /// The result of an individual CreateTopic, DeleteTopic, or
 /// CreatePartition operation.
-pub type TopicResult = Result<String, (String, RDKafkaErrorCode)>;
+///
+/// On failure, the name of the topic comes with the error code and the
+/// detailed error string reported by librdkafka.
+pub type TopicResult = Result<String, (String, RDKafkaErrorCode, String)>;
 
 fn build_topic_results(topics: *const *const RDKafkaTopicResult, n: usize) -> Vec<TopicResult> {
     let mut out = Vec::with_capacity(n);
@@ -593,7 +727,14 @@ fn build_topic_results(topics: *const *const RDKafkaTopicResult, n: usize) -> Ve
         let name = unsafe { cstr_to_owned(rdsys::rd_kafka_topic_result_name(topic)) };
         let err = unsafe { rdsys::rd_kafka_topic_result_error(topic) };
         if err.is_error() {
-            out.push(Err((name, err.into())));
+            let code = err.into();
+            let errstr = unsafe { rdsys::rd_kafka_topic_result_error_string(topic) };
+            let errstr = if errstr.is_null() {
+                err2str(code)
+            } else {
+                unsafe { cstr_to_owned(errstr) }
+            };
+            out.push(Err((name, code, errstr)));
         } else {
             out.push(Ok(name));
         }
@@ -602,19 +743,21 @@ fn build_topic_results(topics: *const *const RDKafkaTopicResult, n: usize) -> Ve
 }
 
 /// The result of a DeleteGroup operation.
-pub type GroupResult = Result<String, (String, RDKafkaErrorCode)>;
+///
+/// On failure, the name of the group comes with the error code and the
+/// detailed error string reported by librdkafka.
+pub type GroupResult = Result<String, (String, RDKafkaErrorCode, String)>;
 
 fn build_group_results(groups: *const *const RDKafkaGroupResult, n: usize) -> Vec<GroupResult> {
     let mut out = Vec::with_capacity(n);
     for i in 0..n {
         let group = unsafe { *groups.add(i) };
         let name = unsafe { cstr_to_owned(rdsys::rd_kafka_group_result_name(group)) };
-        let err = unsafe {
-            let err = rdsys::rd_kafka_group_result_error(group);
-            rdsys::rd_kafka_error_code(err)
-        };
-        if err.is_error() {
-            out.push(Err((name, err.into())));
+        let err = unsafe { rdsys::rd_kafka_group_result_error(group) };
+        let code = unsafe { rdsys::rd_kafka_error_code(err) };
+        if code.is_error() {
+            let errstr = unsafe { cstr_to_owned(rdsys::rd_kafka_error_string(err)) };
+            out.push(Err((name, code.into(), errstr)));
         } else {
             out.push(Ok(name));
         }
@@ -622,6 +765,74 @@ fn build_group_results(groups: *const *const RDKafkaGroupResult, n: usize) -> Ve
     out
 }
 
+//
+// Describe cluster handling
+//
+
+/// The description of a cluster, as returned by
+/// [`AdminClient::describe_cluster`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClusterDescription {
+    /// The ID of the cluster, if the brokers report one.
+    pub cluster_id: Option<String>,
+    /// The ID of the controller broker, if known.
+    pub controller_id: Option<i32>,
+    /// The brokers of the cluster.
+    pub brokers: Vec<ClusterBroker>,
+}
+
+impl ClusterDescription {
+    /// Returns the controller broker, if known.
+    pub fn controller(&self) -> Option<&ClusterBroker> {
+        let controller_id = self.controller_id?;
+        self.brokers
+            .iter()
+            .find(|broker| broker.id == controller_id)
+    }
+}
+
+/// A broker of a [`ClusterDescription`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClusterBroker {
+    /// The ID of the broker.
+    pub id: i32,
+    /// The hostname of the broker.
+    pub host: String,
+    /// The port of the broker.
+    pub port: i32,
+}
+
+//
+// List offsets handling
+//
+
+/// The offset to look up with [`AdminClient::list_offsets`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OffsetSpec {
+    /// The earliest offset of the partition.
+    Earliest,
+    /// The latest offset of the partition, i.e. the offset of the next
+    /// message that will be produced to it.
+    Latest,
+    /// The earliest offset whose timestamp is greater than or equal to the
+    /// specified timestamp, in milliseconds since the Unix epoch. The
+    /// timestamp must not be negative.
+    Timestamp(i64),
+}
+
+impl OffsetSpec {
+    fn to_offset(self) -> KafkaResult<Offset> {
+        match self {
+            OffsetSpec::Earliest => Ok(Offset::Beginning),
+            OffsetSpec::Latest => Ok(Offset::End),
+            OffsetSpec::Timestamp(timestamp) if timestamp < 0 => {
+                Err(KafkaError::OffsetFetch(RDKafkaErrorCode::InvalidArgument))
+            }
+            OffsetSpec::Timestamp(timestamp) => Ok(Offset::Offset(timestamp)),
+        }
+    }
+}
+
 //
 // Create topic handling
 //
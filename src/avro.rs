@@ -0,0 +1,655 @@
+//! Confluent Schema Registry wire format, for Avro interoperability.
+//!
+//! Producers and consumers built on the Confluent serializers, such as Kafka
+//! Connect or Java applications using `KafkaAvroSerializer`, prefix every
+//! encoded key and payload with a [`MAGIC_BYTE`] and the 4-byte, big-endian
+//! ID under which the writer schema is stored in a schema registry. The
+//! [`encode_wire_format`] and [`decode_wire_format`] functions add and remove
+//! that prefix.
+//!
+//! The [`AvroSerializer`] and the [`AvroDeserializer`] plug into the
+//! [`TypedProducer`] and the [`TypedConsumer`]. They look schemas up in a
+//! [`SchemaRegistry`], and delegate the Avro encoding itself to an
+//! [`AvroCodec`]. The [`HttpSchemaRegistry`] is a client of the REST API of
+//! the Confluent Schema Registry, and the [`SerdeAvroCodec`] encodes the types
+//! that implement serde's traits with [`apache_avro`]. A
+//! [`MemorySchemaRegistry`] is provided for tests and for applications with a
+//! fixed set of schemas.
+//!
+//! ```no_run
+//! use serde::{Deserialize, Serialize};
+//!
+//! use rdkafka::avro::{topic_subject, AvroSerializer, HttpSchemaRegistry, SerdeAvroCodec};
+//! use rdkafka::serialization::Serializer;
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Order {
+//!     id: i64,
+//! }
+//!
+//! let codec = SerdeAvroCodec::<Order>::new(
+//!     r#"{"type": "record", "name": "Order", "fields": [{"name": "id", "type": "long"}]}"#,
+//! )
+//! .unwrap();
+//! let registry = HttpSchemaRegistry::new("http://localhost:8081");
+//! let serializer = AvroSerializer::new(registry, codec, topic_subject("orders", false));
+//! let payload = serializer.serialize(&Order { id: 42 }).unwrap();
+//! ```
+//!
+//! This module requires the `avro` feature.
+//!
+//! [`TypedConsumer`]: crate::consumer::TypedConsumer
+//! [`TypedProducer`]: crate::producer::TypedProducer
+
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use apache_avro::Schema;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{KafkaError, KafkaResult};
+use crate::serialization::{Deserializer, Serializer};
+
+/// The first byte of every key or payload in the wire format.
+pub const MAGIC_BYTE: u8 = 0;
+
+/// The length of the prefix added by the wire format.
+pub const WIRE_FORMAT_HEADER_LEN: usize = 5;
+
+/// Prefixes the encoded data with the magic byte and the schema ID.
+pub fn encode_wire_format(schema_id: u32, data: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(WIRE_FORMAT_HEADER_LEN + data.len());
+    bytes.push(MAGIC_BYTE);
+    bytes.extend_from_slice(&schema_id.to_be_bytes());
+    bytes.extend_from_slice(data);
+    bytes
+}
+
+/// Splits bytes in the wire format into the schema ID and the encoded data.
+///
+/// Returns a [`KafkaError::PayloadDecoding`] error if the bytes are too short
+/// or do not start with the magic byte.
+pub fn decode_wire_format(bytes: &[u8]) -> KafkaResult<(u32, &[u8])> {
+    if bytes.len() < WIRE_FORMAT_HEADER_LEN {
+        return Err(KafkaError::PayloadDecoding(format!(
+            "wire format requires at least {} bytes, got {}",
+            WIRE_FORMAT_HEADER_LEN,
+            bytes.len()
+        )));
+    }
+    if bytes[0] != MAGIC_BYTE {
+        return Err(KafkaError::PayloadDecoding(format!(
+            "unknown magic byte {}",
+            bytes[0]
+        )));
+    }
+    let schema_id = u32::from_be_bytes(bytes[1..WIRE_FORMAT_HEADER_LEN].try_into().unwrap());
+    Ok((schema_id, &bytes[WIRE_FORMAT_HEADER_LEN..]))
+}
+
+/// Returns the subject of the keys or values of a topic, following the
+/// default `TopicNameStrategy` of the Confluent serializers.
+pub fn topic_subject(topic: &str, is_key: bool) -> String {
+    format!("{}-{}", topic, if is_key { "key" } else { "value" })
+}
+
+/// A client of a schema registry.
+///
+/// Implementations are expected to cache their responses, since the
+/// [`AvroSerializer`] and the [`AvroDeserializer`] only cache the schemas
+/// they use themselves.
+pub trait SchemaRegistry: Send + Sync {
+    /// Registers the schema under the subject, if it is not registered yet,
+    /// and returns its ID.
+    fn register(&self, subject: &str, schema: &str) -> KafkaResult<u32>;
+
+    /// Returns the schema with the specified ID.
+    fn schema(&self, id: u32) -> KafkaResult<String>;
+}
+
+impl<R: SchemaRegistry + ?Sized> SchemaRegistry for Arc<R> {
+    fn register(&self, subject: &str, schema: &str) -> KafkaResult<u32> {
+        (**self).register(subject, schema)
+    }
+
+    fn schema(&self, id: u32) -> KafkaResult<String> {
+        (**self).schema(id)
+    }
+}
+
+/// A [`SchemaRegistry`] that stores the schemas in memory.
+///
+/// Schema IDs are assigned sequentially, starting at 1. A schema registered
+/// under several subjects has a single ID.
+#[derive(Debug, Default)]
+pub struct MemorySchemaRegistry {
+    schemas: Mutex<Vec<String>>,
+}
+
+impl MemorySchemaRegistry {
+    /// Creates a new, empty registry.
+    pub fn new() -> MemorySchemaRegistry {
+        MemorySchemaRegistry::default()
+    }
+}
+
+impl SchemaRegistry for MemorySchemaRegistry {
+    fn register(&self, _subject: &str, schema: &str) -> KafkaResult<u32> {
+        let mut schemas = self.schemas.lock().unwrap();
+        let index = match schemas.iter().position(|s| s == schema) {
+            Some(index) => index,
+            None => {
+                schemas.push(schema.to_owned());
+                schemas.len() - 1
+            }
+        };
+        Ok(index as u32 + 1)
+    }
+
+    fn schema(&self, id: u32) -> KafkaResult<String> {
+        let schemas = self.schemas.lock().unwrap();
+        (id as usize)
+            .checked_sub(1)
+            .and_then(|index| schemas.get(index))
+            .cloned()
+            .ok_or_else(|| KafkaError::PayloadDecoding(format!("unknown schema ID {}", id)))
+    }
+}
+
+/// The default timeout of the requests of the [`HttpSchemaRegistry`].
+const REGISTRY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The content type of the requests and responses of the schema registry.
+const REGISTRY_CONTENT_TYPE: &str = "application/vnd.schemaregistry.v1+json";
+
+#[derive(Serialize)]
+struct RegisterRequest<'a> {
+    schema: &'a str,
+}
+
+#[derive(Deserialize)]
+struct RegisterResponse {
+    id: u32,
+}
+
+#[derive(Deserialize)]
+struct SchemaResponse {
+    schema: String,
+}
+
+/// A [`SchemaRegistry`] backed by the REST API of a Confluent Schema Registry.
+///
+/// The IDs of registered schemas and the schemas fetched by ID are cached for
+/// the lifetime of the client. Requests are blocking, and time out after 10
+/// seconds by default.
+pub struct HttpSchemaRegistry {
+    url: String,
+    agent: ureq::Agent,
+    authorization: Option<String>,
+    ids: Mutex<HashMap<(String, String), u32>>,
+    schemas: Mutex<HashMap<u32, String>>,
+}
+
+impl HttpSchemaRegistry {
+    /// Creates a client of the schema registry at the specified URL, e.g.
+    /// `http://localhost:8081`.
+    pub fn new<S: Into<String>>(url: S) -> HttpSchemaRegistry {
+        let mut url = url.into();
+        while url.ends_with('/') {
+            url.pop();
+        }
+        HttpSchemaRegistry {
+            url,
+            agent: http_agent(REGISTRY_TIMEOUT),
+            authorization: None,
+            ids: Mutex::new(HashMap::new()),
+            schemas: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Authenticates the requests with HTTP basic authentication.
+    pub fn basic_auth(mut self, username: &str, password: &str) -> HttpSchemaRegistry {
+        let credentials = base64::encode(format!("{}:{}", username, password));
+        self.authorization = Some(format!("Basic {}", credentials));
+        self
+    }
+
+    /// Sets the timeout of the requests.
+    pub fn timeout(mut self, timeout: Duration) -> HttpSchemaRegistry {
+        self.agent = http_agent(timeout);
+        self
+    }
+
+    fn request(&self, method: &str, path: &str) -> ureq::Request {
+        let request = self
+            .agent
+            .request(method, &format!("{}{}", self.url, path))
+            .set("Accept", REGISTRY_CONTENT_TYPE);
+        match &self.authorization {
+            Some(authorization) => request.set("Authorization", authorization),
+            None => request,
+        }
+    }
+}
+
+impl SchemaRegistry for HttpSchemaRegistry {
+    fn register(&self, subject: &str, schema: &str) -> KafkaResult<u32> {
+        let key = (subject.to_owned(), schema.to_owned());
+        if let Some(&id) = self.ids.lock().unwrap().get(&key) {
+            return Ok(id);
+        }
+        let path = format!("/subjects/{}/versions", path_encode(subject));
+        let response: RegisterResponse = self
+            .request("POST", &path)
+            .set("Content-Type", REGISTRY_CONTENT_TYPE)
+            .send_json(RegisterRequest { schema })
+            .map_err(registry_error)
+            .and_then(|response| response.into_json().map_err(|e| e.to_string()))
+            .map_err(|e| {
+                KafkaError::PayloadEncoding(format!(
+                    "could not register schema under subject {}: {}",
+                    subject, e
+                ))
+            })?;
+        self.ids.lock().unwrap().insert(key, response.id);
+        Ok(response.id)
+    }
+
+    fn schema(&self, id: u32) -> KafkaResult<String> {
+        if let Some(schema) = self.schemas.lock().unwrap().get(&id) {
+            return Ok(schema.clone());
+        }
+        let response: SchemaResponse = self
+            .request("GET", &format!("/schemas/ids/{}", id))
+            .call()
+            .map_err(registry_error)
+            .and_then(|response| response.into_json().map_err(|e| e.to_string()))
+            .map_err(|e| {
+                KafkaError::PayloadDecoding(format!("could not fetch schema ID {}: {}", id, e))
+            })?;
+        self.schemas
+            .lock()
+            .unwrap()
+            .insert(id, response.schema.clone());
+        Ok(response.schema)
+    }
+}
+
+impl fmt::Debug for HttpSchemaRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HttpSchemaRegistry")
+            .field("url", &self.url)
+            .finish()
+    }
+}
+
+fn http_agent(timeout: Duration) -> ureq::Agent {
+    ureq::AgentBuilder::new()
+        .timeout(timeout)
+        .user_agent(concat!("rust-rdkafka/", env!("CARGO_PKG_VERSION")))
+        .build()
+}
+
+/// Describes a failed request, including the body of error responses, in
+/// which the registry explains what went wrong.
+fn registry_error(e: ureq::Error) -> String {
+    match e {
+        ureq::Error::Status(status, response) => {
+            format!("{}: {}", status, response.into_string().unwrap_or_default())
+        }
+        e => e.to_string(),
+    }
+}
+
+/// Percent-encodes everything but the unreserved characters, so that the
+/// subject can be used as a path segment.
+fn path_encode(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for &b in s.as_bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(b as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    encoded
+}
+
+/// Converts values of type `T` to and from Avro binary encoding.
+pub trait AvroCodec<T>: Send + Sync {
+    /// Returns the schema that values are encoded with.
+    fn schema(&self) -> &str;
+
+    /// Encodes the value with the schema returned by [`AvroCodec::schema`].
+    fn encode(&self, value: &T) -> KafkaResult<Vec<u8>>;
+
+    /// Decodes data that was encoded with the writer schema, resolving it
+    /// against the schema returned by [`AvroCodec::schema`].
+    fn decode(&self, writer_schema: &str, data: &[u8]) -> KafkaResult<T>;
+}
+
+/// An [`AvroCodec`] for the types that implement serde's `Serialize` and
+/// `DeserializeOwned` traits, based on [`apache_avro`].
+///
+/// Values are converted to Avro values with [`apache_avro::to_value`] and
+/// resolved against the schema of the codec, which is also used as the reader
+/// schema when decoding. Writer schemas are parsed the first time they are
+/// seen, and cached afterwards.
+pub struct SerdeAvroCodec<T> {
+    schema_str: String,
+    schema: Schema,
+    writer_schemas: Mutex<HashMap<String, Arc<Schema>>>,
+    _type: PhantomData<fn(&T) -> T>,
+}
+
+impl<T> SerdeAvroCodec<T> {
+    /// Creates a codec that encodes values with the specified schema, given
+    /// in its JSON form.
+    ///
+    /// Returns a [`KafkaError::PayloadEncoding`] error if the schema is
+    /// invalid.
+    pub fn new<S: Into<String>>(schema: S) -> KafkaResult<SerdeAvroCodec<T>> {
+        let schema_str = schema.into();
+        let schema = Schema::parse_str(&schema_str)
+            .map_err(|e| KafkaError::PayloadEncoding(format!("invalid Avro schema: {}", e)))?;
+        Ok(SerdeAvroCodec {
+            schema_str,
+            schema,
+            writer_schemas: Mutex::new(HashMap::new()),
+            _type: PhantomData,
+        })
+    }
+
+    fn writer_schema(&self, writer_schema: &str) -> KafkaResult<Arc<Schema>> {
+        let mut writer_schemas = self.writer_schemas.lock().unwrap();
+        if let Some(schema) = writer_schemas.get(writer_schema) {
+            return Ok(schema.clone());
+        }
+        let schema = Schema::parse_str(writer_schema).map_err(|e| {
+            KafkaError::PayloadDecoding(format!("invalid Avro writer schema: {}", e))
+        })?;
+        let schema = Arc::new(schema);
+        writer_schemas.insert(writer_schema.to_owned(), schema.clone());
+        Ok(schema)
+    }
+}
+
+impl<T> AvroCodec<T> for SerdeAvroCodec<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    fn schema(&self) -> &str {
+        &self.schema_str
+    }
+
+    fn encode(&self, value: &T) -> KafkaResult<Vec<u8>> {
+        apache_avro::to_value(value)
+            .and_then(|value| value.resolve(&self.schema))
+            .and_then(|value| apache_avro::to_avro_datum(&self.schema, value))
+            .map_err(|e| KafkaError::PayloadEncoding(e.to_string()))
+    }
+
+    fn decode(&self, writer_schema: &str, mut data: &[u8]) -> KafkaResult<T> {
+        let value = if writer_schema == self.schema_str {
+            apache_avro::from_avro_datum(&self.schema, &mut data, None)
+        } else {
+            let writer_schema = self.writer_schema(writer_schema)?;
+            apache_avro::from_avro_datum(&writer_schema, &mut data, Some(&self.schema))
+        };
+        value
+            .and_then(|value| apache_avro::from_value(&value))
+            .map_err(|e| KafkaError::PayloadDecoding(e.to_string()))
+    }
+}
+
+impl<T> fmt::Debug for SerdeAvroCodec<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SerdeAvroCodec")
+            .field("schema", &self.schema_str)
+            .finish()
+    }
+}
+
+/// A [`Serializer`] that encodes values with an [`AvroCodec`] in the wire
+/// format.
+///
+/// The schema of the codec is registered under the subject the first time a
+/// value is serialized, and its ID is cached afterwards.
+pub struct AvroSerializer<T, R, A> {
+    registry: R,
+    codec: A,
+    subject: String,
+    schema_id: Mutex<Option<u32>>,
+    _type: PhantomData<fn(&T)>,
+}
+
+impl<T, R, A> AvroSerializer<T, R, A>
+where
+    R: SchemaRegistry,
+    A: AvroCodec<T>,
+{
+    /// Creates a serializer that registers the schema of the codec under the
+    /// specified subject. See [`topic_subject`] for the subject naming
+    /// convention of the Confluent serializers.
+    pub fn new<S: Into<String>>(registry: R, codec: A, subject: S) -> AvroSerializer<T, R, A> {
+        AvroSerializer {
+            registry,
+            codec,
+            subject: subject.into(),
+            schema_id: Mutex::new(None),
+            _type: PhantomData,
+        }
+    }
+
+    fn schema_id(&self) -> KafkaResult<u32> {
+        let mut schema_id = self.schema_id.lock().unwrap();
+        if let Some(id) = *schema_id {
+            return Ok(id);
+        }
+        let id = self
+            .registry
+            .register(&self.subject, self.codec.schema())
+            .map_err(|e| {
+                KafkaError::PayloadEncoding(format!(
+                    "could not register schema for subject {}: {}",
+                    self.subject, e
+                ))
+            })?;
+        *schema_id = Some(id);
+        Ok(id)
+    }
+}
+
+impl<T, R, A> Serializer<T> for AvroSerializer<T, R, A>
+where
+    R: SchemaRegistry,
+    A: AvroCodec<T>,
+{
+    fn serialize(&self, value: &T) -> KafkaResult<Vec<u8>> {
+        let schema_id = self.schema_id()?;
+        let data = self.codec.encode(value)?;
+        Ok(encode_wire_format(schema_id, &data))
+    }
+}
+
+/// A [`Deserializer`] that decodes values from the wire format with an
+/// [`AvroCodec`].
+///
+/// The writer schema of each value is fetched from the registry by ID the
+/// first time it is seen, and cached afterwards.
+pub struct AvroDeserializer<T, R, A> {
+    registry: R,
+    codec: A,
+    schemas: Mutex<HashMap<u32, Arc<str>>>,
+    _type: PhantomData<fn() -> T>,
+}
+
+impl<T, R, A> AvroDeserializer<T, R, A>
+where
+    R: SchemaRegistry,
+    A: AvroCodec<T>,
+{
+    /// Creates a deserializer that fetches writer schemas from the registry.
+    pub fn new(registry: R, codec: A) -> AvroDeserializer<T, R, A> {
+        AvroDeserializer {
+            registry,
+            codec,
+            schemas: Mutex::new(HashMap::new()),
+            _type: PhantomData,
+        }
+    }
+
+    fn writer_schema(&self, id: u32) -> KafkaResult<Arc<str>> {
+        let mut schemas = self.schemas.lock().unwrap();
+        if let Some(schema) = schemas.get(&id) {
+            return Ok(schema.clone());
+        }
+        let schema: Arc<str> = Arc::from(self.registry.schema(id)?);
+        schemas.insert(id, schema.clone());
+        Ok(schema)
+    }
+}
+
+impl<T, R, A> Deserializer<T> for AvroDeserializer<T, R, A>
+where
+    R: SchemaRegistry,
+    A: AvroCodec<T>,
+{
+    fn deserialize(&self, bytes: &[u8]) -> KafkaResult<T> {
+        let (schema_id, data) = decode_wire_format(bytes)?;
+        let writer_schema = self.writer_schema(schema_id)?;
+        self.codec.decode(&writer_schema, data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encodes strings as Avro `string`s: a zig-zag varint length followed
+    /// by the UTF-8 bytes. Only lengths below 64 are supported.
+    struct StringCodec;
+
+    impl AvroCodec<String> for StringCodec {
+        fn schema(&self) -> &str {
+            r#""string""#
+        }
+
+        fn encode(&self, value: &String) -> KafkaResult<Vec<u8>> {
+            let mut data = vec![(value.len() as u8) << 1];
+            data.extend_from_slice(value.as_bytes());
+            Ok(data)
+        }
+
+        fn decode(&self, writer_schema: &str, data: &[u8]) -> KafkaResult<String> {
+            assert_eq!(writer_schema, self.schema());
+            String::from_utf8(data[1..].to_vec())
+                .map_err(|e| KafkaError::PayloadDecoding(e.to_string()))
+        }
+    }
+
+    #[test]
+    fn test_wire_format() {
+        let bytes = encode_wire_format(258, b"data");
+        assert_eq!(bytes, b"\x00\x00\x00\x01\x02data");
+        assert_eq!(decode_wire_format(&bytes).unwrap(), (258, &b"data"[..]));
+        assert!(matches!(
+            decode_wire_format(b"\x00\x00"),
+            Err(KafkaError::PayloadDecoding(_))
+        ));
+        assert!(matches!(
+            decode_wire_format(b"\x01\x00\x00\x00\x01"),
+            Err(KafkaError::PayloadDecoding(_))
+        ));
+        assert_eq!(topic_subject("orders", true), "orders-key");
+        assert_eq!(topic_subject("orders", false), "orders-value");
+    }
+
+    #[test]
+    fn test_avro_serialization() {
+        let registry = Arc::new(MemorySchemaRegistry::new());
+        registry.register("other-value", r#""long""#).unwrap();
+
+        let serializer = AvroSerializer::new(registry.clone(), StringCodec, "orders-value");
+        let bytes = serializer.serialize(&"hi".to_string()).unwrap();
+        assert_eq!(bytes, b"\x00\x00\x00\x00\x02\x04hi");
+
+        let deserializer = AvroDeserializer::new(registry, StringCodec);
+        assert_eq!(deserializer.deserialize(&bytes).unwrap(), "hi");
+        assert!(matches!(
+            deserializer.deserialize(b"\x00\x00\x00\x00\x09\x04hi"),
+            Err(KafkaError::PayloadDecoding(_))
+        ));
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Order {
+        id: i64,
+        note: Option<String>,
+    }
+
+    #[test]
+    fn test_serde_avro_codec() {
+        let schema = r#"{
+            "type": "record",
+            "name": "Order",
+            "fields": [
+                {"name": "id", "type": "long"},
+                {"name": "note", "type": ["null", "string"], "default": null}
+            ]
+        }"#;
+        let codec = SerdeAvroCodec::<Order>::new(schema).unwrap();
+        let order = Order {
+            id: 42,
+            note: Some("hi".into()),
+        };
+        let data = codec.encode(&order).unwrap();
+        assert_eq!(data, b"\x54\x02\x04hi");
+        assert_eq!(codec.decode(schema, &data).unwrap(), order);
+
+        // Data written with an older schema is resolved against the schema of
+        // the codec.
+        let old_schema = r#"{
+            "type": "record",
+            "name": "Order",
+            "fields": [{"name": "id", "type": "long"}]
+        }"#;
+        assert_eq!(
+            codec.decode(old_schema, b"\x54").unwrap(),
+            Order { id: 42, note: None }
+        );
+        assert!(matches!(
+            codec.decode("{", b"\x54"),
+            Err(KafkaError::PayloadDecoding(_))
+        ));
+        assert!(matches!(
+            SerdeAvroCodec::<Order>::new(r#"{"type": "unknown"}"#),
+            Err(KafkaError::PayloadEncoding(_))
+        ));
+
+        let registry = Arc::new(MemorySchemaRegistry::new());
+        let serializer = AvroSerializer::new(registry.clone(), codec, "orders-value");
+        let bytes = serializer.serialize(&order).unwrap();
+        let deserializer =
+            AvroDeserializer::new(registry, SerdeAvroCodec::<Order>::new(schema).unwrap());
+        assert_eq!(deserializer.deserialize(&bytes).unwrap(), order);
+    }
+
+    #[test]
+    fn test_http_schema_registry() {
+        let registry = HttpSchemaRegistry::new("http://localhost:8081/").basic_auth("user", "pass");
+        assert_eq!(registry.url, "http://localhost:8081");
+        assert_eq!(
+            registry.authorization.as_deref(),
+            Some("Basic dXNlcjpwYXNz")
+        );
+        assert_eq!(path_encode("orders-value"), "orders-value");
+        assert_eq!(path_encode("a/b c"), "a%2Fb%20c");
+    }
+}
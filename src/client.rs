@@ -11,27 +11,33 @@
 //! [`consumer`]: crate::consumer
 //! [`producer`]: crate::producer
 
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::error::Error;
 use std::ffi::{CStr, CString};
+use std::io::{self, Write};
 use std::mem::ManuallyDrop;
-use std::os::raw::{c_char, c_void};
+use std::os::raw::{c_char, c_int, c_void};
+use std::panic::{self, AssertUnwindSafe};
 use std::ptr;
 use std::slice;
 use std::string::ToString;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
 
 use rdkafka_sys as rdsys;
 use rdkafka_sys::types::*;
 
-use crate::config::{ClientConfig, NativeClientConfig, RDKafkaLogLevel};
+use crate::config::{dump_native_config, ClientConfig, NativeClientConfig, RDKafkaLogLevel};
 use crate::consumer::RebalanceProtocol;
 use crate::error::{IsError, KafkaError, KafkaResult};
 use crate::groups::GroupList;
+use crate::interceptor::Interceptor;
 use crate::log::{debug, error, info, trace, warn};
 use crate::metadata::Metadata;
-use crate::statistics::Statistics;
-use crate::util::{self, ErrBuf, KafkaDrop, NativePtr, Timeout};
+use crate::producer::base_producer::ProducerState;
+use crate::statistics::{BrokerState, BrokerStates, Statistics};
+use crate::util::{self, CallbackThread, ErrBuf, KafkaDrop, NativePtr, Timeout};
 
 /// Client-level context.
 ///
@@ -57,6 +63,34 @@ pub trait ClientContext: Send + Sync {
     /// mechanism.
     const ENABLE_REFRESH_OAUTH_TOKEN: bool = false;
 
+    /// Whether to verify the certificates of the brokers by calling
+    /// [`ClientContext::verify_ssl_cert`].
+    ///
+    /// If disabled, the certificates are verified by OpenSSL only.
+    ///
+    /// This parameter is only relevant when connecting to brokers over TLS.
+    const ENABLE_SSL_CERT_VERIFY: bool = false;
+
+    /// Whether [`ClientContext::verify_ssl_cert`] may accept certificates
+    /// that OpenSSL rejected.
+    ///
+    /// If disabled, a certificate is only accepted if both OpenSSL and
+    /// `verify_ssl_cert` accept it, so the method can only add checks. If
+    /// enabled, the verdict of `verify_ssl_cert` replaces the one of OpenSSL,
+    /// which is required to trust certificates that OpenSSL cannot verify,
+    /// e.g. self-signed certificates that are pinned by the application.
+    /// Such implementations must then perform all the checks themselves,
+    /// including checking [`SslCertVerification::x509_error`].
+    const ENABLE_SSL_CERT_OVERRIDE: bool = false;
+
+    /// Whether to extract the broker states from the statistics emitted by
+    /// the client, for [`Client::broker_states`].
+    ///
+    /// If disabled, the statistics are only passed to
+    /// [`ClientContext::stats_raw`], and [`Client::broker_states`] always
+    /// returns an empty vector.
+    const ENABLE_BROKER_STATES: bool = false;
+
     /// Receives log lines from librdkafka.
     ///
     /// The default implementation forwards the log lines to the appropriate
@@ -133,6 +167,65 @@ pub trait ClientContext: Send + Sync {
         Err("Default implementation of generate_oauth_token must be overridden".into())
     }
 
+    /// Verifies a certificate presented by a broker.
+    ///
+    /// The method is called for every certificate in the chain presented by a
+    /// broker, from the root certificate to the certificate of the broker
+    /// itself, and decides whether the certificate is accepted. This allows
+    /// implementing certificate pinning or SPIFFE-based verification. For
+    /// this method to be called, you must also set
+    /// [`ClientContext::ENABLE_SSL_CERT_VERIFY`] to true. The method is
+    /// called from librdkafka's internal threads, and must not block.
+    ///
+    /// Returning an error rejects the certificate, which fails the
+    /// connection to the broker with the returned message. Returning `Ok`
+    /// accepts the certificate if OpenSSL accepted it too, i.e. if its
+    /// [`x509_error`](SslCertVerification::x509_error) is `0`. Certificates
+    /// that OpenSSL rejected are only accepted if
+    /// [`ClientContext::ENABLE_SSL_CERT_OVERRIDE`] is set, in which case
+    /// returning `Ok` clears their X.509 error.
+    ///
+    /// The default implementation accepts the certificates that OpenSSL
+    /// accepted.
+    fn verify_ssl_cert(&self, cert: &SslCertVerification<'_>) -> Result<(), String> {
+        match cert.x509_error {
+            0 => Ok(()),
+            e => Err(format!(
+                "certificate verification failed with X.509 error {}",
+                e
+            )),
+        }
+    }
+
+    /// Returns the configuration of the thread that the crate spawns to poll
+    /// this client, if any, or `None` to use the default configuration.
+    ///
+    /// The callbacks of the client, e.g. delivery reports, statistics and
+    /// rebalances, are served on the thread that polls it. This applies to
+    /// the polling threads of the [`ThreadedProducer`], the [`FutureProducer`]
+    /// and the [`AdminClient`], and to the shard threads of the
+    /// [`ShardedConsumer`]. Other consumers serve their callbacks on the
+    /// threads that call their poll methods. By default, the threads are
+    /// named after their role, e.g. "producer polling thread", and can run on
+    /// any CPU.
+    ///
+    /// [`AdminClient`]: crate::admin::AdminClient
+    /// [`FutureProducer`]: crate::producer::FutureProducer
+    /// [`ShardedConsumer`]: crate::consumer::ShardedConsumer
+    /// [`ThreadedProducer`]: crate::producer::ThreadedProducer
+    fn polling_thread(&self) -> Option<CallbackThread> {
+        None
+    }
+
+    /// Returns the interceptors that observe the messages sent and received
+    /// by the client, in the order in which they are invoked.
+    ///
+    /// See the [`interceptor`](crate::interceptor) module for details. The
+    /// default implementation returns no interceptors.
+    fn interceptors(&self) -> &[Arc<dyn Interceptor>] {
+        &[]
+    }
+
     // NOTE: when adding a new method, remember to add it to the
     // FutureProducerContext as well.
     // https://github.com/rust-lang/rfcs/pull/1406 will maybe help in the
@@ -181,6 +274,20 @@ impl NativeClient {
         self.ptr.ptr()
     }
 
+    /// Reports whether the current assignment of the consumer was lost, e.g.
+    /// because its session with the group coordinator timed out, rather than
+    /// revoked cooperatively by a rebalance.
+    ///
+    /// Partitions that were lost may already be assigned to another member of
+    /// the group, so their offsets should not be committed. This is meant to
+    /// be called from [`ConsumerContext::rebalance`] when partitions are
+    /// revoked.
+    ///
+    /// [`ConsumerContext::rebalance`]: crate::consumer::ConsumerContext::rebalance
+    pub fn assignment_lost(&self) -> bool {
+        unsafe { rdsys::rd_kafka_assignment_lost(self.ptr()) != 0 }
+    }
+
     pub(crate) fn rebalance_protocol(&self) -> RebalanceProtocol {
         let protocol = unsafe { rdsys::rd_kafka_rebalance_protocol(self.ptr()) };
         if protocol.is_null() {
@@ -209,8 +316,31 @@ impl NativeClient {
 /// [`consumer`]: crate::consumer
 /// [`producer`]: crate::producer
 pub struct Client<C: ClientContext = DefaultClientContext> {
+    // The native client must be destroyed before the state that its callbacks
+    // refer to, so this field must come first.
     native: NativeClient,
-    context: Arc<C>,
+    opaque: Box<ClientOpaque<C>>,
+}
+
+/// The state that a client shares with the callbacks of its native client.
+///
+/// The address of the state is registered as the opaque of the native client,
+/// and is passed to every callback.
+pub(crate) struct ClientOpaque<C> {
+    pub(crate) context: Arc<C>,
+    /// The state of the producer, if the client is a producer.
+    pub(crate) producer: Option<Arc<ProducerState>>,
+    /// The broker states reported by the latest statistics, if
+    /// [`ClientContext::ENABLE_BROKER_STATES`] is set.
+    broker_states: Mutex<Vec<BrokerState>>,
+}
+
+impl<C> ClientOpaque<C> {
+    /// Returns the state whose address was registered as the opaque of a
+    /// native client.
+    pub(crate) unsafe fn from_ptr<'a>(opaque: *mut c_void) -> &'a ClientOpaque<C> {
+        &*(opaque as *const ClientOpaque<C>)
+    }
 }
 
 impl<C: ClientContext> Client<C> {
@@ -220,13 +350,37 @@ impl<C: ClientContext> Client<C> {
         native_config: NativeClientConfig,
         rd_kafka_type: RDKafkaType,
         context: C,
+    ) -> KafkaResult<Client<C>> {
+        Client::new_context_arc(
+            config,
+            native_config,
+            rd_kafka_type,
+            Arc::new(context),
+            None,
+        )
+    }
+
+    /// Creates a new `Client` given a configuration, a client type and a
+    /// shared context, for callers that need to register the context with the
+    /// native configuration before the client is created, and the state of
+    /// the producer if the client is a producer.
+    pub(crate) fn new_context_arc(
+        config: &ClientConfig,
+        native_config: NativeClientConfig,
+        rd_kafka_type: RDKafkaType,
+        context: Arc<C>,
+        producer: Option<Arc<ProducerState>>,
     ) -> KafkaResult<Client<C>> {
         let mut err_buf = ErrBuf::new();
-        let context = Arc::new(context);
+        let opaque = Box::new(ClientOpaque {
+            context,
+            producer,
+            broker_states: Mutex::new(Vec::new()),
+        });
         unsafe {
             rdsys::rd_kafka_conf_set_opaque(
                 native_config.ptr(),
-                Arc::as_ptr(&context) as *mut c_void,
+                &*opaque as *const ClientOpaque<C> as *mut c_void,
             )
         };
         unsafe { rdsys::rd_kafka_conf_set_log_cb(native_config.ptr(), Some(native_log_cb::<C>)) };
@@ -244,6 +398,19 @@ impl<C: ClientContext> Client<C> {
                 )
             };
         }
+        if C::ENABLE_SSL_CERT_VERIFY {
+            let ret = unsafe {
+                rdsys::rd_kafka_conf_set_ssl_cert_verify_cb(
+                    native_config.ptr(),
+                    Some(native_ssl_cert_verify_cb::<C>),
+                )
+            };
+            if ret.is_error() {
+                return Err(KafkaError::ClientCreation(
+                    "SSL certificate verification is not supported by librdkafka".into(),
+                ));
+            }
+        }
 
         let client_ptr = unsafe {
             let native_config = ManuallyDrop::new(native_config);
@@ -264,7 +431,7 @@ impl<C: ClientContext> Client<C> {
 
         Ok(Client {
             native: unsafe { NativeClient::from_ptr(client_ptr) },
-            context,
+            opaque,
         })
     }
 
@@ -280,7 +447,15 @@ impl<C: ClientContext> Client<C> {
 
     /// Returns a reference to the context.
     pub fn context(&self) -> &Arc<C> {
-        &self.context
+        &self.opaque.context
+    }
+
+    /// Returns the effective configuration of the client, including
+    /// librdkafka's default values for the parameters that were not set.
+    ///
+    /// See [`ClientConfig::config_dump`].
+    pub fn config_dump(&self) -> HashMap<String, String> {
+        unsafe { dump_native_config(rdsys::rd_kafka_conf(self.native_ptr()) as *mut _) }
     }
 
     /// Returns the metadata information for the specified topic, or for all topics in the cluster
@@ -290,12 +465,31 @@ impl<C: ClientContext> Client<C> {
         topic: Option<&str>,
         timeout: T,
     ) -> KafkaResult<Metadata> {
-        let mut metadata_ptr: *const RDKafkaMetadata = ptr::null_mut();
-        let (flag, native_topic) = if let Some(topic_name) = topic {
-            (0, Some(self.native_topic(topic_name)?))
+        let (all_topics, native_topic) = if let Some(topic_name) = topic {
+            (false, Some(self.native_topic(topic_name)?))
         } else {
-            (1, None)
+            (true, None)
         };
+        self.fetch_native_metadata(all_topics, native_topic, timeout)
+    }
+
+    /// Fetches the metadata of the brokers in the cluster, and of the topics
+    /// the client already knows about.
+    pub(crate) fn fetch_broker_metadata<T: Into<Timeout>>(
+        &self,
+        timeout: T,
+    ) -> KafkaResult<Metadata> {
+        self.fetch_native_metadata(false, None, timeout)
+    }
+
+    fn fetch_native_metadata<T: Into<Timeout>>(
+        &self,
+        all_topics: bool,
+        native_topic: Option<NativeTopic>,
+        timeout: T,
+    ) -> KafkaResult<Metadata> {
+        let mut metadata_ptr: *const RDKafkaMetadata = ptr::null_mut();
+        let flag = if all_topics { 1 } else { 0 };
         trace!("Starting metadata fetch");
         let ret = unsafe {
             rdsys::rd_kafka_metadata(
@@ -351,6 +545,18 @@ impl<C: ClientContext> Client<C> {
         String::from_utf8(buf.to_vec()).ok()
     }
 
+    /// Returns the ID of the current controller broker, or `None` if it could
+    /// not be retrieved within the timeout.
+    pub fn fetch_controller_id<T: Into<Timeout>>(&self, timeout: T) -> Option<i32> {
+        let controller_id =
+            unsafe { rdsys::rd_kafka_controllerid(self.native_ptr(), timeout.into().as_millis()) };
+        if controller_id < 0 {
+            None
+        } else {
+            Some(controller_id)
+        }
+    }
+
     /// Returns the group membership information for the given group. If no group is
     /// specified, all groups will be returned.
     pub fn fetch_group_list<T: Into<Timeout>>(
@@ -400,6 +606,90 @@ impl<C: ClientContext> Client<C> {
         }
     }
 
+    /// Writes librdkafka's dump of the internal state of the client, such as
+    /// its brokers, topics, partitions and queues, to the writer.
+    ///
+    /// The dump is meant for humans debugging a stuck client, e.g. a producer
+    /// whose messages are never delivered; its format is not stable. It is
+    /// written to a temporary file first, which is then copied to the writer.
+    pub fn dump<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let file = unsafe { libc::tmpfile() };
+        if file.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+        let result = unsafe { copy_dump(self.native_ptr(), file, writer) };
+        unsafe { libc::fclose(file) };
+        result
+    }
+
+    /// Returns the connection state of every broker known to the client,
+    /// sorted by name.
+    ///
+    /// The states are taken from the latest statistics emitted by the client,
+    /// so they are only available when `statistics.interval.ms` is set, and
+    /// are as old as the statistics interval. Together with
+    /// [`BrokerState::stateage`] and the connection counters, they show which
+    /// brokers the client is failing to reach, while errors like
+    /// [`RDKafkaErrorCode::AllBrokersDown`] only describe the cluster as a
+    /// whole.
+    ///
+    /// The states are only extracted from the statistics when
+    /// [`ClientContext::ENABLE_BROKER_STATES`] is set. Returns an empty vector
+    /// otherwise, or if no statistics were emitted yet.
+    pub fn broker_states(&self) -> Vec<BrokerState> {
+        self.opaque.broker_states.lock().unwrap().clone()
+    }
+
+    /// Adds brokers to the bootstrap list of the client, and returns the
+    /// number of brokers that were added.
+    ///
+    /// The `brokers` are a comma-separated list, in the format of the
+    /// `bootstrap.servers` property. The client connects to the new brokers
+    /// in the background, so this is useful when brokers are discovered after
+    /// the client is created. Brokers that cannot be parsed are not counted,
+    /// while brokers that were already added are counted without being added
+    /// again.
+    pub fn add_brokers(&self, brokers: &str) -> KafkaResult<usize> {
+        let brokers_c = CString::new(brokers)?;
+        let added = unsafe { rdsys::rd_kafka_brokers_add(self.native_ptr(), brokers_c.as_ptr()) };
+        Ok(added.max(0) as usize)
+    }
+
+    /// Sets the SASL `OAUTHBEARER` token and its metadata.
+    ///
+    /// When [`ClientContext::ENABLE_REFRESH_OAUTH_TOKEN`] is set, this is done
+    /// automatically with the token returned by
+    /// [`ClientContext::generate_oauth_token`]. This method allows setting a
+    /// token that was obtained by other means, e.g. by a background task.
+    pub fn oauthbearer_set_token(&self, token: &OAuthToken) -> KafkaResult<()> {
+        let token_c = CString::new(token.token.as_str())?;
+        let principal_name_c = CString::new(token.principal_name.as_str())?;
+        unsafe {
+            set_oauthbearer_token(
+                self.native_ptr(),
+                &token_c,
+                &principal_name_c,
+                token.lifetime_ms,
+            )
+        }
+    }
+
+    /// Signals that the SASL `OAUTHBEARER` token could not be refreshed.
+    ///
+    /// The `reason` is logged by librdkafka, which will retry the refresh
+    /// after a short backoff.
+    pub fn oauthbearer_set_token_failure(&self, reason: &str) -> KafkaResult<()> {
+        let reason_c = CString::new(reason)?;
+        let code = unsafe {
+            rdsys::rd_kafka_oauthbearer_set_token_failure(self.native_ptr(), reason_c.as_ptr())
+        };
+        if code.is_error() {
+            let error = unsafe { util::cstr_to_owned(rdsys::rd_kafka_err2str(code)) };
+            return Err(KafkaError::OAuthToken(error));
+        }
+        Ok(())
+    }
+
     /// Returns a NativeTopic from the current client. The NativeTopic shouldn't outlive the client
     /// it was generated from.
     pub(crate) fn native_topic(&self, topic: &str) -> KafkaResult<NativeTopic> {
@@ -452,6 +742,47 @@ impl NativeQueue {
     }
 }
 
+/// The number of callback events that were dropped instead of being handled.
+static DROPPED_CALLBACK_EVENTS: AtomicU64 = AtomicU64::new(0);
+
+/// Returns the number of callback events that were dropped because they could
+/// not be handled safely, across all the clients of the process.
+///
+/// librdkafka may invoke the log, statistics, error and delivery callbacks
+/// while the process is exiting, when thread-local storage and other runtime
+/// facilities used by the callbacks may already be torn down. Rather than
+/// letting the resulting panics unwind into librdkafka, which would abort the
+/// process, such events are dropped and counted here. Panics in the callbacks
+/// of a client context are handled, and counted, in the same way, and are
+/// logged with their message at the error level.
+pub fn dropped_callback_events() -> u64 {
+    DROPPED_CALLBACK_EVENTS.load(Ordering::Relaxed)
+}
+
+/// Records a callback event that was dropped instead of being handled.
+pub(crate) fn record_dropped_callback_event() {
+    DROPPED_CALLBACK_EVENTS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Runs the handler of a callback event invoked by librdkafka, dropping the
+/// event if the handler panics.
+pub(crate) fn handle_callback_event<F: FnOnce()>(handler: F) {
+    if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(handler)) {
+        record_dropped_callback_event();
+        let message = match payload.downcast_ref::<&str>() {
+            Some(message) => *message,
+            None => match payload.downcast_ref::<String>() {
+                Some(message) => message.as_str(),
+                None => "Box<dyn Any>",
+            },
+        };
+        // Logging may panic too while the process is exiting.
+        let _ = panic::catch_unwind(|| {
+            error!("Callback panicked, dropping the event: {}", message);
+        });
+    }
+}
+
 pub(crate) unsafe extern "C" fn native_log_cb<C: ClientContext>(
     client: *const RDKafka,
     level: i32,
@@ -461,37 +792,151 @@ pub(crate) unsafe extern "C" fn native_log_cb<C: ClientContext>(
     let fac = CStr::from_ptr(fac).to_string_lossy();
     let log_message = CStr::from_ptr(buf).to_string_lossy();
 
-    let context = &mut *(rdsys::rd_kafka_opaque(client) as *mut C);
-    context.log(
-        RDKafkaLogLevel::from_int(level),
-        fac.trim(),
-        log_message.trim(),
-    );
+    let context = &ClientOpaque::<C>::from_ptr(rdsys::rd_kafka_opaque(client)).context;
+    handle_callback_event(|| {
+        context.log(
+            RDKafkaLogLevel::from_int(level),
+            fac.trim(),
+            log_message.trim(),
+        )
+    });
 }
 
 pub(crate) unsafe extern "C" fn native_stats_cb<C: ClientContext>(
-    _conf: *mut RDKafka,
+    _client: *mut RDKafka,
     json: *mut c_char,
     json_len: usize,
     opaque: *mut c_void,
 ) -> i32 {
-    let context = &mut *(opaque as *mut C);
-    context.stats_raw(slice::from_raw_parts(json as *mut u8, json_len));
+    let json = slice::from_raw_parts(json as *mut u8, json_len);
+    let client_opaque = ClientOpaque::<C>::from_ptr(opaque);
+    if C::ENABLE_BROKER_STATES {
+        match serde_json::from_slice::<BrokerStates>(json) {
+            Ok(states) => {
+                *client_opaque
+                    .broker_states
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = states.to_sorted_vec();
+            }
+            Err(e) => warn!("Could not parse broker states from statistics JSON: {}", e),
+        }
+    }
+    let context = &client_opaque.context;
+    handle_callback_event(|| context.stats_raw(json));
     0 // librdkafka will free the json buffer
 }
 
+unsafe fn copy_dump<W: Write>(
+    client: *mut RDKafka,
+    file: *mut libc::FILE,
+    writer: &mut W,
+) -> io::Result<()> {
+    rdsys::rd_kafka_dump(file, client);
+    if libc::fflush(file) != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    libc::rewind(file);
+    let mut buf = [0; 8192];
+    loop {
+        let n = libc::fread(buf.as_mut_ptr() as *mut c_void, 1, buf.len(), file);
+        if n == 0 {
+            break;
+        }
+        writer.write_all(&buf[..n])?;
+    }
+    if libc::ferror(file) != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
 pub(crate) unsafe extern "C" fn native_error_cb<C: ClientContext>(
-    _client: *mut RDKafka,
+    client: *mut RDKafka,
     err: i32,
     reason: *const c_char,
     opaque: *mut c_void,
 ) {
     let err = RDKafkaRespErr::try_from(err).expect("global error not an rd_kafka_resp_err_t");
-    let error = KafkaError::Global(err.into());
     let reason = CStr::from_ptr(reason).to_string_lossy();
+    let error = match err {
+        RDKafkaRespErr::RD_KAFKA_RESP_ERR__FATAL => {
+            // Fencing is the expected outcome of a static member being
+            // replaced, so it is reported with a dedicated error.
+            let mut err_buf = ErrBuf::new();
+            let code =
+                rdsys::rd_kafka_fatal_error(client, err_buf.as_mut_ptr(), err_buf.capacity());
+            if code == RDKafkaRespErr::RD_KAFKA_RESP_ERR_FENCED_INSTANCE_ID {
+                KafkaError::InstanceFenced(err_buf.to_string())
+            } else {
+                KafkaError::Global(err.into())
+            }
+        }
+        _ => KafkaError::Global(err.into()),
+    };
+
+    let context = &ClientOpaque::<C>::from_ptr(opaque).context;
+    handle_callback_event(|| context.error(error, reason.trim()));
+}
+
+/// A certificate presented by a broker, as passed to
+/// [`ClientContext::verify_ssl_cert`].
+#[derive(Debug)]
+pub struct SslCertVerification<'a> {
+    /// The name of the broker, as `host:port`.
+    pub broker_name: &'a str,
+    /// The ID of the broker, or `-1` if it is not known yet.
+    pub broker_id: i32,
+    /// The depth of the certificate in the chain, where `0` is the
+    /// certificate of the broker itself.
+    pub depth: i32,
+    /// The X.509 error reported by OpenSSL for the certificate, or `0` if
+    /// OpenSSL accepted it.
+    pub x509_error: i32,
+    /// The DER-encoded certificate.
+    pub certificate: &'a [u8],
+}
 
-    let context = &mut *(opaque as *mut C);
-    context.error(error, reason.trim());
+unsafe extern "C" fn native_ssl_cert_verify_cb<C: ClientContext>(
+    _client: *mut RDKafka,
+    broker_name: *const c_char,
+    broker_id: i32,
+    x509_error: *mut c_int,
+    depth: c_int,
+    buf: *const c_char,
+    size: usize,
+    errstr: *mut c_char,
+    errstr_size: usize,
+    opaque: *mut c_void,
+) -> c_int {
+    let context = &ClientOpaque::<C>::from_ptr(opaque).context;
+    let broker_name = CStr::from_ptr(broker_name).to_string_lossy();
+    let cert = SslCertVerification {
+        broker_name: &broker_name,
+        broker_id,
+        depth,
+        x509_error: *x509_error,
+        certificate: slice::from_raw_parts(buf as *const u8, size),
+    };
+    let mut result = None;
+    handle_callback_event(|| result = Some(context.verify_ssl_cert(&cert)));
+    let message = match result {
+        Some(Ok(())) if C::ENABLE_SSL_CERT_OVERRIDE || *x509_error == 0 => {
+            *x509_error = 0;
+            return 1;
+        }
+        Some(Ok(())) => format!(
+            "certificate verification failed with X.509 error {}",
+            *x509_error
+        ),
+        Some(Err(message)) => message,
+        None => "SSL certificate verification callback panicked".into(),
+    };
+    if errstr_size > 0 {
+        let len = message.len().min(errstr_size - 1);
+        ptr::copy_nonoverlapping(message.as_ptr() as *const c_char, errstr, len);
+        *errstr.add(len) = 0;
+    }
+    0
 }
 
 /// A generated OAuth token and its associated metadata.
@@ -510,13 +955,36 @@ pub struct OAuthToken {
     pub lifetime_ms: i64,
 }
 
+unsafe fn set_oauthbearer_token(
+    client: *mut RDKafka,
+    token: &CStr,
+    principal_name: &CStr,
+    lifetime_ms: i64,
+) -> KafkaResult<()> {
+    let mut err_buf = ErrBuf::new();
+    let code = rdkafka_sys::rd_kafka_oauthbearer_set_token(
+        client,
+        token.as_ptr(),
+        lifetime_ms,
+        principal_name.as_ptr(),
+        ptr::null_mut(),
+        0,
+        err_buf.as_mut_ptr(),
+        err_buf.capacity(),
+    );
+    if code.is_error() {
+        return Err(KafkaError::OAuthToken(err_buf.to_string()));
+    }
+    Ok(())
+}
+
 pub(crate) unsafe extern "C" fn native_oauth_refresh_cb<C: ClientContext>(
     client: *mut RDKafka,
     oauthbearer_config: *const c_char,
     opaque: *mut c_void,
 ) {
     let res: Result<_, Box<dyn Error>> = (|| {
-        let context = &mut *(opaque as *mut C);
+        let context = &ClientOpaque::<C>::from_ptr(opaque).context;
         let oauthbearer_config = match oauthbearer_config.is_null() {
             true => None,
             false => Some(util::cstr_to_owned(oauthbearer_config)),
@@ -528,25 +996,14 @@ pub(crate) unsafe extern "C" fn native_oauth_refresh_cb<C: ClientContext>(
     })();
     match res {
         Ok((token, principal_name, lifetime_ms)) => {
-            let mut err_buf = ErrBuf::new();
-            let code = rdkafka_sys::rd_kafka_oauthbearer_set_token(
-                client,
-                token.as_ptr(),
-                lifetime_ms,
-                principal_name.as_ptr(),
-                ptr::null_mut(),
-                0,
-                err_buf.as_mut_ptr(),
-                err_buf.capacity(),
-            );
-            if code == RDKafkaRespErr::RD_KAFKA_RESP_ERR_NO_ERROR {
-                debug!("successfully set refreshed OAuth token");
-            } else {
-                debug!(
-                    "failed to set refreshed OAuth token (code {:?}): {}",
-                    code, err_buf
-                );
-                rdkafka_sys::rd_kafka_oauthbearer_set_token_failure(client, err_buf.as_mut_ptr());
+            match set_oauthbearer_token(client, &token, &principal_name, lifetime_ms) {
+                Ok(()) => debug!("successfully set refreshed OAuth token"),
+                Err(e) => {
+                    debug!("failed to set refreshed OAuth token: {}", e);
+                    let message = CString::new(e.to_string())
+                        .expect("librdkafka error strings are valid CStrings");
+                    rdkafka_sys::rd_kafka_oauthbearer_set_token_failure(client, message.as_ptr());
+                }
             }
         }
         Err(e) => {
@@ -585,4 +1042,146 @@ mod tests {
         .unwrap();
         assert!(!client.native_ptr().is_null());
     }
+
+    #[test]
+    fn test_client_dump() {
+        let config = ClientConfig::new();
+        let native_config = config.create_native_config().unwrap();
+        let client = Client::new(
+            &config,
+            native_config,
+            RDKafkaType::RD_KAFKA_PRODUCER,
+            DefaultClientContext,
+        )
+        .unwrap();
+        let mut dump = Vec::new();
+        client.dump(&mut dump).unwrap();
+        let dump = String::from_utf8_lossy(&dump);
+        assert!(dump.contains("rd_kafka_t"), "{}", dump);
+    }
+
+    #[test]
+    fn test_ssl_cert_verify_cb() {
+        struct PinningContext;
+
+        impl ClientContext for PinningContext {
+            const ENABLE_SSL_CERT_VERIFY: bool = true;
+            const ENABLE_SSL_CERT_OVERRIDE: bool = true;
+
+            fn verify_ssl_cert(&self, cert: &SslCertVerification<'_>) -> Result<(), String> {
+                match cert.certificate {
+                    b"pinned" => Ok(()),
+                    _ => Err(format!("certificate of {} is not pinned", cert.broker_name)),
+                }
+            }
+        }
+
+        struct AcceptingContext;
+
+        impl ClientContext for AcceptingContext {
+            const ENABLE_SSL_CERT_VERIFY: bool = true;
+
+            fn verify_ssl_cert(&self, _: &SslCertVerification<'_>) -> Result<(), String> {
+                Ok(())
+            }
+        }
+
+        unsafe fn verify<C: ClientContext>(
+            context: C,
+            cert: &[u8],
+            x509_error: &mut c_int,
+            errstr: &mut [c_char],
+        ) -> c_int {
+            let opaque = ClientOpaque {
+                context: Arc::new(context),
+                producer: None,
+                broker_states: Mutex::new(Vec::new()),
+            };
+            let broker_name = CString::new("kafka:9093").unwrap();
+            native_ssl_cert_verify_cb::<C>(
+                ptr::null_mut(),
+                broker_name.as_ptr(),
+                1,
+                x509_error,
+                0,
+                cert.as_ptr() as *const c_char,
+                cert.len(),
+                errstr.as_mut_ptr(),
+                errstr.len(),
+                &opaque as *const ClientOpaque<C> as *mut c_void,
+            )
+        }
+
+        let mut x509_error = 20;
+        let mut errstr = [0; 16];
+        unsafe {
+            assert_eq!(
+                verify(PinningContext, b"pinned", &mut x509_error, &mut errstr),
+                1
+            );
+            assert_eq!(x509_error, 0);
+            assert_eq!(
+                verify(PinningContext, b"other", &mut x509_error, &mut errstr),
+                0
+            );
+        }
+        let message = unsafe { CStr::from_ptr(errstr.as_ptr()) };
+        assert_eq!(message.to_str().unwrap(), "certificate of ");
+
+        // Without the override, the verdict of OpenSSL is kept.
+        x509_error = 20;
+        unsafe {
+            assert_eq!(
+                verify(AcceptingContext, b"other", &mut x509_error, &mut errstr),
+                0
+            );
+        }
+        assert_eq!(x509_error, 20);
+        let message = unsafe { CStr::from_ptr(errstr.as_ptr()) };
+        assert_eq!(message.to_str().unwrap(), "certificate ver");
+        x509_error = 0;
+        unsafe {
+            assert_eq!(
+                verify(AcceptingContext, b"other", &mut x509_error, &mut errstr),
+                1
+            );
+        }
+    }
+
+    #[test]
+    fn test_oauthbearer_set_token_without_oauthbearer() {
+        let config = ClientConfig::new();
+        let native_config = config.create_native_config().unwrap();
+        let client = Client::new(
+            &config,
+            native_config,
+            RDKafkaType::RD_KAFKA_PRODUCER,
+            DefaultClientContext,
+        )
+        .unwrap();
+        let token = OAuthToken {
+            token: "token".into(),
+            principal_name: "principal".into(),
+            lifetime_ms: i64::MAX,
+        };
+        assert!(matches!(
+            client.oauthbearer_set_token(&token),
+            Err(KafkaError::OAuthToken(_))
+        ));
+        assert!(matches!(
+            client.oauthbearer_set_token_failure("failure"),
+            Err(KafkaError::OAuthToken(_))
+        ));
+    }
+
+    #[test]
+    fn test_handle_callback_event() {
+        let dropped = dropped_callback_events();
+        let mut handled = false;
+        handle_callback_event(|| handled = true);
+        assert!(handled);
+        assert_eq!(dropped_callback_events(), dropped);
+        handle_callback_event(|| panic!("callback panicked"));
+        assert_eq!(dropped_callback_events(), dropped + 1);
+    }
 }
@@ -0,0 +1,296 @@
+//! Content-type driven payload codecs.
+//!
+//! A [`CodecRegistry`] maps content types, such as `application/json`, to the
+//! [`Serializer`]s and [`Deserializer`]s that convert values of a given type
+//! to and from message payloads. When serializing, the registry records the
+//! content type in the [`CONTENT_TYPE_HEADER`] header of the message; when
+//! deserializing, it reads the same header to pick the right deserializer for
+//! each message. This allows a single topic to carry messages in different
+//! formats, e.g. while its producers are migrated from one format to another.
+//!
+//! The registry is itself a [`Serializer`] and a [`Deserializer`], and is
+//! meant to be used as the payload serializer of a [`TypedProducer`] and the
+//! payload deserializer of a [`TypedConsumer`], which pass it the headers of
+//! every message.
+//!
+//! ```
+//! use rdkafka::codec::{CodecRegistry, JSON_CONTENT_TYPE};
+//! use rdkafka::serialization::{BytesDeserializer, BytesSerializer};
+//! use rdkafka::serialization::{JsonDeserializer, JsonSerializer};
+//!
+//! let mut registry = CodecRegistry::<String>::new();
+//! registry
+//!     .register(JSON_CONTENT_TYPE, JsonSerializer, JsonDeserializer)
+//!     .register("text/plain", BytesSerializer, BytesDeserializer)
+//!     .set_default_content_type(JSON_CONTENT_TYPE);
+//! ```
+//!
+//! [`TypedConsumer`]: crate::consumer::TypedConsumer
+//! [`TypedProducer`]: crate::producer::TypedProducer
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::error::{KafkaError, KafkaResult};
+use crate::message::{BorrowedHeaders, Header, Headers, Message, OwnedHeaders};
+use crate::serialization::{Deserializer, Serializer};
+
+/// The header used to record the content type of a message payload.
+pub const CONTENT_TYPE_HEADER: &str = "content-type";
+
+/// The content type of JSON payloads.
+pub const JSON_CONTENT_TYPE: &str = "application/json";
+
+/// Returns the content type recorded in the headers of the message, if any.
+pub fn content_type<M: Message>(message: &M) -> Option<&str> {
+    headers_content_type(message.headers()?)
+}
+
+fn headers_content_type<H: Headers>(headers: &H) -> Option<&str> {
+    headers
+        .iter()
+        .find(|header| header.key == CONTENT_TYPE_HEADER)
+        .and_then(|header| header.value)
+        .and_then(|value| std::str::from_utf8(value).ok())
+}
+
+struct Codec<T> {
+    serializer: Box<dyn Serializer<T>>,
+    deserializer: Box<dyn Deserializer<T>>,
+}
+
+/// A set of [`Serializer`]s and [`Deserializer`]s indexed by content type.
+pub struct CodecRegistry<T> {
+    codecs: HashMap<String, Codec<T>>,
+    default_content_type: Option<String>,
+}
+
+impl<T> CodecRegistry<T> {
+    /// Creates a new, empty registry.
+    pub fn new() -> CodecRegistry<T> {
+        CodecRegistry {
+            codecs: HashMap::new(),
+            default_content_type: None,
+        }
+    }
+
+    /// Registers the serializer and deserializer of the specified content
+    /// type, replacing any previously registered for it.
+    pub fn register<S, KS, KD>(
+        &mut self,
+        content_type: S,
+        serializer: KS,
+        deserializer: KD,
+    ) -> &mut CodecRegistry<T>
+    where
+        S: Into<String>,
+        KS: Serializer<T> + 'static,
+        KD: Deserializer<T> + 'static,
+    {
+        self.codecs.insert(
+            content_type.into(),
+            Codec {
+                serializer: Box::new(serializer),
+                deserializer: Box::new(deserializer),
+            },
+        );
+        self
+    }
+
+    /// Sets the default content type.
+    ///
+    /// Values are serialized with the default content type, unless the
+    /// headers of their message already have a [`CONTENT_TYPE_HEADER`]
+    /// header, and messages that have no such header are deserialized with
+    /// it.
+    pub fn set_default_content_type<S>(&mut self, content_type: S) -> &mut CodecRegistry<T>
+    where
+        S: Into<String>,
+    {
+        self.default_content_type = Some(content_type.into());
+        self
+    }
+
+    /// Returns the serializer registered for the specified content type, if
+    /// any.
+    pub fn serializer(&self, content_type: &str) -> Option<&dyn Serializer<T>> {
+        self.codecs
+            .get(content_type)
+            .map(|codec| &*codec.serializer)
+    }
+
+    /// Returns the deserializer registered for the specified content type, if
+    /// any.
+    pub fn deserializer(&self, content_type: &str) -> Option<&dyn Deserializer<T>> {
+        self.codecs
+            .get(content_type)
+            .map(|codec| &*codec.deserializer)
+    }
+
+    fn default_content_type(&self) -> Option<&str> {
+        self.default_content_type.as_deref()
+    }
+
+    fn expect_serializer(&self, content_type: &str) -> KafkaResult<&dyn Serializer<T>> {
+        self.serializer(content_type).ok_or_else(|| {
+            KafkaError::PayloadEncoding(format!("no codec for content type {}", content_type))
+        })
+    }
+
+    fn expect_deserializer(&self, content_type: Option<&str>) -> KafkaResult<&dyn Deserializer<T>> {
+        let content_type = content_type
+            .or_else(|| self.default_content_type())
+            .ok_or_else(|| KafkaError::PayloadDecoding("message has no content type".into()))?;
+        self.deserializer(content_type).ok_or_else(|| {
+            KafkaError::PayloadDecoding(format!("no codec for content type {}", content_type))
+        })
+    }
+}
+
+impl<T> Serializer<T> for CodecRegistry<T> {
+    /// Serializes the value with the default content type.
+    fn serialize(&self, value: &T) -> KafkaResult<Vec<u8>> {
+        let content_type = self
+            .default_content_type()
+            .ok_or_else(|| KafkaError::PayloadEncoding("no default content type".into()))?;
+        self.expect_serializer(content_type)?.serialize(value)
+    }
+
+    /// Serializes the value with the content type recorded in the headers,
+    /// or with the default content type, which is then recorded in the
+    /// headers.
+    fn serialize_with_headers(
+        &self,
+        value: &T,
+        headers: Option<OwnedHeaders>,
+    ) -> KafkaResult<(Vec<u8>, Option<OwnedHeaders>)> {
+        if let Some(content_type) = headers.as_ref().and_then(headers_content_type) {
+            let serializer = self.expect_serializer(content_type)?;
+            return serializer.serialize_with_headers(value, headers);
+        }
+        let content_type = self
+            .default_content_type()
+            .ok_or_else(|| KafkaError::PayloadEncoding("no default content type".into()))?;
+        let serializer = self.expect_serializer(content_type)?;
+        let headers = headers.unwrap_or_default().insert(Header {
+            key: CONTENT_TYPE_HEADER,
+            value: Some(content_type),
+        });
+        serializer.serialize_with_headers(value, Some(headers))
+    }
+}
+
+impl<T> Deserializer<T> for CodecRegistry<T> {
+    /// Deserializes the bytes with the default content type.
+    fn deserialize(&self, bytes: &[u8]) -> KafkaResult<T> {
+        self.expect_deserializer(None)?.deserialize(bytes)
+    }
+
+    /// Deserializes the bytes with the content type recorded in the headers,
+    /// or with the default content type if there is none.
+    fn deserialize_with_headers(
+        &self,
+        bytes: &[u8],
+        headers: Option<&BorrowedHeaders>,
+    ) -> KafkaResult<T> {
+        let content_type = headers.and_then(headers_content_type);
+        self.expect_deserializer(content_type)?
+            .deserialize_with_headers(bytes, headers)
+    }
+}
+
+impl<T> Default for CodecRegistry<T> {
+    fn default() -> CodecRegistry<T> {
+        CodecRegistry::new()
+    }
+}
+
+impl<T> fmt::Debug for CodecRegistry<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CodecRegistry")
+            .field("content_types", &self.codecs.keys().collect::<Vec<_>>())
+            .field("default_content_type", &self.default_content_type)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serialization::{BytesDeserializer, BytesSerializer};
+
+    /// Encodes strings as their reversed UTF-8 bytes.
+    struct ReversedSerializer;
+
+    impl Serializer<String> for ReversedSerializer {
+        fn serialize(&self, value: &String) -> KafkaResult<Vec<u8>> {
+            Ok(value.bytes().rev().collect())
+        }
+    }
+
+    struct ReversedDeserializer;
+
+    impl Deserializer<String> for ReversedDeserializer {
+        fn deserialize(&self, bytes: &[u8]) -> KafkaResult<String> {
+            String::from_utf8(bytes.iter().rev().copied().collect())
+                .map_err(|e| KafkaError::PayloadDecoding(e.to_string()))
+        }
+    }
+
+    fn content_type_header(content_type: &str) -> OwnedHeaders {
+        OwnedHeaders::new().insert(Header {
+            key: CONTENT_TYPE_HEADER,
+            value: Some(content_type),
+        })
+    }
+
+    #[test]
+    fn test_codec_registry() {
+        let mut registry = CodecRegistry::<String>::new();
+        registry
+            .register("text/plain", BytesSerializer, BytesDeserializer)
+            .register("text/reversed", ReversedSerializer, ReversedDeserializer);
+        let value = "hello".to_string();
+
+        assert!(matches!(
+            registry.serialize_with_headers(&value, None),
+            Err(KafkaError::PayloadEncoding(_))
+        ));
+        registry.set_default_content_type("text/plain");
+        let (payload, headers) = registry.serialize_with_headers(&value, None).unwrap();
+        assert_eq!(payload, b"hello");
+        let headers = headers.unwrap();
+        assert_eq!(headers_content_type(&headers), Some("text/plain"));
+
+        // The content type recorded in the headers takes precedence.
+        let (payload, headers) = registry
+            .serialize_with_headers(&value, Some(content_type_header("text/reversed")))
+            .unwrap();
+        assert_eq!(payload, b"olleh");
+        let headers = headers.unwrap();
+        assert_eq!(headers.count(), 1);
+        assert_eq!(
+            registry
+                .deserialize_with_headers(&payload, Some(headers.as_borrowed()))
+                .unwrap(),
+            "hello"
+        );
+
+        // Messages without a content type use the default one.
+        assert_eq!(
+            registry.deserialize_with_headers(b"hello", None).unwrap(),
+            "hello"
+        );
+        assert_eq!(registry.deserialize(b"hello").unwrap(), "hello");
+
+        let unknown = content_type_header("application/avro");
+        assert!(matches!(
+            registry.serialize_with_headers(&value, Some(unknown.clone())),
+            Err(KafkaError::PayloadEncoding(_))
+        ));
+        assert!(matches!(
+            registry.deserialize_with_headers(b"hello", Some(unknown.as_borrowed())),
+            Err(KafkaError::PayloadDecoding(_))
+        ));
+    }
+}
@@ -23,10 +23,17 @@
 //! [librdkafka-config]: https://github.com/edenhill/librdkafka/blob/master/CONFIGURATION.md
 
 use std::collections::HashMap;
-use std::ffi::{CStr, CString};
+use std::env;
+use std::ffi::{CStr, CString, OsString};
+use std::fmt;
+use std::fs;
+use std::io;
 use std::iter::FromIterator;
-use std::os::raw::c_char;
+use std::os::raw::{c_char, c_void};
+use std::path::Path;
 use std::ptr;
+use std::slice;
+use std::time::Duration;
 
 use rdkafka_sys as rdsys;
 use rdkafka_sys::types::*;
@@ -34,7 +41,7 @@ use rdkafka_sys::types::*;
 use crate::client::ClientContext;
 use crate::error::{IsError, KafkaError, KafkaResult};
 use crate::log::{log_enabled, DEBUG, INFO, WARN};
-use crate::util::{ErrBuf, KafkaDrop, NativePtr};
+use crate::util::{cstr_to_owned, ErrBuf, KafkaDrop, NativePtr};
 
 /// The log levels supported by librdkafka.
 #[derive(Copy, Clone, Debug)]
@@ -155,6 +162,31 @@ impl NativeClientConfig {
             .to_string_lossy()
             .into())
     }
+
+    /// Returns the values of all the parameters in the configuration,
+    /// including librdkafka's default values for the parameters that were
+    /// not overridden.
+    ///
+    /// Note that the values of sensitive parameters, such as
+    /// `sasl.password`, are included.
+    pub fn dump(&self) -> HashMap<String, String> {
+        unsafe { dump_native_config(self.ptr()) }
+    }
+}
+
+/// Returns the parameters of a librdkafka configuration, as returned by
+/// `rd_kafka_conf_dump`.
+pub(crate) unsafe fn dump_native_config(conf: *mut RDKafkaConf) -> HashMap<String, String> {
+    let mut count = 0;
+    let dump = rdsys::rd_kafka_conf_dump(conf, &mut count);
+    // The dump alternates between names and values.
+    let entries = slice::from_raw_parts(dump, count);
+    let params = entries
+        .chunks_exact(2)
+        .map(|pair| (cstr_to_owned(pair[0]), cstr_to_owned(pair[1])))
+        .collect();
+    rdsys::rd_kafka_conf_dump_free(dump, count);
+    params
 }
 
 /// Client configuration.
@@ -164,6 +196,8 @@ pub struct ClientConfig {
     /// The librdkafka logging level. Refer to [`RDKafkaLogLevel`] for the list
     /// of available levels.
     pub log_level: RDKafkaLogLevel,
+    strict: bool,
+    ssl_certs: Vec<SslCert>,
 }
 
 impl Default for ClientConfig {
@@ -178,6 +212,8 @@ impl ClientConfig {
         ClientConfig {
             conf_map: HashMap::new(),
             log_level: log_level_from_global_config(),
+            strict: false,
+            ssl_certs: Vec::new(),
         }
     }
 
@@ -218,6 +254,30 @@ impl ClientConfig {
         self
     }
 
+    /// Sets an SSL certificate, private key or CA certificate from memory.
+    ///
+    /// This is an alternative to the `ssl.certificate.location`,
+    /// `ssl.key.location` and `ssl.ca.location` properties for credentials
+    /// that are not stored on disk, e.g. because they are fetched from a
+    /// secrets manager at runtime. A private key that is set this way must not
+    /// be encrypted, so `ssl.key.password` is not used. The data is only
+    /// checked when the client is created, and setting a credential of the
+    /// same type again replaces it.
+    pub fn set_ssl_cert(
+        &mut self,
+        cert_type: SslCertType,
+        encoding: SslCertEncoding,
+        data: &[u8],
+    ) -> &mut ClientConfig {
+        self.ssl_certs.retain(|cert| cert.cert_type != cert_type);
+        self.ssl_certs.push(SslCert {
+            cert_type,
+            encoding,
+            data: data.to_vec(),
+        });
+        self
+    }
+
     /// Sets the log level of the client. If not specified, the log level will be calculated based
     /// on the global log level of the log crate.
     pub fn set_log_level(&mut self, log_level: RDKafkaLogLevel) -> &mut ClientConfig {
@@ -225,6 +285,171 @@ impl ClientConfig {
         self
     }
 
+    /// Sets the parameters listed in the contents of a Java `.properties`
+    /// file, overriding existing values.
+    ///
+    /// This allows the configuration of the Java and Rust clients of an
+    /// application to be shared verbatim. The full `.properties` syntax is
+    /// supported: comment lines starting with `#` or `!`, keys separated from
+    /// values by `=`, `:` or whitespace, lines continued with a trailing
+    /// backslash, and backslash escapes, including `\uXXXX` escapes.
+    ///
+    /// Returns a [`KafkaError::ClientConfig`] error if an escape sequence is
+    /// malformed, in which case the configuration is left unchanged. Note
+    /// that properties specific to the Java client, such as
+    /// `key.serializer`, are not recognized by librdkafka and must be removed
+    /// before a client is created.
+    ///
+    /// ```
+    /// use rdkafka::config::ClientConfig;
+    ///
+    /// let mut config = ClientConfig::new();
+    /// config
+    ///     .set_properties(
+    ///         "# Shared configuration\n\
+    ///          bootstrap.servers=kafka-1:9092,kafka-2:9092\n\
+    ///          client.id : billing\n",
+    ///     )
+    ///     .unwrap();
+    /// assert_eq!(config.get("client.id"), Some("billing"));
+    /// ```
+    pub fn set_properties(&mut self, properties: &str) -> KafkaResult<&mut ClientConfig> {
+        let properties = parse_java_properties(properties).map_err(|(error, line)| {
+            KafkaError::ClientConfig(
+                RDKafkaConfRes::RD_KAFKA_CONF_INVALID,
+                error.into(),
+                line,
+                String::new(),
+            )
+        })?;
+        self.conf_map.extend(properties);
+        Ok(self)
+    }
+
+    /// Like [`ClientConfig::set_properties`], but reads the properties from
+    /// the file at the specified path.
+    pub fn load_properties<P: AsRef<Path>>(&mut self, path: P) -> io::Result<&mut ClientConfig> {
+        let properties = fs::read_to_string(path)?;
+        Ok(self.set_properties(&properties)?)
+    }
+
+    /// Creates a configuration from the Java `.properties` file at the
+    /// specified path. See [`ClientConfig::set_properties`] for details.
+    pub fn from_properties_file<P: AsRef<Path>>(path: P) -> io::Result<ClientConfig> {
+        let mut config = ClientConfig::new();
+        config.load_properties(path)?;
+        Ok(config)
+    }
+
+    /// Creates a configuration from the environment variables whose name
+    /// starts with the specified prefix.
+    ///
+    /// The name of each property is derived from the rest of the name of the
+    /// variable, lowercased, with single underscores replaced by dots and
+    /// double underscores replaced by single underscores. For example, with
+    /// the prefix `KAFKA_`, `KAFKA_BOOTSTRAP_SERVERS` sets
+    /// `bootstrap.servers`. Variables whose name or value is not valid
+    /// Unicode are ignored.
+    pub fn from_env(prefix: &str) -> ClientConfig {
+        ClientConfig::from_vars(prefix, env::vars_os())
+    }
+
+    fn from_vars<I>(prefix: &str, vars: I) -> ClientConfig
+    where
+        I: IntoIterator<Item = (OsString, OsString)>,
+    {
+        let mut config = ClientConfig::new();
+        for (name, value) in vars {
+            let (name, value) = match (name.into_string(), value.into_string()) {
+                (Ok(name), Ok(value)) => (name, value),
+                _ => continue,
+            };
+            if name.len() > prefix.len() && name.starts_with(prefix) {
+                let key = name[prefix.len()..]
+                    .to_lowercase()
+                    .split("__")
+                    .map(|part| part.replace('_', "."))
+                    .collect::<Vec<_>>()
+                    .join("_");
+                config.set(key, value);
+            }
+        }
+        config
+    }
+
+    /// Enables or disables strict validation of the configuration.
+    ///
+    /// In strict mode, creating a consumer or producer fails if the
+    /// configuration contains properties that are deprecated, or that do not
+    /// apply to the type of client being created, such as consumer properties
+    /// on a producer. librdkafka only logs a warning for those properties,
+    /// which makes configuration mistakes easy to miss. Strict mode is disabled
+    /// by default.
+    pub fn set_strict(&mut self, strict: bool) -> &mut ClientConfig {
+        self.strict = strict;
+        self
+    }
+
+    /// Checks that the configuration contains no deprecated properties, and
+    /// no properties that do not apply to the specified kind of clients.
+    ///
+    /// Unknown properties are not reported here, as librdkafka always rejects
+    /// them when the client is created. See also [`ClientConfig::set_strict`].
+    pub fn validate(&self, clients: ConfigClients) -> KafkaResult<()> {
+        let properties = ClientConfig::properties()
+            .into_iter()
+            .map(|property| (property.name.clone(), property))
+            .collect::<HashMap<_, _>>();
+        for (key, value) in &self.conf_map {
+            let property = match properties.get(key) {
+                Some(property) => property,
+                None => continue,
+            };
+            let error = if property.deprecated {
+                "Configuration property is deprecated"
+            } else if clients == ConfigClients::All
+                || property.clients == ConfigClients::All
+                || property.clients == clients
+            {
+                continue;
+            } else if property.clients == ConfigClients::Consumer {
+                "Configuration property only applies to consumers"
+            } else {
+                "Configuration property only applies to producers"
+            };
+            return Err(KafkaError::ClientConfig(
+                RDKafkaConfRes::RD_KAFKA_CONF_INVALID,
+                error.into(),
+                key.to_string(),
+                value.to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Validates the configuration for the specified kind of clients if
+    /// strict mode is enabled.
+    pub(crate) fn validate_if_strict(&self, clients: ConfigClients) -> KafkaResult<()> {
+        if self.strict {
+            self.validate(clients)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns the effective value of every parameter, including librdkafka's
+    /// default values for the parameters that were not set.
+    ///
+    /// This is useful to debug mismatches between the settings of clients
+    /// and brokers. Returns an error if the configuration is invalid. See
+    /// also [`Client::config_dump`], which returns the configuration of a
+    /// running client.
+    ///
+    /// [`Client::config_dump`]: crate::client::Client::config_dump
+    pub fn config_dump(&self) -> KafkaResult<HashMap<String, String>> {
+        Ok(self.create_native_config()?.dump())
+    }
+
     /// Builds a native librdkafka configuration.
     pub fn create_native_config(&self) -> KafkaResult<NativeClientConfig> {
         let conf = unsafe { NativeClientConfig::from_ptr(rdsys::rd_kafka_conf_new()) };
@@ -250,9 +475,66 @@ impl ClientConfig {
                 ));
             }
         }
+        for cert in &self.ssl_certs {
+            let ret = unsafe {
+                rdsys::rd_kafka_conf_set_ssl_cert(
+                    conf.ptr(),
+                    cert.cert_type.to_native(),
+                    cert.encoding.to_native(),
+                    cert.data.as_ptr() as *const c_void,
+                    cert.data.len(),
+                    err_buf.as_mut_ptr(),
+                    err_buf.capacity(),
+                )
+            };
+            if ret.is_error() {
+                return Err(KafkaError::ClientConfig(
+                    ret,
+                    err_buf.to_string(),
+                    format!("{:?}", cert.cert_type),
+                    format!("<{} bytes>", cert.data.len()),
+                ));
+            }
+        }
         Ok(conf)
     }
 
+    /// Returns the metadata of all the configuration properties supported by
+    /// librdkafka.
+    ///
+    /// The metadata is extracted from the library at runtime, so it always
+    /// matches the version of librdkafka in use. An empty list is returned if
+    /// the metadata cannot be extracted.
+    pub fn properties() -> Vec<ConfigProperty> {
+        unsafe {
+            let fp = libc::tmpfile();
+            if fp.is_null() {
+                return Vec::new();
+            }
+            rdsys::rd_kafka_conf_properties_show(fp);
+            libc::rewind(fp);
+            let mut table = Vec::new();
+            let mut buf = [0u8; 4096];
+            loop {
+                let n = libc::fread(buf.as_mut_ptr() as *mut c_void, 1, buf.len(), fp);
+                if n == 0 {
+                    break;
+                }
+                table.extend_from_slice(&buf[..n]);
+            }
+            libc::fclose(fp);
+            parse_properties(&String::from_utf8_lossy(&table))
+        }
+    }
+
+    /// Returns the metadata of the configuration property named `key`, or
+    /// `None` if librdkafka does not support such a property.
+    pub fn describe(key: &str) -> Option<ConfigProperty> {
+        ClientConfig::properties()
+            .into_iter()
+            .find(|property| property.name == key)
+    }
+
     /// Uses the current configuration to create a new Consumer or Producer.
     pub fn create<T: FromClientConfig>(&self) -> KafkaResult<T> {
         T::from_config(self)
@@ -268,6 +550,200 @@ impl ClientConfig {
     }
 }
 
+/// The scope of a configuration property.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigScope {
+    /// The property applies to the whole client.
+    Global,
+    /// The property applies to each topic. Topic properties set on a
+    /// [`ClientConfig`] are used as defaults for all topics.
+    Topic,
+}
+
+/// The kind of clients a configuration property applies to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigClients {
+    /// The property applies to both consumers and producers.
+    All,
+    /// The property only applies to consumers.
+    Consumer,
+    /// The property only applies to producers.
+    Producer,
+}
+
+/// The importance of a configuration property, as rated by librdkafka.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ConfigImportance {
+    /// Low importance.
+    Low,
+    /// Medium importance.
+    Medium,
+    /// High importance.
+    High,
+}
+
+/// The metadata of a librdkafka configuration property.
+///
+/// See [`ClientConfig::properties`] and [`ClientConfig::describe`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConfigProperty {
+    /// The name of the property.
+    pub name: String,
+    /// The scope of the property.
+    pub scope: ConfigScope,
+    /// The kind of clients the property applies to.
+    pub clients: ConfigClients,
+    /// The type of the property, e.g. `integer`, `boolean` or `enum value`.
+    pub property_type: String,
+    /// The range of valid values, or the list of valid values for
+    /// enumerations, if any.
+    pub range: Option<String>,
+    /// The default value, if any.
+    pub default: Option<String>,
+    /// The importance of the property.
+    pub importance: ConfigImportance,
+    /// The description of the property.
+    pub description: String,
+    /// Whether the property is deprecated.
+    pub deprecated: bool,
+}
+
+/// Parses the table of configuration properties printed by
+/// `rd_kafka_conf_properties_show`.
+fn parse_properties(table: &str) -> Vec<ConfigProperty> {
+    let mut properties = Vec::new();
+    let mut scope = ConfigScope::Global;
+    for line in table.lines() {
+        if line.starts_with("## Global") {
+            scope = ConfigScope::Global;
+            continue;
+        } else if line.starts_with("## Topic") {
+            scope = ConfigScope::Topic;
+            continue;
+        }
+        let fields = line.splitn(6, '|').map(str::trim).collect::<Vec<_>>();
+        if fields.len() != 6 || fields[0] == "Property" || fields[0].starts_with('-') {
+            continue;
+        }
+        let clients = match fields[1] {
+            "C" => ConfigClients::Consumer,
+            "P" => ConfigClients::Producer,
+            _ => ConfigClients::All,
+        };
+        let importance = match fields[4] {
+            "high" => ConfigImportance::High,
+            "medium" => ConfigImportance::Medium,
+            _ => ConfigImportance::Low,
+        };
+        let (description, property_type) = match fields[5].rfind("<br>*Type: ") {
+            Some(i) => (
+                &fields[5][..i],
+                fields[5][i + "<br>*Type: ".len()..].trim_end_matches('*'),
+            ),
+            None => (fields[5], ""),
+        };
+        let non_empty = |field: &str| Some(field.to_string()).filter(|f| !f.is_empty());
+        properties.push(ConfigProperty {
+            name: fields[0].to_string(),
+            scope,
+            clients,
+            property_type: property_type.to_string(),
+            range: non_empty(fields[2]),
+            default: non_empty(fields[3]),
+            importance,
+            description: description.trim().to_string(),
+            deprecated: description.starts_with("**DEPRECATED**"),
+        });
+    }
+    properties
+}
+
+/// Parses the contents of a Java `.properties` file into key-value pairs.
+///
+/// On failure, returns a description of the error and the offending line.
+fn parse_java_properties(input: &str) -> Result<Vec<(String, String)>, (&'static str, String)> {
+    let mut properties = Vec::new();
+    let mut lines = input.lines();
+    while let Some(line) = lines.next() {
+        let line = line.trim_start_matches(is_properties_whitespace);
+        if line.is_empty() || line.starts_with('#') || line.starts_with('!') {
+            continue;
+        }
+        // Join continued lines, dropping the leading whitespace of each
+        // continuation line.
+        let mut logical = line.to_string();
+        while ends_with_continuation(&logical) {
+            logical.pop();
+            match lines.next() {
+                Some(next) => logical.push_str(next.trim_start_matches(is_properties_whitespace)),
+                None => break,
+            }
+        }
+
+        let mut key_end = logical.len();
+        let mut escaped = false;
+        for (i, c) in logical.char_indices() {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '=' || c == ':' || is_properties_whitespace(c) {
+                key_end = i;
+                break;
+            }
+        }
+        let rest = logical[key_end..].trim_start_matches(is_properties_whitespace);
+        let value = match rest.chars().next() {
+            Some('=') | Some(':') => rest[1..].trim_start_matches(is_properties_whitespace),
+            _ => rest,
+        };
+        match (
+            unescape_java_property(&logical[..key_end]),
+            unescape_java_property(value),
+        ) {
+            (Some(key), Some(value)) => properties.push((key, value)),
+            _ => return Err(("Malformed \\uXXXX escape sequence", logical)),
+        }
+    }
+    Ok(properties)
+}
+
+fn is_properties_whitespace(c: char) -> bool {
+    c == ' ' || c == '\t' || c == '\x0c'
+}
+
+fn ends_with_continuation(line: &str) -> bool {
+    line.chars().rev().take_while(|c| *c == '\\').count() % 2 == 1
+}
+
+fn unescape_java_property(escaped: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(escaped.len());
+    let mut chars = escaped.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some('f') => unescaped.push('\x0c'),
+            Some('u') => {
+                let code = chars.by_ref().take(4).collect::<String>();
+                if code.len() != 4 {
+                    return None;
+                }
+                let code = u32::from_str_radix(&code, 16).ok()?;
+                unescaped.push(std::char::from_u32(code)?);
+            }
+            Some(c) => unescaped.push(c),
+            None => (),
+        }
+    }
+    Some(unescaped)
+}
+
 impl FromIterator<(String, String)> for ClientConfig {
     fn from_iter<I>(iter: I) -> ClientConfig
     where
@@ -288,6 +764,474 @@ impl Extend<(String, String)> for ClientConfig {
     }
 }
 
+/// The type of an SSL credential set with [`ClientConfig::set_ssl_cert`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SslCertType {
+    /// The client certificate.
+    Certificate,
+    /// The private key of the client certificate.
+    PrivateKey,
+    /// The CA certificates used to verify the certificates of the brokers.
+    Ca,
+}
+
+impl SslCertType {
+    fn to_native(self) -> RDKafkaCertType {
+        match self {
+            SslCertType::Certificate => RDKafkaCertType::RD_KAFKA_CERT_PUBLIC_KEY,
+            SslCertType::PrivateKey => RDKafkaCertType::RD_KAFKA_CERT_PRIVATE_KEY,
+            SslCertType::Ca => RDKafkaCertType::RD_KAFKA_CERT_CA,
+        }
+    }
+}
+
+/// The encoding of an SSL credential set with [`ClientConfig::set_ssl_cert`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SslCertEncoding {
+    /// A PKCS#12 archive, which may only be used for the client certificate.
+    Pkcs12,
+    /// A single DER-encoded certificate or key.
+    Der,
+    /// One or more PEM-encoded certificates or keys.
+    Pem,
+}
+
+impl SslCertEncoding {
+    fn to_native(self) -> RDKafkaCertEncoding {
+        match self {
+            SslCertEncoding::Pkcs12 => RDKafkaCertEncoding::RD_KAFKA_CERT_ENC_PKCS12,
+            SslCertEncoding::Der => RDKafkaCertEncoding::RD_KAFKA_CERT_ENC_DER,
+            SslCertEncoding::Pem => RDKafkaCertEncoding::RD_KAFKA_CERT_ENC_PEM,
+        }
+    }
+}
+
+#[derive(Clone)]
+struct SslCert {
+    cert_type: SslCertType,
+    encoding: SslCertEncoding,
+    data: Vec<u8>,
+}
+
+impl fmt::Debug for SslCert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The data is left out, since it may contain a private key.
+        f.debug_struct("SslCert")
+            .field("cert_type", &self.cert_type)
+            .field("encoding", &self.encoding)
+            .finish()
+    }
+}
+
+//
+// ********** CONFIG BUILDER **********
+//
+
+/// A compression codec, for the `compression.codec` property.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    /// No compression.
+    None,
+    /// Gzip compression.
+    Gzip,
+    /// Snappy compression.
+    Snappy,
+    /// LZ4 compression.
+    Lz4,
+    /// Zstandard compression.
+    ///
+    /// Requires librdkafka to be built with zstd support, i.e. the `zstd`
+    /// feature of this crate.
+    Zstd,
+}
+
+impl Compression {
+    fn as_str(self) -> &'static str {
+        match self {
+            Compression::None => "none",
+            Compression::Gzip => "gzip",
+            Compression::Snappy => "snappy",
+            Compression::Lz4 => "lz4",
+            Compression::Zstd => "zstd",
+        }
+    }
+}
+
+/// The acknowledgements a producer waits for, for the `acks` property.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Acks {
+    /// Do not wait for any acknowledgement.
+    None,
+    /// Wait for the leader of the partition to write the message.
+    Leader,
+    /// Wait for all the in-sync replicas of the partition to write the
+    /// message.
+    All,
+}
+
+impl Acks {
+    fn as_str(self) -> &'static str {
+        match self {
+            Acks::None => "0",
+            Acks::Leader => "1",
+            Acks::All => "all",
+        }
+    }
+}
+
+/// Where a consumer starts when it has no valid committed offset, for the
+/// `auto.offset.reset` property.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AutoOffsetReset {
+    /// Start from the earliest offset.
+    Earliest,
+    /// Start from the latest offset.
+    Latest,
+    /// Report an error to the consumer.
+    Error,
+}
+
+impl AutoOffsetReset {
+    fn as_str(self) -> &'static str {
+        match self {
+            AutoOffsetReset::Earliest => "earliest",
+            AutoOffsetReset::Latest => "latest",
+            AutoOffsetReset::Error => "error",
+        }
+    }
+}
+
+/// The protocol used to communicate with brokers, for the
+/// `security.protocol` property.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SecurityProtocol {
+    /// Unauthenticated, unencrypted connections.
+    Plaintext,
+    /// TLS connections.
+    Ssl,
+    /// SASL authentication over unencrypted connections.
+    SaslPlaintext,
+    /// SASL authentication over TLS connections.
+    SaslSsl,
+}
+
+impl SecurityProtocol {
+    fn as_str(self) -> &'static str {
+        match self {
+            SecurityProtocol::Plaintext => "plaintext",
+            SecurityProtocol::Ssl => "ssl",
+            SecurityProtocol::SaslPlaintext => "sasl_plaintext",
+            SecurityProtocol::SaslSsl => "sasl_ssl",
+        }
+    }
+}
+
+/// A typed builder for a [`ClientConfig`].
+///
+/// The builder provides typed setters for the most frequently used
+/// properties, and [`ClientConfigBuilder::set`] for all the others. Unlike
+/// with [`ClientConfig::set`], mistakes are reported when the configuration
+/// is built rather than when a client is created: [`ClientConfigBuilder::build`]
+/// checks every property against the properties known to librdkafka, and
+/// every value against the type and range of its property.
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use rdkafka::config::{Acks, ClientConfigBuilder, Compression};
+///
+/// let config = ClientConfigBuilder::new()
+///     .bootstrap_servers(&["kafka-1:9092", "kafka-2:9092"])
+///     .compression(Compression::Lz4)
+///     .acks(Acks::All)
+///     .linger(Duration::from_millis(5))
+///     .set("queue.buffering.max.messages", "500000")
+///     .build()
+///     .unwrap();
+/// assert_eq!(config.get("compression.codec"), Some("lz4"));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ClientConfigBuilder {
+    config: ClientConfig,
+}
+
+impl ClientConfigBuilder {
+    /// Creates a new builder with an empty configuration.
+    pub fn new() -> ClientConfigBuilder {
+        ClientConfigBuilder::default()
+    }
+
+    /// Sets the initial list of brokers, `bootstrap.servers`.
+    pub fn bootstrap_servers<S: AsRef<str>>(self, servers: &[S]) -> ClientConfigBuilder {
+        let servers = servers.iter().map(AsRef::as_ref).collect::<Vec<_>>();
+        self.set("bootstrap.servers", servers.join(","))
+    }
+
+    /// Sets the client identifier, `client.id`.
+    pub fn client_id<S: Into<String>>(self, client_id: S) -> ClientConfigBuilder {
+        self.set("client.id", client_id)
+    }
+
+    /// Sets the consumer group, `group.id`.
+    pub fn group_id<S: Into<String>>(self, group_id: S) -> ClientConfigBuilder {
+        self.set("group.id", group_id)
+    }
+
+    /// Sets the protocol used to communicate with brokers,
+    /// `security.protocol`.
+    pub fn security_protocol(self, protocol: SecurityProtocol) -> ClientConfigBuilder {
+        self.set("security.protocol", protocol.as_str())
+    }
+
+    /// Sets the compression codec of produced messages, `compression.codec`.
+    pub fn compression(self, compression: Compression) -> ClientConfigBuilder {
+        self.set("compression.codec", compression.as_str())
+    }
+
+    /// Sets the acknowledgements that producers wait for, `acks`.
+    pub fn acks(self, acks: Acks) -> ClientConfigBuilder {
+        self.set("acks", acks.as_str())
+    }
+
+    /// Enables or disables the idempotent producer, `enable.idempotence`.
+    pub fn enable_idempotence(self, enable: bool) -> ClientConfigBuilder {
+        self.set("enable.idempotence", enable.to_string())
+    }
+
+    /// Sets how long producers wait to batch messages, `linger.ms`.
+    pub fn linger(self, linger: Duration) -> ClientConfigBuilder {
+        self.set("linger.ms", linger.as_millis().to_string())
+    }
+
+    /// Sets how long producers try to deliver a message,
+    /// `message.timeout.ms`.
+    pub fn message_timeout(self, timeout: Duration) -> ClientConfigBuilder {
+        self.set("message.timeout.ms", timeout.as_millis().to_string())
+    }
+
+    /// Sets where consumers start when they have no valid committed offset,
+    /// `auto.offset.reset`.
+    pub fn auto_offset_reset(self, reset: AutoOffsetReset) -> ClientConfigBuilder {
+        self.set("auto.offset.reset", reset.as_str())
+    }
+
+    /// Enables or disables the periodic commit of consumer offsets,
+    /// `enable.auto.commit`.
+    pub fn enable_auto_commit(self, enable: bool) -> ClientConfigBuilder {
+        self.set("enable.auto.commit", enable.to_string())
+    }
+
+    /// Sets the consumer group session timeout, `session.timeout.ms`.
+    pub fn session_timeout(self, timeout: Duration) -> ClientConfigBuilder {
+        self.set("session.timeout.ms", timeout.as_millis().to_string())
+    }
+
+    /// Sets how often statistics are emitted, `statistics.interval.ms`.
+    pub fn statistics_interval(self, interval: Duration) -> ClientConfigBuilder {
+        self.set("statistics.interval.ms", interval.as_millis().to_string())
+    }
+
+    /// Sets the log level of the client. See [`ClientConfig::set_log_level`].
+    pub fn log_level(mut self, log_level: RDKafkaLogLevel) -> ClientConfigBuilder {
+        self.config.set_log_level(log_level);
+        self
+    }
+
+    /// Sets a property that has no typed setter.
+    pub fn set<K, V>(mut self, key: K, value: V) -> ClientConfigBuilder
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.config.set(key, value);
+        self
+    }
+
+    /// Validates the configuration and returns it.
+    ///
+    /// Returns a [`KafkaError::ClientConfig`] error for the first property
+    /// that librdkafka does not know, or whose value librdkafka rejects.
+    pub fn build(self) -> KafkaResult<ClientConfig> {
+        self.config.create_native_config()?;
+        Ok(self.config)
+    }
+}
+
+//
+// ********** TOPIC CONFIG **********
+//
+
+/// A native rdkafka-sys topic config.
+pub struct NativeTopicConfig {
+    ptr: NativePtr<RDKafkaTopicConf>,
+}
+
+unsafe impl KafkaDrop for RDKafkaTopicConf {
+    const TYPE: &'static str = "topic config";
+    const DROP: unsafe extern "C" fn(*mut Self) = rdsys::rd_kafka_topic_conf_destroy;
+}
+
+impl NativeTopicConfig {
+    /// Wraps a pointer to an `RDKafkaTopicConf` object and returns a new
+    /// `NativeTopicConfig`.
+    pub(crate) unsafe fn from_ptr(ptr: *mut RDKafkaTopicConf) -> NativeTopicConfig {
+        NativeTopicConfig {
+            ptr: NativePtr::from_ptr(ptr).unwrap(),
+        }
+    }
+
+    /// Returns the pointer to the librdkafka RDKafkaTopicConf structure.
+    pub fn ptr(&self) -> *mut RDKafkaTopicConf {
+        self.ptr.ptr()
+    }
+
+    /// Gets the value of a parameter in the configuration.
+    ///
+    /// This method reflects librdkafka's view of the current value of the
+    /// parameter. See [`NativeClientConfig::get`] for details.
+    pub fn get(&self, key: &str) -> KafkaResult<String> {
+        let make_err = |res| {
+            KafkaError::ClientConfig(
+                res,
+                match res {
+                    RDKafkaConfRes::RD_KAFKA_CONF_UNKNOWN => "Unknown configuration name",
+                    RDKafkaConfRes::RD_KAFKA_CONF_INVALID => "Invalid configuration value",
+                    RDKafkaConfRes::RD_KAFKA_CONF_OK => "OK",
+                }
+                .into(),
+                key.into(),
+                "".into(),
+            )
+        };
+        let key_c = CString::new(key.to_string())?;
+
+        let mut size = 0_usize;
+        let res = unsafe {
+            rdsys::rd_kafka_topic_conf_get(self.ptr(), key_c.as_ptr(), ptr::null_mut(), &mut size)
+        };
+        if res.is_error() {
+            return Err(make_err(res));
+        }
+
+        let mut buf = vec![0_u8; size];
+        let res = unsafe {
+            rdsys::rd_kafka_topic_conf_get(
+                self.ptr(),
+                key_c.as_ptr(),
+                buf.as_mut_ptr() as *mut c_char,
+                &mut size,
+            )
+        };
+        if res.is_error() {
+            return Err(make_err(res));
+        }
+
+        Ok(CStr::from_bytes_with_nul(&buf)
+            .unwrap()
+            .to_string_lossy()
+            .into())
+    }
+}
+
+/// Topic configuration.
+///
+/// Topic properties set on a [`ClientConfig`] apply to all the topics of a
+/// client. A `TopicConfig` holds properties, such as `message.timeout.ms` or
+/// `partitioner`, that only apply to a single topic. It can be installed with
+/// [`BaseProducer::configure_topic`].
+///
+/// [`BaseProducer::configure_topic`]: crate::producer::BaseProducer::configure_topic
+#[derive(Clone, Debug, Default)]
+pub struct TopicConfig {
+    conf_map: HashMap<String, String>,
+}
+
+impl TopicConfig {
+    /// Creates a new empty configuration.
+    pub fn new() -> TopicConfig {
+        TopicConfig::default()
+    }
+
+    /// Gets a reference to the underlying config map
+    pub fn config_map(&self) -> &HashMap<String, String> {
+        &self.conf_map
+    }
+
+    /// Gets the value of a parameter in the configuration.
+    ///
+    /// Returns the current value set for `key`, or `None` if no value for `key`
+    /// exists.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.conf_map.get(key).map(|val| val.as_str())
+    }
+
+    /// Sets a parameter in the configuration.
+    ///
+    /// If there is an existing value for `key` in the configuration, it is
+    /// overridden with the new `value`.
+    pub fn set<K, V>(&mut self, key: K, value: V) -> &mut TopicConfig
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.conf_map.insert(key.into(), value.into());
+        self
+    }
+
+    /// Removes a parameter from the configuration.
+    pub fn remove<'a>(&'a mut self, key: &str) -> &'a mut TopicConfig {
+        self.conf_map.remove(key);
+        self
+    }
+
+    /// Builds a native librdkafka topic configuration, starting from
+    /// librdkafka's defaults.
+    pub fn create_native_config(&self) -> KafkaResult<NativeTopicConfig> {
+        let conf = unsafe { NativeTopicConfig::from_ptr(rdsys::rd_kafka_topic_conf_new()) };
+        self.apply(&conf)?;
+        Ok(conf)
+    }
+
+    /// Sets all the parameters of this configuration on an existing native
+    /// topic configuration.
+    pub(crate) fn apply(&self, conf: &NativeTopicConfig) -> KafkaResult<()> {
+        let mut err_buf = ErrBuf::new();
+        for (key, value) in &self.conf_map {
+            let key_c = CString::new(key.to_string())?;
+            let value_c = CString::new(value.to_string())?;
+            let ret = unsafe {
+                rdsys::rd_kafka_topic_conf_set(
+                    conf.ptr(),
+                    key_c.as_ptr(),
+                    value_c.as_ptr(),
+                    err_buf.as_mut_ptr(),
+                    err_buf.capacity(),
+                )
+            };
+            if ret.is_error() {
+                return Err(KafkaError::ClientConfig(
+                    ret,
+                    err_buf.to_string(),
+                    key.to_string(),
+                    value.to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromIterator<(String, String)> for TopicConfig {
+    fn from_iter<I>(iter: I) -> TopicConfig
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        TopicConfig {
+            conf_map: iter.into_iter().collect(),
+        }
+    }
+}
+
 /// Return the log level
 fn log_level_from_global_config() -> RDKafkaLogLevel {
     if log_enabled!(target: "librdkafka", DEBUG) {
@@ -316,7 +1260,7 @@ pub trait FromClientConfigAndContext<C: ClientContext>: Sized {
 
 #[cfg(test)]
 mod tests {
-    use super::ClientConfig;
+    use super::*;
 
     #[test]
     fn test_client_config_set_map() {
@@ -329,4 +1273,187 @@ mod tests {
         assert_eq!(config.get("b").unwrap(), "2");
         assert_eq!(config.get("c").unwrap(), "3");
     }
+
+    #[test]
+    fn test_client_config_builder() {
+        let config = ClientConfigBuilder::new()
+            .bootstrap_servers(&["a:9092", "b:9092"])
+            .compression(Compression::Lz4)
+            .acks(Acks::All)
+            .session_timeout(Duration::from_secs(10))
+            .set("socket.keepalive.enable", "true")
+            .build()
+            .unwrap();
+        assert_eq!(config.get("bootstrap.servers"), Some("a:9092,b:9092"));
+        assert_eq!(config.get("compression.codec"), Some("lz4"));
+        assert_eq!(config.get("acks"), Some("all"));
+        assert_eq!(config.get("session.timeout.ms"), Some("10000"));
+
+        for (key, value) in &[
+            ("no.such.property", "1"),
+            ("linger.ms", "forever"),
+            ("socket.keepalive.enable", "maybe"),
+        ] {
+            let result = ClientConfigBuilder::new().set(*key, *value).build();
+            assert!(
+                matches!(&result, Err(KafkaError::ClientConfig(_, _, k, _)) if k == key),
+                "{}",
+                key
+            );
+        }
+    }
+
+    #[test]
+    fn test_client_config_from_vars() {
+        let vars = vec![
+            ("KAFKA_BOOTSTRAP_SERVERS", "kafka:9092"),
+            ("KAFKA_SSL__KEY_LOCATION", "/key.pem"),
+            ("KAFKA_", "ignored"),
+            ("PATH", "/bin"),
+        ];
+        let config = ClientConfig::from_vars(
+            "KAFKA_",
+            vars.into_iter().map(|(k, v)| (k.into(), v.into())),
+        );
+        assert_eq!(config.config_map().len(), 2);
+        assert_eq!(config.get("bootstrap.servers"), Some("kafka:9092"));
+        assert_eq!(config.get("ssl_key.location"), Some("/key.pem"));
+    }
+
+    #[test]
+    fn test_client_config_dump() {
+        let dump = ClientConfig::new()
+            .set("linger.ms", "42")
+            .config_dump()
+            .unwrap();
+        assert_eq!(dump.get("linger.ms").map(String::as_str), Some("42"));
+        assert_eq!(dump.get("client.id").map(String::as_str), Some("rdkafka"));
+    }
+
+    #[test]
+    fn test_client_config_ssl_cert() {
+        let mut config = ClientConfig::new();
+        config.set_ssl_cert(SslCertType::Ca, SslCertEncoding::Pem, b"not a certificate");
+        config.set_ssl_cert(SslCertType::Ca, SslCertEncoding::Pem, b"still not one");
+        assert!(!format!("{:?}", config).contains("not one"));
+        match config.create_native_config() {
+            Err(KafkaError::ClientConfig(_, _, key, value)) => {
+                assert_eq!(key, "Ca");
+                assert_eq!(value, "<13 bytes>");
+            }
+            _ => panic!("invalid certificate accepted"),
+        }
+    }
+
+    #[test]
+    fn test_set_java_properties() {
+        let mut config = ClientConfig::new();
+        config.set("client.id", "rust");
+        config
+            .set_properties(
+                "# comment\n\
+                 ! another comment\n\
+                 \n\
+                 client.id=billing\n\
+                 \x20 bootstrap.servers : kafka-1:9092,\\\n\
+                 \x20   kafka-2:9092\n\
+                 sasl.jaas.config value with spaces\n\
+                 key\\=with\\:separators=x\\u0041\\ty\n\
+                 empty.value\n",
+            )
+            .unwrap();
+        assert_eq!(config.get("client.id"), Some("billing"));
+        assert_eq!(
+            config.get("bootstrap.servers"),
+            Some("kafka-1:9092,kafka-2:9092")
+        );
+        assert_eq!(config.get("sasl.jaas.config"), Some("value with spaces"));
+        assert_eq!(config.get("key=with:separators"), Some("xA\ty"));
+        assert_eq!(config.get("empty.value"), Some(""));
+
+        assert!(matches!(
+            config.set_properties("a=1\nb=\\u12\n"),
+            Err(KafkaError::ClientConfig(..))
+        ));
+        assert_eq!(config.get("a"), None);
+    }
+
+    #[test]
+    fn test_parse_properties() {
+        let table = "\
+## Global configuration properties
+
+Property                                 | C/P | Range           |       Default | Importance | Description
+-----------------------------------------|-----|-----------------|--------------:|------------| --------------------------
+client.id                                |  *  |                 |       rdkafka | low        | Client identifier. <br>*Type: string*
+message.max.bytes                        |  *  | 1000 .. 1000000000 |   1000000 | medium     | Maximum Kafka protocol request message size. <br>*Type: integer*
+
+## Topic configuration properties
+
+Property                                 | C/P | Range           |       Default | Importance | Description
+-----------------------------------------|-----|-----------------|--------------:|------------| --------------------------
+auto.offset.reset                        |  C  | smallest, earliest, largest, latest, error |       largest | high       | Action to take when there is no initial offset. <br>*Type: enum value*
+offset.store.method                      |  C  | file, broker    |        broker | low        | **DEPRECATED** Offset commit store method. <br>*Type: enum value*
+";
+        let properties = parse_properties(table);
+        assert_eq!(properties.len(), 4);
+        assert_eq!(
+            properties[1],
+            ConfigProperty {
+                name: "message.max.bytes".into(),
+                scope: ConfigScope::Global,
+                clients: ConfigClients::All,
+                property_type: "integer".into(),
+                range: Some("1000 .. 1000000000".into()),
+                default: Some("1000000".into()),
+                importance: ConfigImportance::Medium,
+                description: "Maximum Kafka protocol request message size.".into(),
+                deprecated: false,
+            }
+        );
+        assert_eq!(properties[0].range, None);
+        assert_eq!(properties[2].scope, ConfigScope::Topic);
+        assert_eq!(properties[2].clients, ConfigClients::Consumer);
+        assert_eq!(properties[2].importance, ConfigImportance::High);
+        assert_eq!(properties[2].property_type, "enum value");
+        assert!(!properties[2].deprecated);
+        assert!(properties[3].deprecated);
+    }
+
+    #[test]
+    fn test_client_config_validate() {
+        let mut config = ClientConfig::new();
+        config
+            .set("client.id", "test")
+            .set("enable.auto.commit", "false");
+        assert!(config.validate(ConfigClients::Consumer).is_ok());
+        assert!(config.validate(ConfigClients::All).is_ok());
+        assert!(matches!(
+            config.validate(ConfigClients::Producer),
+            Err(KafkaError::ClientConfig(_, _, key, _)) if key == "enable.auto.commit"
+        ));
+
+        config.set("offset.store.method", "broker");
+        assert!(matches!(
+            config.validate(ConfigClients::All),
+            Err(KafkaError::ClientConfig(_, _, key, _)) if key == "offset.store.method"
+        ));
+    }
+
+    #[test]
+    fn test_topic_config() {
+        let config: TopicConfig = vec![("message.timeout.ms".into(), "1234".into())]
+            .into_iter()
+            .collect();
+        let native_config = config.create_native_config().unwrap();
+        assert_eq!(native_config.get("message.timeout.ms").unwrap(), "1234");
+        assert!(native_config.get("not.a.property").is_err());
+
+        let mut config = TopicConfig::new();
+        config.set("message.timeout.ms", "not a number");
+        assert!(matches!(
+            config.create_native_config(),
+            Err(KafkaError::ClientConfig(_, _, key, _)) if key == "message.timeout.ms"
+        ));
+    }
 }
@@ -5,22 +5,23 @@ use std::ffi::CString;
 use std::mem::ManuallyDrop;
 use std::os::raw::c_void;
 use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
 use rdkafka_sys as rdsys;
 use rdkafka_sys::types::*;
 
-use crate::client::{Client, NativeClient, NativeQueue};
+use crate::client::{handle_callback_event, Client, ClientOpaque, NativeClient, NativeQueue};
 use crate::config::{
-    ClientConfig, FromClientConfig, FromClientConfigAndContext, NativeClientConfig,
+    ClientConfig, ConfigClients, FromClientConfig, FromClientConfigAndContext, NativeClientConfig,
 };
 use crate::consumer::{
-    CommitMode, Consumer, ConsumerContext, ConsumerGroupMetadata, DefaultConsumerContext,
-    RebalanceProtocol,
+    CommitMode, ConsumeHooks, Consumer, ConsumerContext, ConsumerEvent, ConsumerGroupMetadata,
+    DefaultConsumerContext, RebalanceProtocol,
 };
-use crate::error::{IsError, KafkaError, KafkaResult, RDKafkaError};
+use crate::error::{err2str, IsError, KafkaError, KafkaResult, RDKafkaError, RDKafkaErrorCode};
 use crate::groups::GroupList;
-use crate::log::trace;
+use crate::log::{enter_span, trace, warn};
 use crate::message::{BorrowedMessage, Message};
 use crate::metadata::Metadata;
 use crate::topic_partition_list::{Offset, TopicPartitionList};
@@ -32,19 +33,22 @@ pub(crate) unsafe extern "C" fn native_commit_cb<C: ConsumerContext>(
     offsets: *mut RDKafkaTopicPartitionList,
     opaque_ptr: *mut c_void,
 ) {
-    let context = &mut *(opaque_ptr as *mut C);
+    enter_span!("rdkafka.commit_callback", success = !err.is_error());
+    let context = &ClientOpaque::<C>::from_ptr(opaque_ptr).context;
     let commit_error = if err.is_error() {
         Err(KafkaError::ConsumerCommit(err.into()))
     } else {
         Ok(())
     };
-    if offsets.is_null() {
-        let tpl = TopicPartitionList::new();
-        context.commit_callback(commit_error, &tpl);
+    let tpl = if offsets.is_null() {
+        ManuallyDrop::new(TopicPartitionList::new())
     } else {
-        let tpl = ManuallyDrop::new(TopicPartitionList::from_ptr(offsets));
-        context.commit_callback(commit_error, &tpl);
+        ManuallyDrop::new(TopicPartitionList::from_ptr(offsets))
+    };
+    for interceptor in context.interceptors() {
+        handle_callback_event(|| interceptor.on_commit(&commit_error, &tpl));
     }
+    context.commit_callback(commit_error, &tpl);
 }
 
 /// Native rebalance callback. This callback will run on every rebalance, and it will call the
@@ -55,9 +59,14 @@ unsafe extern "C" fn native_rebalance_cb<C: ConsumerContext>(
     native_tpl: *mut RDKafkaTopicPartitionList,
     opaque_ptr: *mut c_void,
 ) {
-    let context = &mut *(opaque_ptr as *mut C);
+    let context = &ClientOpaque::<C>::from_ptr(opaque_ptr).context;
     let native_client = ManuallyDrop::new(NativeClient::from_ptr(rk));
     let mut tpl = ManuallyDrop::new(TopicPartitionList::from_ptr(native_tpl));
+    enter_span!(
+        "rdkafka.rebalance",
+        event = ?err,
+        partitions = tpl.count(),
+    );
     context.rebalance(&native_client, err, &mut tpl);
 }
 
@@ -71,6 +80,9 @@ where
 {
     client: Client<C>,
     main_queue_min_poll_interval: Timeout,
+    closed: AtomicBool,
+    group_id: Option<String>,
+    group_instance_id: Option<String>,
 }
 
 impl FromClientConfig for BaseConsumer {
@@ -82,6 +94,7 @@ impl FromClientConfig for BaseConsumer {
 /// Creates a new `BaseConsumer` starting from a `ClientConfig`.
 impl<C: ConsumerContext> FromClientConfigAndContext<C> for BaseConsumer<C> {
     fn from_config_and_context(config: &ClientConfig, context: C) -> KafkaResult<BaseConsumer<C>> {
+        config.validate_if_strict(ConfigClients::Consumer)?;
         BaseConsumer::new(config, config.create_native_config()?, context)
     }
 }
@@ -115,6 +128,9 @@ where
         Ok(BaseConsumer {
             client,
             main_queue_min_poll_interval,
+            closed: AtomicBool::new(false),
+            group_id: config.get("group.id").map(str::to_owned),
+            group_instance_id: config.get("group.instance.id").map(str::to_owned),
         })
     }
 
@@ -154,8 +170,104 @@ where
     ///
     /// The returned message lives in the memory of the consumer and cannot outlive it.
     pub fn poll<T: Into<Timeout>>(&self, timeout: T) -> Option<KafkaResult<BorrowedMessage<'_>>> {
-        self.poll_raw(timeout.into())
-            .map(|ptr| unsafe { BorrowedMessage::from_consumer(ptr, self) })
+        enter_span!("rdkafka.poll");
+        let result = self
+            .poll_raw(timeout.into())
+            .map(|ptr| unsafe { BorrowedMessage::from_consumer(ptr, self) });
+        if let Some(result) = &result {
+            self.context().consumed(result);
+        }
+        #[cfg(feature = "tracing")]
+        if let Some(Ok(message)) = &result {
+            tracing::debug!(
+                topic = message.topic(),
+                partition = message.partition(),
+                offset = message.offset(),
+                "Message received"
+            );
+        }
+        result
+    }
+
+    /// Polls the consumer for the next event.
+    ///
+    /// This is equivalent to [`poll`](BaseConsumer::poll), except that the
+    /// end of a partition is reported as a [`ConsumerEvent::PartitionEof`]
+    /// that identifies the topic, partition and offset, rather than as a
+    /// [`KafkaError::PartitionEOF`] error, which makes it easier to tell when
+    /// a backfill is complete.
+    pub fn poll_event<T: Into<Timeout>>(&self, timeout: T) -> Option<ConsumerEvent<'_>> {
+        enter_span!("rdkafka.poll");
+        let ptr = self.poll_raw(timeout.into())?;
+        if let Some(event) = ConsumerEvent::partition_eof(&ptr) {
+            return Some(event);
+        }
+        let result = unsafe { BorrowedMessage::from_consumer(ptr, self) };
+        self.context().consumed(&result);
+        Some(match result {
+            Ok(message) => ConsumerEvent::Message(message),
+            Err(e) => ConsumerEvent::Error(e),
+        })
+    }
+
+    /// Polls the consumer for up to `max_messages` messages at once.
+    ///
+    /// This differs from [`poll`](BaseConsumer::poll) in that all the
+    /// messages are fetched from librdkafka in a single call, which reduces
+    /// the per-message overhead for consumers with a high throughput. The call
+    /// blocks until `max_messages` messages are available or the timeout
+    /// expires, whichever happens first, and returns the messages that were
+    /// received, which may be none. The returned vector can also contain the
+    /// consumer errors, such as the end of a partition, that occurred in
+    /// between the messages.
+    ///
+    /// As with `poll`, callbacks are served while the call blocks. The main
+    /// queue is however only polled once per call, so the timeout should be
+    /// kept shorter than the
+    /// [`main_queue_min_poll_interval`](ConsumerContext::main_queue_min_poll_interval)
+    /// unless the main queue is redirected to the consumer queue.
+    ///
+    /// This requires the `group.id` property to be set. The returned messages
+    /// live in the memory of the consumer and cannot outlive it.
+    pub fn consume_batch<T: Into<Timeout>>(
+        &self,
+        max_messages: usize,
+        timeout: T,
+    ) -> KafkaResult<Vec<KafkaResult<BorrowedMessage<'_>>>> {
+        enter_span!("rdkafka.consume_batch");
+        if max_messages == 0 {
+            return Ok(Vec::new());
+        }
+        let queue = self.client.consumer_queue().ok_or_else(|| {
+            let code = RDKafkaErrorCode::UnknownGroup;
+            KafkaError::MessageConsumption(code, err2str(code))
+        })?;
+        unsafe { rdsys::rd_kafka_poll(self.client.native_ptr(), 0) };
+        let mut ptrs: Vec<*mut RDKafkaMessage> = Vec::with_capacity(max_messages);
+        let ret = unsafe {
+            rdsys::rd_kafka_consume_batch_queue(
+                queue.ptr(),
+                timeout.into().as_millis(),
+                ptrs.as_mut_ptr(),
+                max_messages,
+            )
+        };
+        if ret < 0 {
+            let code = unsafe { rdsys::rd_kafka_last_error() }.into();
+            return Err(KafkaError::MessageConsumption(code, err2str(code)));
+        }
+        unsafe { ptrs.set_len(ret as usize) };
+        let messages = ptrs
+            .into_iter()
+            .map(|ptr| unsafe {
+                let ptr = NativePtr::from_ptr(ptr).expect("batch messages are not null");
+                BorrowedMessage::from_consumer(ptr, self)
+            })
+            .collect::<Vec<_>>();
+        for message in &messages {
+            self.context().consumed(message);
+        }
+        Ok(messages)
     }
 
     /// Returns an iterator over the available messages.
@@ -261,7 +373,14 @@ where
                 self.client.native_ptr(),
             ))
         }?;
-        Some(ConsumerGroupMetadata(ptr))
+        // librdkafka does not expose the fields of the metadata, so they are
+        // taken from the inputs it was built from.
+        Some(ConsumerGroupMetadata::new(
+            ptr,
+            self.group_id.clone(),
+            self.member_id(),
+            self.group_instance_id.clone(),
+        ))
     }
 
     fn subscribe(&self, topics: &[&str]) -> KafkaResult<()> {
@@ -301,6 +420,18 @@ where
         Ok(())
     }
 
+    fn close(&self) -> KafkaResult<()> {
+        if self.closed.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+        trace!("Closing consumer: {:?}", self.client.native_ptr());
+        let ret_code = unsafe { rdsys::rd_kafka_consumer_close(self.client.native_ptr()) };
+        if ret_code.is_error() {
+            return Err(KafkaError::Global(ret_code.into()));
+        }
+        Ok(())
+    }
+
     fn incremental_assign(&self, assignment: &TopicPartitionList) -> KafkaResult<()> {
         let ret = unsafe {
             RDKafkaError::from_ptr(rdsys::rd_kafka_incremental_assign(
@@ -355,6 +486,11 @@ where
         topic_partition_list: &TopicPartitionList,
         mode: CommitMode,
     ) -> KafkaResult<()> {
+        enter_span!(
+            "rdkafka.commit",
+            partitions = topic_partition_list.count(),
+            mode = ?mode,
+        );
         let error = unsafe {
             rdsys::rd_kafka_commit(
                 self.client.native_ptr(),
@@ -370,6 +506,7 @@ where
     }
 
     fn commit_consumer_state(&self, mode: CommitMode) -> KafkaResult<()> {
+        enter_span!("rdkafka.commit", mode = ?mode);
         let error = unsafe {
             rdsys::rd_kafka_commit(self.client.native_ptr(), ptr::null_mut(), mode as i32)
         };
@@ -381,6 +518,13 @@ where
     }
 
     fn commit_message(&self, message: &BorrowedMessage<'_>, mode: CommitMode) -> KafkaResult<()> {
+        enter_span!(
+            "rdkafka.commit",
+            topic = message.topic(),
+            partition = message.partition(),
+            offset = message.offset(),
+            mode = ?mode,
+        );
         let error = unsafe {
             rdsys::rd_kafka_commit_message(self.client.native_ptr(), message.ptr(), mode as i32)
         };
@@ -519,13 +663,7 @@ where
     }
 
     fn position(&self) -> KafkaResult<TopicPartitionList> {
-        let tpl = self.assignment()?;
-        let error = unsafe { rdsys::rd_kafka_position(self.client.native_ptr(), tpl.ptr()) };
-        if error.is_error() {
-            Err(KafkaError::MetadataFetch(error.into()))
-        } else {
-            Ok(tpl)
-        }
+        self.positions(self.assignment()?)
     }
 
     fn fetch_metadata<T: Into<Timeout>>(
@@ -584,8 +722,10 @@ where
     C: ConsumerContext,
 {
     fn drop(&mut self) {
-        trace!("Destroying consumer: {:?}", self.client.native_ptr()); // TODO: fix me (multiple executions ?)
-        unsafe { rdsys::rd_kafka_consumer_close(self.client.native_ptr()) };
+        trace!("Destroying consumer: {:?}", self.client.native_ptr());
+        if let Err(e) = self.close() {
+            warn!("Failed to close consumer: {}", e);
+        }
         trace!("Consumer destroyed: {:?}", self.client.native_ptr());
     }
 }
@@ -656,13 +796,17 @@ where
     /// associated consumer regularly, even if no messages are expected, to
     /// serve callbacks.
     pub fn poll<T: Into<Timeout>>(&self, timeout: T) -> Option<KafkaResult<BorrowedMessage<'_>>> {
-        unsafe {
+        let result = unsafe {
             NativePtr::from_ptr(rdsys::rd_kafka_consume_queue(
                 self.queue.ptr(),
                 timeout.into().as_millis(),
             ))
         }
-        .map(|ptr| unsafe { BorrowedMessage::from_consumer(ptr, &self.consumer) })
+        .map(|ptr| unsafe { BorrowedMessage::from_consumer(ptr, &self.consumer) });
+        if let Some(result) = &result {
+            self.consumer.context().consumed(result);
+        }
+        result
     }
 
     /// Sets a callback that will be invoked whenever the queue becomes
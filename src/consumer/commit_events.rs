@@ -0,0 +1,178 @@
+//! Streams of offset commit results.
+//!
+//! Automatic commits run in the background, and their failures are only
+//! reported to [`ConsumerContext::commit_callback`], so a consumer keeps
+//! consuming even while none of its offsets are being committed. A
+//! [`CommitStream`] surfaces the results of all commits, automatic or not, as
+//! a [`Stream`] that can be watched by a separate task, e.g. to raise an alert
+//! when commits start failing.
+//!
+//! ```no_run
+//! use futures::StreamExt;
+//!
+//! use rdkafka::config::ClientConfig;
+//! use rdkafka::consumer::commit_events::CommitEventsContext;
+//! use rdkafka::consumer::{Consumer, StreamConsumer};
+//!
+//! # async fn run() {
+//! let (context, mut commits) = CommitEventsContext::new(16);
+//! let consumer: StreamConsumer<CommitEventsContext> = ClientConfig::new()
+//!     .set("bootstrap.servers", "localhost:9092")
+//!     .set("group.id", "orders")
+//!     .create_with_context(context)
+//!     .unwrap();
+//! consumer.subscribe(&["orders"]).unwrap();
+//! tokio::spawn(async move {
+//!     while let Some(event) = commits.next().await {
+//!         if let Err(e) = event.result {
+//!             eprintln!("failed to commit {:?}: {}", event.offsets, e);
+//!         }
+//!     }
+//! });
+//! loop {
+//!     let _message = consumer.recv().await;
+//! }
+//! # }
+//! ```
+
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::task::{Context, Poll};
+
+use futures_channel::mpsc;
+use futures_util::stream::Stream;
+
+use crate::client::ClientContext;
+use crate::consumer::ConsumerContext;
+use crate::error::KafkaResult;
+use crate::log::warn;
+use crate::topic_partition_list::TopicPartitionList;
+
+/// The result of an offset commit.
+#[derive(Debug)]
+pub struct CommitEvent {
+    /// Whether the commit succeeded.
+    pub result: KafkaResult<()>,
+    /// The offsets that were committed, with the error of each partition if
+    /// the commit failed.
+    pub offsets: TopicPartitionList,
+}
+
+/// Reports the results of offset commits to a [`CommitStream`].
+///
+/// Contexts that want to surface commit results must call
+/// [`CommitReporter::report`] from their
+/// [`commit_callback`](ConsumerContext::commit_callback).
+/// [`CommitEventsContext`] does nothing else.
+#[derive(Debug)]
+pub struct CommitReporter {
+    tx: Mutex<mpsc::Sender<CommitEvent>>,
+}
+
+impl CommitReporter {
+    /// Passes the result of a commit to the stream.
+    ///
+    /// If the stream is full, because it is not consumed fast enough, the
+    /// result is dropped and a warning is logged. If the stream was dropped,
+    /// the result is silently discarded.
+    pub fn report(&self, result: KafkaResult<()>, offsets: &TopicPartitionList) {
+        let event = CommitEvent {
+            result,
+            offsets: offsets.clone(),
+        };
+        if let Err(e) = self.tx.lock().unwrap().try_send(event) {
+            if e.is_full() {
+                warn!("Commit event stream is full, dropping commit result");
+            }
+        }
+    }
+}
+
+/// A stream of the results of offset commits.
+///
+/// The stream terminates once its [`CommitReporter`] is dropped, i.e. when
+/// the consumer that owns the reporter is dropped.
+#[derive(Debug)]
+pub struct CommitStream {
+    rx: mpsc::Receiver<CommitEvent>,
+}
+
+impl Stream for CommitStream {
+    type Item = CommitEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<CommitEvent>> {
+        Pin::new(&mut self.rx).poll_next(cx)
+    }
+}
+
+/// Creates a connected reporter and stream, which buffers up to
+/// `capacity + 1` commit results.
+pub fn commit_events(capacity: usize) -> (CommitReporter, CommitStream) {
+    let (tx, rx) = mpsc::channel(capacity);
+    (CommitReporter { tx: Mutex::new(tx) }, CommitStream { rx })
+}
+
+/// A consumer context that reports the results of offset commits to a
+/// [`CommitStream`], and otherwise behaves like the default context.
+#[derive(Debug)]
+pub struct CommitEventsContext {
+    reporter: CommitReporter,
+}
+
+impl CommitEventsContext {
+    /// Creates a context and the stream of its commit results.
+    ///
+    /// See [`commit_events`] for the meaning of `capacity`.
+    pub fn new(capacity: usize) -> (CommitEventsContext, CommitStream) {
+        let (reporter, stream) = commit_events(capacity);
+        (CommitEventsContext { reporter }, stream)
+    }
+}
+
+impl ClientContext for CommitEventsContext {}
+
+impl ConsumerContext for CommitEventsContext {
+    fn commit_callback(&self, result: KafkaResult<()>, offsets: &TopicPartitionList) {
+        self.reporter.report(result, offsets);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+    use futures::StreamExt;
+
+    use super::*;
+    use crate::error::{KafkaError, RDKafkaErrorCode};
+
+    #[test]
+    fn test_commit_events() {
+        let (reporter, mut stream) = commit_events(0);
+        let mut tpl = TopicPartitionList::new();
+        tpl.add_partition("orders", 0);
+        reporter.report(Ok(()), &tpl);
+        // The channel has room for one message per sender beyond its
+        // capacity, so this result is dropped.
+        reporter.report(
+            Err(KafkaError::ConsumerCommit(
+                RDKafkaErrorCode::RequestTimedOut,
+            )),
+            &tpl,
+        );
+
+        let event = block_on(stream.next()).unwrap();
+        assert!(event.result.is_ok());
+        assert_eq!(event.offsets, tpl);
+
+        reporter.report(
+            Err(KafkaError::ConsumerCommit(
+                RDKafkaErrorCode::RequestTimedOut,
+            )),
+            &tpl,
+        );
+        drop(reporter);
+        let event = block_on(stream.next()).unwrap();
+        assert!(event.result.is_err());
+        assert!(block_on(stream.next()).is_none());
+    }
+}
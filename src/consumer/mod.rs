@@ -1,29 +1,45 @@
 //! Kafka consumers.
 
+use std::cmp;
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::os::raw::c_void;
 use std::ptr;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use rdkafka_sys as rdsys;
 use rdkafka_sys::types::*;
 
-use crate::client::{Client, ClientContext, NativeClient};
-use crate::error::KafkaResult;
+use crate::client::{handle_callback_event, Client, ClientContext, NativeClient};
+use crate::error::{IsError, KafkaError, KafkaResult};
 use crate::groups::GroupList;
+use crate::interceptor::intercept_consume;
 use crate::log::{error, trace};
 use crate::message::BorrowedMessage;
 use crate::metadata::Metadata;
+use crate::producer::DlqProducer;
 use crate::topic_partition_list::{Offset, TopicPartitionList};
-use crate::util::{cstr_to_owned, KafkaDrop, NativePtr, Timeout};
+use crate::util::{cstr_to_owned, AsyncRuntime, KafkaDrop, NativePtr, Timeout};
 
 pub mod base_consumer;
+pub mod commit_events;
+pub mod sharded_consumer;
 pub mod stream_consumer;
+#[cfg(feature = "gzip")]
+#[cfg_attr(docsrs, doc(cfg(feature = "gzip")))]
+pub mod transcoding;
+pub mod typed_consumer;
 
 // Re-exports.
 #[doc(inline)]
 pub use self::base_consumer::BaseConsumer;
 #[doc(inline)]
-pub use self::stream_consumer::{MessageStream, StreamConsumer};
+pub use self::sharded_consumer::ShardedConsumer;
+#[doc(inline)]
+pub use self::stream_consumer::{EventStream, MessageStream, StreamConsumer};
+#[doc(inline)]
+pub use self::typed_consumer::{TypedConsumer, TypedMessage};
 
 /// Rebalance information.
 #[derive(Clone, Debug)]
@@ -36,6 +52,50 @@ pub enum Rebalance<'a> {
     Error(String),
 }
 
+/// An event returned by [`BaseConsumer::poll_event`] or
+/// [`StreamConsumer::recv_event`].
+#[derive(Debug)]
+pub enum ConsumerEvent<'a> {
+    /// A message was received.
+    Message(BorrowedMessage<'a>),
+    /// The consumer reached the end of a partition, i.e. it consumed all the
+    /// messages that were in the partition when it was fetched.
+    ///
+    /// This is only reported if the `enable.partition.eof` property is set to
+    /// `true`, and then at most once each time the end of the partition is
+    /// reached.
+    PartitionEof {
+        /// The topic of the partition.
+        topic: String,
+        /// The partition number.
+        partition: i32,
+        /// The offset after the last message of the partition.
+        offset: i64,
+    },
+    /// A consumer error occurred.
+    Error(KafkaError),
+}
+
+impl<'a> ConsumerEvent<'a> {
+    /// Returns the event of a message, or `None` if the message is not the
+    /// end of a partition.
+    fn partition_eof(message: &RDKafkaMessage) -> Option<ConsumerEvent<'a>> {
+        if message.err != RDKafkaRespErr::RD_KAFKA_RESP_ERR__PARTITION_EOF {
+            return None;
+        }
+        let topic = if message.rkt.is_null() {
+            String::new()
+        } else {
+            unsafe { cstr_to_owned(rdsys::rd_kafka_topic_name(message.rkt)) }
+        };
+        Some(ConsumerEvent::PartitionEof {
+            topic,
+            partition: message.partition,
+            offset: message.offset,
+        })
+    }
+}
+
 /// Consumer-specific context.
 ///
 /// This user-defined object can be used to provide custom callbacks for
@@ -112,6 +172,17 @@ pub trait ConsumerContext: ClientContext {
     #[allow(unused_variables)]
     fn commit_callback(&self, result: KafkaResult<()>, offsets: &TopicPartitionList) {}
 
+    /// Called when the consumer was not polled within `max.poll.interval.ms`,
+    /// and thus left its group, as reported by a
+    /// [`KafkaError::MaxPollExceeded`] consumer error.
+    ///
+    /// This usually means that processing the messages takes longer than
+    /// allowed, and can be used to e.g. pause the intake of new work. The
+    /// consumer rejoins the group on the next poll. The callback runs on the
+    /// thread that polls the consumer, right before the error is returned.
+    #[allow(unused_variables)]
+    fn max_poll_exceeded(&self, reason: &str) {}
+
     /// Returns the minimum interval at which to poll the main queue, which
     /// services the logging, stats, and error callbacks.
     ///
@@ -131,6 +202,22 @@ pub trait ConsumerContext: ClientContext {
     }
 }
 
+/// The hooks of a [`ConsumerContext`] that observe consumed messages, as an
+/// object-safe trait, so that message streams need not be generic over the
+/// context.
+pub(crate) trait ConsumeHooks: Send + Sync {
+    fn consumed(&self, result: &KafkaResult<BorrowedMessage<'_>>);
+}
+
+impl<C: ConsumerContext> ConsumeHooks for C {
+    fn consumed(&self, result: &KafkaResult<BorrowedMessage<'_>>) {
+        intercept_consume(self.interceptors(), result);
+        if let Err(KafkaError::MaxPollExceeded(reason)) = result {
+            handle_callback_event(|| self.max_poll_exceeded(reason));
+        }
+    }
+}
+
 /// An inert [`ConsumerContext`] that can be used when no customizations are
 /// needed.
 #[derive(Clone, Debug, Default)]
@@ -168,14 +255,67 @@ pub enum CommitMode {
 
 /// Consumer group metadata.
 ///
-/// For use with [`Producer::send_offsets_to_transaction`].
+/// For use with [`Producer::send_offsets_to_transaction`]. The metadata is a
+/// snapshot of the group membership of the consumer at the time it was
+/// retrieved; its generation ID and member ID identify the consumer to the
+/// group coordinator, so that offsets committed by a consumer that was fenced
+/// by a rebalance are rejected. librdkafka does not expose the generation ID,
+/// but the other identifiers are available from the accessors.
 ///
 /// [`Producer::send_offsets_to_transaction`]: crate::producer::Producer::send_offsets_to_transaction
-pub struct ConsumerGroupMetadata(NativePtr<RDKafkaConsumerGroupMetadata>);
+pub struct ConsumerGroupMetadata {
+    ptr: NativePtr<RDKafkaConsumerGroupMetadata>,
+    group_id: Option<String>,
+    member_id: Option<String>,
+    group_instance_id: Option<String>,
+}
 
 impl ConsumerGroupMetadata {
+    pub(crate) fn new(
+        ptr: NativePtr<RDKafkaConsumerGroupMetadata>,
+        group_id: Option<String>,
+        member_id: Option<String>,
+        group_instance_id: Option<String>,
+    ) -> ConsumerGroupMetadata {
+        ConsumerGroupMetadata {
+            ptr,
+            group_id,
+            member_id,
+            group_instance_id,
+        }
+    }
+
     pub(crate) fn ptr(&self) -> *const RDKafkaConsumerGroupMetadata {
-        self.0.ptr()
+        self.ptr.ptr()
+    }
+
+    /// Returns the ID of the consumer group, as specified by the `group.id`
+    /// configuration property.
+    pub fn group_id(&self) -> Option<&str> {
+        self.group_id.as_deref()
+    }
+
+    /// Returns the member ID assigned to the consumer by the group
+    /// coordinator when the metadata was retrieved, or `None` if the consumer
+    /// had not joined the group yet.
+    pub fn member_id(&self) -> Option<&str> {
+        self.member_id.as_deref()
+    }
+
+    /// Returns the static group membership ID of the consumer, as specified
+    /// by the `group.instance.id` configuration property, if any.
+    pub fn group_instance_id(&self) -> Option<&str> {
+        self.group_instance_id.as_deref()
+    }
+}
+
+impl fmt::Debug for ConsumerGroupMetadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConsumerGroupMetadata")
+            .field("group_id", &self.group_id)
+            .field("member_id", &self.member_id)
+            .field("group_instance_id", &self.group_instance_id)
+            .finish()
     }
 }
 
@@ -233,6 +373,32 @@ where
     /// Unsubscribes the current subscription list.
     fn unsubscribe(&self);
 
+    /// Closes the consumer.
+    ///
+    /// Closing the consumer commits the final offsets if `enable.auto.commit`
+    /// is set, revokes the assignment, which invokes the rebalance callbacks
+    /// of the context, and leaves the consumer group, so that the partitions
+    /// of the consumer are reassigned right away rather than after the
+    /// `session.timeout.ms` of the group expires. This method blocks until
+    /// all of that is done.
+    ///
+    /// The consumer is closed when it is dropped if this method was not
+    /// called, but calling it explicitly allows errors to be handled. Once
+    /// the consumer is closed, it stops fetching messages and should only be
+    /// dropped. Calling this method again on a [`BaseConsumer`] or a
+    /// [`StreamConsumer`] has no effect.
+    ///
+    /// The default implementation closes the underlying client. Consumers
+    /// that wrap another consumer should delegate to it instead, so that it
+    /// knows it was closed.
+    fn close(&self) -> KafkaResult<()> {
+        let ret_code = unsafe { rdsys::rd_kafka_consumer_close(self.client().native_ptr()) };
+        if ret_code.is_error() {
+            return Err(KafkaError::Global(ret_code.into()));
+        }
+        Ok(())
+    }
+
     /// Manually assigns topics and partitions to the consumer. If used,
     /// automatic consumer rebalance won't be activated.
     fn assign(&self, assignment: &TopicPartitionList) -> KafkaResult<()>;
@@ -287,6 +453,44 @@ where
     /// from the message and adds 1 to the offset of the message.
     fn commit_message(&self, message: &BorrowedMessage<'_>, mode: CommitMode) -> KafkaResult<()>;
 
+    /// Skips the provided message, typically because it cannot be processed.
+    ///
+    /// The offset following the message is committed synchronously, so that
+    /// the message is not consumed again by this consumer group, even after a
+    /// restart or a rebalance. See [`Consumer::skip_to_quarantine`] to keep a
+    /// copy of the message for later inspection.
+    fn skip(&self, message: &BorrowedMessage<'_>) -> KafkaResult<()> {
+        self.commit_message(message, CommitMode::Sync)
+    }
+
+    /// Like [`Consumer::skip`], but the message is first forwarded to a dead
+    /// letter queue with [`DlqProducer::forward_blocking`], with the `error`
+    /// that prevented its processing.
+    ///
+    /// The forwarded message has the key, payload, timestamp and headers of
+    /// the original message, as well as the `dlq.*` headers described in
+    /// [`DlqProducer`]. The offset is only committed once the forwarded
+    /// message is delivered. If the delivery fails, its error is returned,
+    /// and if it is not reported within `timeout`, an `OperationTimedOut`
+    /// error is returned; in both cases the offset is not committed.
+    fn skip_to_quarantine<PC, R, E, T>(
+        &self,
+        message: &BorrowedMessage<'_>,
+        dlq: &DlqProducer<PC, R>,
+        error: &E,
+        timeout: T,
+    ) -> KafkaResult<()>
+    where
+        PC: ClientContext + 'static,
+        R: AsyncRuntime,
+        E: Display + ?Sized,
+        T: Into<Timeout>,
+        Self: Sized,
+    {
+        dlq.forward_blocking(message, error, 0, timeout)?;
+        self.skip(message)
+    }
+
     /// Stores offset to be used on the next (auto)commit. When
     /// using this `enable.auto.offset.store` should be set to `false` in the
     /// config.
@@ -344,6 +548,26 @@ where
     /// Retrieve current positions (offsets) for topics and partitions.
     fn position(&self) -> KafkaResult<TopicPartitionList>;
 
+    /// Retrieves the current positions (offsets) for the specified topics and
+    /// partitions.
+    ///
+    /// The position of a partition is the offset of the next message that
+    /// will be returned to the application, and is set to
+    /// [`Offset::Invalid`] for partitions that the consumer has not fetched
+    /// from. Comparing the positions with the offsets returned by
+    /// [`Consumer::committed_offsets`] and with the high watermarks of the
+    /// partitions gives the exact lag of the consumer. Unlike
+    /// [`Consumer::committed_offsets`], this method does not query the
+    /// brokers.
+    fn positions(&self, tpl: TopicPartitionList) -> KafkaResult<TopicPartitionList> {
+        let error = unsafe { rdsys::rd_kafka_position(self.client().native_ptr(), tpl.ptr()) };
+        if error.is_error() {
+            Err(KafkaError::MetadataFetch(error.into()))
+        } else {
+            Ok(tpl)
+        }
+    }
+
     /// Returns the metadata information for the specified topic, or for all
     /// topics in the cluster if no topic is specified.
     fn fetch_metadata<T>(&self, topic: Option<&str>, timeout: T) -> KafkaResult<Metadata>
@@ -362,6 +586,80 @@ where
         T: Into<Timeout>,
         Self: Sized;
 
+    /// Returns the member ID that the group coordinator assigned to the
+    /// consumer, or `None` if the consumer has not joined a group yet.
+    ///
+    /// With static group membership, i.e. when `group.instance.id` is set,
+    /// the member ID changes every time the consumer rejoins the group, while
+    /// the instance ID stays the same. If another consumer joins the group
+    /// with the same instance ID, this consumer is fenced: its
+    /// [`ClientContext::error`] callback receives a
+    /// [`KafkaError::InstanceFenced`] error, and the consumer is no longer
+    /// usable.
+    ///
+    /// [`ClientContext::error`]: crate::client::ClientContext::error
+    /// [`KafkaError::InstanceFenced`]: crate::error::KafkaError::InstanceFenced
+    fn member_id(&self) -> Option<String> {
+        unsafe {
+            let ptr = rdsys::rd_kafka_memberid(self.client().native_ptr());
+            if ptr.is_null() {
+                return None;
+            }
+            let member_id = cstr_to_owned(ptr);
+            rdsys::rd_kafka_mem_free(self.client().native_ptr(), ptr as *mut c_void);
+            Some(member_id).filter(|id| !id.is_empty())
+        }
+    }
+
+    /// Returns the lag of the consumer on each of its assigned partitions,
+    /// i.e. the number of messages between its position and the high
+    /// watermark of the partition, keyed by topic and partition.
+    ///
+    /// Partitions that the consumer has not fetched from yet are measured
+    /// from their committed offset, and are left out if they have none. The
+    /// timeout applies to the whole operation, which queries the committed
+    /// offsets and the watermarks of the partitions from the brokers.
+    fn lag<T>(&self, timeout: T) -> KafkaResult<HashMap<(String, i32), i64>>
+    where
+        T: Into<Timeout>,
+        Self: Sized,
+    {
+        let timeout = timeout.into();
+        let start = Instant::now();
+        let remaining = || match timeout {
+            Timeout::After(t) => Timeout::After(t.saturating_sub(start.elapsed())),
+            Timeout::Never => Timeout::Never,
+        };
+
+        let positions = self.positions(self.assignment()?)?;
+        let mut offsets = HashMap::new();
+        let mut unfetched = TopicPartitionList::new();
+        for elem in positions.elements() {
+            match elem.offset() {
+                Offset::Offset(offset) => {
+                    offsets.insert((elem.topic().to_owned(), elem.partition()), offset);
+                }
+                _ => {
+                    unfetched.add_partition(elem.topic(), elem.partition());
+                }
+            }
+        }
+        if unfetched.count() > 0 {
+            for elem in self.committed_offsets(unfetched, remaining())?.elements() {
+                if let Offset::Offset(offset) = elem.offset() {
+                    offsets.insert((elem.topic().to_owned(), elem.partition()), offset);
+                }
+            }
+        }
+
+        let mut lag = HashMap::with_capacity(offsets.len());
+        for ((topic, partition), offset) in offsets {
+            let (_, high) = self.fetch_watermarks(&topic, partition, remaining())?;
+            lag.insert((topic, partition), cmp::max(high - offset, 0));
+        }
+        Ok(lag)
+    }
+
     /// Returns the group membership information for the given group. If no group is
     /// specified, all groups will be returned.
     fn fetch_group_list<T>(&self, group: Option<&str>, timeout: T) -> KafkaResult<GroupList>
@@ -377,4 +675,12 @@ where
 
     /// Reports the rebalance protocol in use.
     fn rebalance_protocol(&self) -> RebalanceProtocol;
+
+    /// Reports whether the current assignment was lost rather than revoked
+    /// cooperatively.
+    ///
+    /// See [`NativeClient::assignment_lost`] for details.
+    fn assignment_lost(&self) -> bool {
+        self.client().native_client().assignment_lost()
+    }
 }
@@ -0,0 +1,268 @@
+//! Thread-per-shard consumers.
+//!
+//! A [`ShardedConsumer`] runs a fixed number of [`BaseConsumer`]s that share
+//! the same configuration, and therefore the same consumer group. Each
+//! consumer lives on its own dedicated thread, so that the partitions of the
+//! subscribed topics are spread across the shards by the group rebalance
+//! protocol, and messages from different shards are processed in parallel.
+//!
+//! All the shards are stopped together when the `ShardedConsumer` is dropped
+//! or [`shutdown`](ShardedConsumer::shutdown) is called: every thread is
+//! signaled first, and only then are the threads joined, so that all the
+//! consumers leave the group at roughly the same time and trigger a single
+//! rebalance.
+//!
+//! A panic in the message handler does not stop its shard: the panic is
+//! logged, counted in [`ShardStats::panics`], and the shard keeps polling.
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::config::{ClientConfig, FromClientConfigAndContext};
+use crate::consumer::{BaseConsumer, Consumer, ConsumerContext};
+use crate::error::{KafkaError, KafkaResult};
+use crate::log::{error, trace, warn};
+use crate::message::BorrowedMessage;
+use crate::util::CallbackThread;
+
+/// Counters maintained by a single shard.
+#[derive(Debug, Default)]
+struct ShardCounters {
+    messages: AtomicU64,
+    errors: AtomicU64,
+    panics: AtomicU64,
+}
+
+/// A snapshot of the activity of a single shard.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ShardStats {
+    /// The number of messages successfully received by the shard.
+    pub messages: u64,
+    /// The number of errors returned while polling the shard.
+    pub errors: u64,
+    /// The number of times the handler panicked on the shard.
+    pub panics: u64,
+}
+
+/// A snapshot of the activity of all the shards of a [`ShardedConsumer`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ShardedConsumerStats {
+    /// The statistics of each shard, indexed by shard number.
+    pub shards: Vec<ShardStats>,
+}
+
+impl ShardedConsumerStats {
+    /// Returns the total number of messages received across all shards.
+    pub fn messages(&self) -> u64 {
+        self.shards.iter().map(|s| s.messages).sum()
+    }
+
+    /// Returns the total number of errors returned across all shards.
+    pub fn errors(&self) -> u64 {
+        self.shards.iter().map(|s| s.errors).sum()
+    }
+
+    /// Returns the total number of handler panics across all shards.
+    pub fn panics(&self) -> u64 {
+        self.shards.iter().map(|s| s.panics).sum()
+    }
+}
+
+struct Shard {
+    counters: Arc<ShardCounters>,
+    handle: Option<JoinHandle<()>>,
+}
+
+/// A group of [`BaseConsumer`]s, each polled by its own thread.
+///
+/// See the [module-level documentation](self) for details.
+#[must_use = "The sharded consumer will stop immediately if unused"]
+pub struct ShardedConsumer {
+    shards: Vec<Shard>,
+    should_stop: Arc<AtomicBool>,
+}
+
+impl ShardedConsumer {
+    /// Creates `shard_count` consumers from the provided configuration,
+    /// subscribes each of them to `topics`, and starts one polling thread per
+    /// consumer.
+    ///
+    /// The `context_fn` is called once per shard, with the shard number, to
+    /// create the context of the corresponding consumer. The `handler` is
+    /// invoked on the shard's thread for every message or error received by
+    /// that shard.
+    ///
+    /// All the consumers are created and subscribed before any thread is
+    /// started, so that configuration and subscription errors are returned
+    /// to the caller. If a thread cannot be started, the shards that were
+    /// already started are stopped, and a [`KafkaError::ClientCreation`]
+    /// error is returned.
+    pub fn new<C, F, H>(
+        config: &ClientConfig,
+        shard_count: usize,
+        topics: &[&str],
+        context_fn: F,
+        handler: H,
+    ) -> KafkaResult<ShardedConsumer>
+    where
+        C: ConsumerContext + 'static,
+        F: Fn(usize) -> C,
+        H: Fn(usize, KafkaResult<BorrowedMessage<'_>>) + Send + Sync + 'static,
+    {
+        let consumers = (0..shard_count)
+            .map(|n| {
+                let consumer = BaseConsumer::from_config_and_context(config, context_fn(n))?;
+                consumer.subscribe(topics)?;
+                Ok(consumer)
+            })
+            .collect::<KafkaResult<Vec<_>>>()?;
+
+        let handler = Arc::new(handler);
+        let mut sharded_consumer = ShardedConsumer {
+            shards: Vec::with_capacity(shard_count),
+            should_stop: Arc::new(AtomicBool::new(false)),
+        };
+        for (n, consumer) in consumers.into_iter().enumerate() {
+            let counters = Arc::new(ShardCounters::default());
+            let spawned = {
+                let counters = Arc::clone(&counters);
+                let should_stop = Arc::clone(&sharded_consumer.should_stop);
+                let handler = Arc::clone(&handler);
+                consumer
+                    .context()
+                    .polling_thread()
+                    .unwrap_or_else(|| CallbackThread::new(format!("consumer shard {}", n)))
+                    .spawn(move || {
+                        trace!("Shard {} polling loop started", n);
+                        while !should_stop.load(Ordering::Relaxed) {
+                            if let Some(result) = consumer.poll(Duration::from_millis(100)) {
+                                match result {
+                                    Ok(_) => counters.messages.fetch_add(1, Ordering::Relaxed),
+                                    Err(_) => counters.errors.fetch_add(1, Ordering::Relaxed),
+                                };
+                                let handled =
+                                    panic::catch_unwind(AssertUnwindSafe(|| handler(n, result)));
+                                if handled.is_err() {
+                                    error!("Shard {} handler panicked", n);
+                                    counters.panics.fetch_add(1, Ordering::Relaxed);
+                                }
+                            }
+                        }
+                        trace!("Shard {} polling loop terminated", n);
+                    })
+            };
+            match spawned {
+                Ok(handle) => sharded_consumer.shards.push(Shard {
+                    counters,
+                    handle: Some(handle),
+                }),
+                // Dropping the sharded consumer stops the shards that were
+                // already started.
+                Err(e) => {
+                    return Err(KafkaError::ClientCreation(format!(
+                        "failed to start the thread of shard {}: {}",
+                        n, e
+                    )))
+                }
+            }
+        }
+        Ok(sharded_consumer)
+    }
+
+    /// Returns the number of shards.
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Returns a snapshot of the statistics of every shard.
+    pub fn stats(&self) -> ShardedConsumerStats {
+        ShardedConsumerStats {
+            shards: self
+                .shards
+                .iter()
+                .map(|shard| ShardStats {
+                    messages: shard.counters.messages.load(Ordering::Relaxed),
+                    errors: shard.counters.errors.load(Ordering::Relaxed),
+                    panics: shard.counters.panics.load(Ordering::Relaxed),
+                })
+                .collect(),
+        }
+    }
+
+    /// Stops all the shards and waits for their threads to terminate.
+    ///
+    /// This is equivalent to dropping the `ShardedConsumer`, but makes the
+    /// intent explicit and returns the final statistics.
+    pub fn shutdown(mut self) -> ShardedConsumerStats {
+        self.stop();
+        self.stats()
+    }
+
+    fn stop(&mut self) {
+        trace!("Stopping all shards");
+        self.should_stop.store(true, Ordering::Relaxed);
+        for (n, shard) in self.shards.iter_mut().enumerate() {
+            if let Some(handle) = shard.handle.take() {
+                match handle.join() {
+                    Ok(()) => trace!("Shard {} stopped", n),
+                    Err(e) => warn!("Failure while terminating shard {}: {:?}", n, e),
+                }
+            }
+        }
+    }
+}
+
+impl Drop for ShardedConsumer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::ClientContext;
+
+    #[test]
+    fn test_sharded_consumer_stats() {
+        let stats = ShardedConsumerStats {
+            shards: vec![
+                ShardStats {
+                    messages: 3,
+                    errors: 1,
+                    panics: 0,
+                },
+                ShardStats {
+                    messages: 4,
+                    errors: 0,
+                    panics: 2,
+                },
+            ],
+        };
+        assert_eq!(stats.messages(), 7);
+        assert_eq!(stats.errors(), 1);
+        assert_eq!(stats.panics(), 2);
+        assert_eq!(ShardedConsumerStats::default().messages(), 0);
+    }
+
+    struct PinnedContext;
+
+    impl ClientContext for PinnedContext {
+        fn polling_thread(&self) -> Option<CallbackThread> {
+            Some(CallbackThread::new("pinned shard").cpu_affinity(vec![usize::MAX]))
+        }
+    }
+
+    impl ConsumerContext for PinnedContext {}
+
+    #[test]
+    fn test_sharded_consumer_thread_failure() {
+        let mut config = ClientConfig::new();
+        config.set("group.id", "sharded");
+        let result = ShardedConsumer::new(&config, 2, &["topic"], |_| PinnedContext, |_, _| ());
+        assert!(matches!(result, Err(KafkaError::ClientCreation(_))));
+    }
+}
@@ -20,18 +20,18 @@ use rdkafka_sys as rdsys;
 use rdkafka_sys::types::*;
 
 use crate::client::{Client, NativeQueue};
-use crate::config::{ClientConfig, FromClientConfig, FromClientConfigAndContext};
+use crate::config::{ClientConfig, ConfigClients, FromClientConfig, FromClientConfigAndContext};
 use crate::consumer::base_consumer::BaseConsumer;
 use crate::consumer::{
-    CommitMode, Consumer, ConsumerContext, ConsumerGroupMetadata, DefaultConsumerContext,
-    RebalanceProtocol,
+    CommitMode, ConsumeHooks, Consumer, ConsumerContext, ConsumerEvent, ConsumerGroupMetadata,
+    DefaultConsumerContext, RebalanceProtocol,
 };
 use crate::error::{KafkaError, KafkaResult};
 use crate::groups::GroupList;
 use crate::message::BorrowedMessage;
 use crate::metadata::Metadata;
 use crate::topic_partition_list::{Offset, TopicPartitionList};
-use crate::util::{AsyncRuntime, DefaultRuntime, NativePtr, Timeout};
+use crate::util::{self, AsyncRuntime, DefaultRuntime, NativePtr, Timeout};
 
 unsafe extern "C" fn native_message_queue_nonempty_cb(_: *mut RDKafka, opaque_ptr: *mut c_void) {
     let wakers = &*(opaque_ptr as *const WakerSlab);
@@ -92,35 +92,40 @@ impl WakerSlab {
 pub struct MessageStream<'a> {
     wakers: &'a WakerSlab,
     queue: &'a NativeQueue,
+    hooks: &'a dyn ConsumeHooks,
     slot: usize,
 }
 
 impl<'a> MessageStream<'a> {
-    fn new(wakers: &'a WakerSlab, queue: &'a NativeQueue) -> MessageStream<'a> {
+    fn new(
+        wakers: &'a WakerSlab,
+        queue: &'a NativeQueue,
+        hooks: &'a dyn ConsumeHooks,
+    ) -> MessageStream<'a> {
         let slot = wakers.register();
         MessageStream {
             wakers,
             queue,
+            hooks,
             slot,
         }
     }
 
-    fn poll(&self) -> Option<KafkaResult<BorrowedMessage<'a>>> {
-        unsafe {
-            NativePtr::from_ptr(rdsys::rd_kafka_consume_queue(self.queue.ptr(), 0))
-                .map(|p| BorrowedMessage::from_consumer(p, self.queue))
-        }
+    fn poll(&self) -> Option<NativePtr<RDKafkaMessage>> {
+        unsafe { NativePtr::from_ptr(rdsys::rd_kafka_consume_queue(self.queue.ptr(), 0)) }
     }
-}
 
-impl<'a> Stream for MessageStream<'a> {
-    type Item = KafkaResult<BorrowedMessage<'a>>;
+    fn consumed(&self, ptr: NativePtr<RDKafkaMessage>) -> KafkaResult<BorrowedMessage<'a>> {
+        let result = unsafe { BorrowedMessage::from_consumer(ptr, self.queue) };
+        self.hooks.consumed(&result);
+        result
+    }
 
-    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    fn poll_raw(&self, cx: &mut Context<'_>) -> Poll<NativePtr<RDKafkaMessage>> {
         // If there is a message ready, yield it immediately to avoid the
         // taking the lock in `self.set_waker`.
         if let Some(message) = self.poll() {
-            return Poll::Ready(Some(message));
+            return Poll::Ready(message);
         }
 
         // Otherwise, we need to wait for a message to become available. Store
@@ -135,17 +140,49 @@ impl<'a> Stream for MessageStream<'a> {
         // installed the waker.
         match self.poll() {
             None => Poll::Pending,
-            Some(message) => Poll::Ready(Some(message)),
+            Some(message) => Poll::Ready(message),
         }
     }
 }
 
+impl<'a> Stream for MessageStream<'a> {
+    type Item = KafkaResult<BorrowedMessage<'a>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.poll_raw(cx).map(|ptr| Some(self.consumed(ptr)))
+    }
+}
+
 impl<'a> Drop for MessageStream<'a> {
     fn drop(&mut self) {
         self.wakers.unregister(self.slot);
     }
 }
 
+/// A stream of events from a [`StreamConsumer`].
+///
+/// See the documentation of [`StreamConsumer::event_stream`] for details.
+pub struct EventStream<'a> {
+    messages: MessageStream<'a>,
+}
+
+impl<'a> Stream for EventStream<'a> {
+    type Item = ConsumerEvent<'a>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let messages = &self.messages;
+        messages.poll_raw(cx).map(|ptr| {
+            Some(match ConsumerEvent::partition_eof(&ptr) {
+                Some(event) => event,
+                None => match messages.consumed(ptr) {
+                    Ok(message) => ConsumerEvent::Message(message),
+                    Err(e) => ConsumerEvent::Error(e),
+                },
+            })
+        })
+    }
+}
+
 /// A high-level consumer with a [`Stream`](futures_util::Stream) interface.
 ///
 /// This consumer doesn't need to be polled explicitly. Extracting an item from
@@ -159,6 +196,11 @@ impl<'a> Drop for MessageStream<'a> {
 /// every `max.poll.interval.ms` milliseconds, or librdkafka will assume that
 /// the processing thread is wedged and leave the consumer groups.
 ///
+/// The results of offset commits, including automatic ones, are reported to
+/// [`ConsumerContext::commit_callback`]. See the
+/// [`commit_events`](crate::consumer::commit_events) module to receive them
+/// as a stream instead.
+///
 /// [KIP-62]: https://cwiki.apache.org/confluence/display/KAFKA/KIP-62%3A+Allow+consumer+to+send+heartbeats+from+a+background+thread
 #[must_use = "Consumer polling thread will stop immediately if unused"]
 pub struct StreamConsumer<C = DefaultConsumerContext, R = DefaultRuntime>
@@ -188,6 +230,7 @@ where
     R: AsyncRuntime,
 {
     fn from_config_and_context(config: &ClientConfig, context: C) -> KafkaResult<Self> {
+        config.validate_if_strict(ConfigClients::Consumer)?;
         let native_config = config.create_native_config()?;
         let poll_interval = {
             let millis: u64 = native_config
@@ -265,7 +308,7 @@ where
     /// If you want multiple independent views of a Kafka topic, create multiple
     /// consumers, not multiple message streams.
     pub fn stream(&self) -> MessageStream<'_> {
-        MessageStream::new(&self.wakers, &self.queue)
+        MessageStream::new(&self.wakers, &self.queue, &**self.base.context())
     }
 
     /// Receives the next message from the stream.
@@ -295,6 +338,35 @@ where
             .expect("kafka streams never terminate")
     }
 
+    /// Constructs a stream that yields the events of this consumer.
+    ///
+    /// This is equivalent to [`stream`](StreamConsumer::stream), except that
+    /// the end of a partition is reported as a
+    /// [`ConsumerEvent::PartitionEof`] that identifies the topic, partition
+    /// and offset, rather than as a [`KafkaError::PartitionEOF`] error, like
+    /// with [`BaseConsumer::poll_event`].
+    pub fn event_stream(&self) -> EventStream<'_> {
+        EventStream {
+            messages: self.stream(),
+        }
+    }
+
+    /// Receives the next event from the stream.
+    ///
+    /// This is equivalent to [`recv`](StreamConsumer::recv), except that the
+    /// end of a partition is reported as a [`ConsumerEvent::PartitionEof`].
+    /// See [`event_stream`](StreamConsumer::event_stream) for details.
+    ///
+    /// This method is [cancellation safe].
+    ///
+    /// [cancellation safe]: https://docs.rs/tokio/latest/tokio/macro.select.html#cancellation-safety
+    pub async fn recv_event(&self) -> ConsumerEvent<'_> {
+        self.event_stream()
+            .next()
+            .await
+            .expect("kafka streams never terminate")
+    }
+
     /// Splits messages for the specified partition into their own stream.
     ///
     /// If the `topic` or `partition` is invalid, returns `None`.
@@ -360,6 +432,82 @@ where
     }
 }
 
+/// Asynchronous variants of the blocking queries of the [`Consumer`] trait.
+///
+/// Each query waits for a round trip to the brokers, which can take seconds,
+/// so it runs with [`AsyncRuntime::spawn_blocking`] rather than on the
+/// executor thread of the calling task. Like
+/// [`StreamConsumer::split_partition_queue`], these methods are implemented
+/// for `&Arc<Self>`, so that the consumer outlives the query even if the
+/// returned future is dropped early.
+impl<C, R> StreamConsumer<C, R>
+where
+    C: ConsumerContext + 'static,
+    R: AsyncRuntime,
+{
+    /// Like [`Consumer::fetch_metadata`], but asynchronous.
+    pub async fn fetch_metadata_async<T>(
+        self: &Arc<Self>,
+        topic: Option<&str>,
+        timeout: T,
+    ) -> KafkaResult<Metadata>
+    where
+        T: Into<Timeout>,
+    {
+        let consumer = self.clone();
+        let topic = topic.map(str::to_owned);
+        let timeout = timeout.into();
+        util::run_blocking::<R, _, _>(move || {
+            consumer.base.fetch_metadata(topic.as_deref(), timeout)
+        })
+        .await
+    }
+
+    /// Like [`Consumer::fetch_watermarks`], but asynchronous.
+    pub async fn fetch_watermarks_async<T>(
+        self: &Arc<Self>,
+        topic: &str,
+        partition: i32,
+        timeout: T,
+    ) -> KafkaResult<(i64, i64)>
+    where
+        T: Into<Timeout>,
+    {
+        let consumer = self.clone();
+        let topic = topic.to_owned();
+        let timeout = timeout.into();
+        util::run_blocking::<R, _, _>(move || {
+            consumer.base.fetch_watermarks(&topic, partition, timeout)
+        })
+        .await
+    }
+
+    /// Like [`Consumer::committed`], but asynchronous.
+    pub async fn committed_async<T>(self: &Arc<Self>, timeout: T) -> KafkaResult<TopicPartitionList>
+    where
+        T: Into<Timeout>,
+    {
+        let consumer = self.clone();
+        let timeout = timeout.into();
+        util::run_blocking::<R, _, _>(move || consumer.base.committed(timeout)).await
+    }
+
+    /// Like [`Consumer::offsets_for_times`], but asynchronous.
+    pub async fn offsets_for_times_async<T>(
+        self: &Arc<Self>,
+        timestamps: TopicPartitionList,
+        timeout: T,
+    ) -> KafkaResult<TopicPartitionList>
+    where
+        T: Into<Timeout>,
+    {
+        let consumer = self.clone();
+        let timeout = timeout.into();
+        util::run_blocking::<R, _, _>(move || consumer.base.offsets_for_times(timestamps, timeout))
+            .await
+    }
+}
+
 impl<C, R> Consumer<C> for StreamConsumer<C, R>
 where
     C: ConsumerContext,
@@ -380,6 +528,10 @@ where
         self.base.unsubscribe();
     }
 
+    fn close(&self) -> KafkaResult<()> {
+        self.base.close()
+    }
+
     fn assign(&self, assignment: &TopicPartitionList) -> KafkaResult<()> {
         self.base.assign(assignment)
     }
@@ -489,6 +641,10 @@ where
         self.base.position()
     }
 
+    fn positions(&self, tpl: TopicPartitionList) -> KafkaResult<TopicPartitionList> {
+        self.base.positions(tpl)
+    }
+
     fn fetch_metadata<T>(&self, topic: Option<&str>, timeout: T) -> KafkaResult<Metadata>
     where
         T: Into<Timeout>,
@@ -561,7 +717,7 @@ where
     /// If you want multiple independent views of a Kafka partition, create
     /// multiple consumers, not multiple partition streams.
     pub fn stream(&self) -> MessageStream<'_> {
-        MessageStream::new(&self.wakers, &self.queue)
+        MessageStream::new(&self.wakers, &self.queue, &**self._consumer.base.context())
     }
 
     /// Receives the next message from the stream.
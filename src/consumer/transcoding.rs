@@ -0,0 +1,207 @@
+//! Transparent unwrapping of legacy payload framings.
+//!
+//! Topics that were historically written by producers wrapping their
+//! payloads in gzip, with a magic prefix to tell them apart, can be read with
+//! a [`GzipFraming`]. Payloads that start with the configured magic prefix are
+//! stripped of it and decompressed, while all other payloads are passed
+//! through unchanged. This makes it possible to migrate producers off the
+//! legacy framing gradually, without changing the message handlers.
+//!
+//! The [`TranscodingConsumer`] wraps a [`BaseConsumer`] and applies the
+//! framing to every message it receives. For other consumers, like the
+//! [`StreamConsumer`](crate::consumer::StreamConsumer), use
+//! [`GzipFraming::transcode`] directly on the received messages.
+//!
+//! Decompressed payloads are limited to
+//! [`DEFAULT_MAX_DECODED_SIZE`] bytes by default, so that a small payload that
+//! decompresses to a huge one cannot exhaust the memory of the consumer. See
+//! [`GzipFraming::max_decoded_size`] to change the limit.
+//!
+//! This module requires the `gzip` feature.
+
+use std::borrow::Cow;
+use std::io::Read;
+
+use flate2::read::GzDecoder;
+
+use crate::consumer::{BaseConsumer, ConsumerContext, DefaultConsumerContext};
+use crate::error::{KafkaError, KafkaResult};
+use crate::message::{BorrowedMessage, Message, OwnedMessage};
+use crate::util::Timeout;
+
+/// The default maximum size of a decompressed payload, which matches the
+/// default of the `message.max.bytes` property.
+pub const DEFAULT_MAX_DECODED_SIZE: usize = 1_000_000;
+
+/// A legacy framing made of a magic prefix followed by a gzip stream.
+#[derive(Clone, Debug)]
+pub struct GzipFraming {
+    magic: Vec<u8>,
+    max_decoded_size: usize,
+}
+
+impl GzipFraming {
+    /// Creates a new framing that recognizes payloads starting with `magic`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `magic` is empty, since every payload would then be
+    /// considered framed.
+    pub fn new<M: Into<Vec<u8>>>(magic: M) -> GzipFraming {
+        let magic = magic.into();
+        assert!(!magic.is_empty(), "the magic prefix must not be empty");
+        GzipFraming {
+            magic,
+            max_decoded_size: DEFAULT_MAX_DECODED_SIZE,
+        }
+    }
+
+    /// Sets the maximum size of a decompressed payload, in bytes.
+    ///
+    /// Payloads that decompress to more bytes are rejected with a
+    /// [`KafkaError::PayloadDecoding`] error. Defaults to
+    /// [`DEFAULT_MAX_DECODED_SIZE`].
+    pub fn max_decoded_size(mut self, max_decoded_size: usize) -> GzipFraming {
+        self.max_decoded_size = max_decoded_size;
+        self
+    }
+
+    /// Returns the magic prefix of the framing.
+    pub fn magic(&self) -> &[u8] {
+        &self.magic
+    }
+
+    /// Reports whether the payload uses this framing.
+    pub fn matches(&self, payload: &[u8]) -> bool {
+        payload.starts_with(&self.magic)
+    }
+
+    /// Unwraps the payload if it uses this framing, or returns it unchanged
+    /// otherwise.
+    pub fn unwrap_payload<'a>(&self, payload: &'a [u8]) -> KafkaResult<Cow<'a, [u8]>> {
+        if !self.matches(payload) {
+            return Ok(Cow::Borrowed(payload));
+        }
+        let mut decoded = Vec::new();
+        GzDecoder::new(&payload[self.magic.len()..])
+            .take(self.max_decoded_size as u64 + 1)
+            .read_to_end(&mut decoded)
+            .map_err(|e| KafkaError::PayloadDecoding(e.to_string()))?;
+        if decoded.len() > self.max_decoded_size {
+            return Err(KafkaError::PayloadDecoding(format!(
+                "decompressed payload exceeds {} bytes",
+                self.max_decoded_size
+            )));
+        }
+        Ok(Cow::Owned(decoded))
+    }
+
+    /// Detaches the message, unwrapping its payload if it uses this framing.
+    pub fn transcode(&self, message: &BorrowedMessage<'_>) -> KafkaResult<OwnedMessage> {
+        let mut owned = message.detach();
+        if let Some(payload) = message.payload() {
+            if let Cow::Owned(decoded) = self.unwrap_payload(payload).map_err(|e| {
+                KafkaError::PayloadDecoding(format!(
+                    "{} (topic {}, partition {}, offset {})",
+                    e,
+                    message.topic(),
+                    message.partition(),
+                    message.offset()
+                ))
+            })? {
+                owned.set_payload(Some(decoded));
+            }
+        }
+        Ok(owned)
+    }
+}
+
+/// A [`BaseConsumer`] that unwraps a [`GzipFraming`] on every message.
+pub struct TranscodingConsumer<C = DefaultConsumerContext>
+where
+    C: ConsumerContext,
+{
+    consumer: BaseConsumer<C>,
+    framing: GzipFraming,
+}
+
+impl<C> TranscodingConsumer<C>
+where
+    C: ConsumerContext,
+{
+    /// Wraps the consumer, unwrapping the specified framing.
+    pub fn new(consumer: BaseConsumer<C>, framing: GzipFraming) -> TranscodingConsumer<C> {
+        TranscodingConsumer { consumer, framing }
+    }
+
+    /// Returns the underlying consumer.
+    pub fn consumer(&self) -> &BaseConsumer<C> {
+        &self.consumer
+    }
+
+    /// Returns the framing unwrapped by this consumer.
+    pub fn framing(&self) -> &GzipFraming {
+        &self.framing
+    }
+
+    /// Polls the consumer for new messages, unwrapping their payloads.
+    ///
+    /// See [`BaseConsumer::poll`] for details. Since unwrapped payloads do not
+    /// live in the consumer's memory, messages are returned as
+    /// [`OwnedMessage`]s.
+    pub fn poll<T: Into<Timeout>>(&self, timeout: T) -> Option<KafkaResult<OwnedMessage>> {
+        self.consumer
+            .poll(timeout)
+            .map(|result| result.and_then(|message| self.framing.transcode(&message)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    use super::*;
+
+    #[test]
+    fn test_gzip_framing() {
+        let framing = GzipFraming::new(&b"GZ1"[..]);
+
+        let mut encoder = GzEncoder::new(b"GZ1".to_vec(), Compression::default());
+        encoder.write_all(b"legacy payload").unwrap();
+        let framed = encoder.finish().unwrap();
+
+        assert!(framing.matches(&framed));
+        assert_eq!(
+            framing.unwrap_payload(&framed).unwrap().as_ref(),
+            b"legacy payload"
+        );
+        assert!(matches!(
+            framing.unwrap_payload(b"plain payload").unwrap(),
+            Cow::Borrowed(b"plain payload")
+        ));
+        assert!(matches!(
+            framing.unwrap_payload(b"GZ1 not gzip"),
+            Err(KafkaError::PayloadDecoding(_))
+        ));
+
+        let framing = framing.max_decoded_size(6);
+        assert!(matches!(
+            framing.unwrap_payload(&framed),
+            Err(KafkaError::PayloadDecoding(_))
+        ));
+        let framing = framing.max_decoded_size(14);
+        assert_eq!(
+            framing.unwrap_payload(&framed).unwrap().as_ref(),
+            b"legacy payload"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "the magic prefix must not be empty")]
+    fn test_gzip_framing_empty_magic() {
+        let _ = GzipFraming::new(Vec::new());
+    }
+}
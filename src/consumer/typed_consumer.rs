@@ -0,0 +1,168 @@
+//! Consumer of domain types.
+//!
+//! See the [`TypedConsumer`] for details.
+
+use std::marker::PhantomData;
+
+use crate::consumer::{BaseConsumer, ConsumerContext, DefaultConsumerContext};
+use crate::error::{KafkaError, KafkaResult};
+use crate::message::{BorrowedHeaders, BorrowedMessage, Message, OwnedHeaders, Timestamp};
+use crate::serialization::{BytesDeserializer, Deserializer};
+use crate::util::Timeout;
+
+/// A message whose key and payload were deserialized into domain types by a
+/// [`TypedConsumer`].
+#[derive(Debug)]
+pub struct TypedMessage<K, V> {
+    key: Option<K>,
+    payload: Option<V>,
+    topic: String,
+    partition: i32,
+    offset: i64,
+    timestamp: Timestamp,
+    headers: Option<OwnedHeaders>,
+}
+
+impl<K, V> TypedMessage<K, V> {
+    /// Returns the key of the message, or `None` if there is no key.
+    pub fn key(&self) -> Option<&K> {
+        self.key.as_ref()
+    }
+
+    /// Returns the payload of the message, or `None` if there is no payload.
+    pub fn payload(&self) -> Option<&V> {
+        self.payload.as_ref()
+    }
+
+    /// Consumes the message, returning its key and payload.
+    pub fn into_key_payload(self) -> (Option<K>, Option<V>) {
+        (self.key, self.payload)
+    }
+
+    /// Returns the source topic of the message.
+    pub fn topic(&self) -> &str {
+        &self.topic
+    }
+
+    /// Returns the partition number where the message is stored.
+    pub fn partition(&self) -> i32 {
+        self.partition
+    }
+
+    /// Returns the offset of the message within the partition.
+    pub fn offset(&self) -> i64 {
+        self.offset
+    }
+
+    /// Returns the message timestamp.
+    pub fn timestamp(&self) -> Timestamp {
+        self.timestamp
+    }
+
+    /// Returns the headers of the message, or `None` if there are no headers.
+    pub fn headers(&self) -> Option<&OwnedHeaders> {
+        self.headers.as_ref()
+    }
+}
+
+/// A [`BaseConsumer`] that deserializes the keys and payloads of the messages
+/// it receives into domain types.
+///
+/// The keys are deserialized with a [`Deserializer`] of type `KD`, and the
+/// payloads with a [`Deserializer`] of type `VD`. Both default to the
+/// [`BytesDeserializer`], which supports `Vec<u8>` and `String`. Payloads are
+/// deserialized with [`Deserializer::deserialize_with_headers`], so that a
+/// [`CodecRegistry`](crate::codec::CodecRegistry) can pick the codec of each
+/// message from its content type.
+pub struct TypedConsumer<
+    K,
+    V,
+    KD = BytesDeserializer,
+    VD = BytesDeserializer,
+    C = DefaultConsumerContext,
+> where
+    C: ConsumerContext,
+{
+    consumer: BaseConsumer<C>,
+    key_deserializer: KD,
+    value_deserializer: VD,
+    _types: PhantomData<fn() -> (K, V)>,
+}
+
+impl<K, V, KD, VD, C> TypedConsumer<K, V, KD, VD, C>
+where
+    KD: Deserializer<K>,
+    VD: Deserializer<V>,
+    C: ConsumerContext,
+{
+    /// Wraps the consumer, deserializing keys and payloads with the
+    /// specified deserializers.
+    pub fn new(
+        consumer: BaseConsumer<C>,
+        key_deserializer: KD,
+        value_deserializer: VD,
+    ) -> TypedConsumer<K, V, KD, VD, C> {
+        TypedConsumer {
+            consumer,
+            key_deserializer,
+            value_deserializer,
+            _types: PhantomData,
+        }
+    }
+
+    /// Returns the underlying consumer.
+    pub fn consumer(&self) -> &BaseConsumer<C> {
+        &self.consumer
+    }
+
+    /// Polls the consumer for new messages, deserializing their keys and
+    /// payloads.
+    ///
+    /// See [`BaseConsumer::poll`] for details. If the key or the payload of a
+    /// message cannot be deserialized, a [`KafkaError::PayloadDecoding`]
+    /// error that includes the topic, partition and offset of the message is
+    /// returned.
+    pub fn poll<T: Into<Timeout>>(&self, timeout: T) -> Option<KafkaResult<TypedMessage<K, V>>> {
+        self.consumer
+            .poll(timeout)
+            .map(|result| result.and_then(|message| self.deserialize(&message)))
+    }
+
+    fn deserialize(&self, message: &BorrowedMessage<'_>) -> KafkaResult<TypedMessage<K, V>> {
+        let context = |e: KafkaError, what: &str| {
+            KafkaError::PayloadDecoding(format!(
+                "could not deserialize {}: {} (topic {}, partition {}, offset {})",
+                what,
+                e,
+                message.topic(),
+                message.partition(),
+                message.offset()
+            ))
+        };
+        let key = match message.key() {
+            Some(key) => Some(
+                self.key_deserializer
+                    .deserialize(key)
+                    .map_err(|e| context(e, "key"))?,
+            ),
+            None => None,
+        };
+        let payload = match message.payload() {
+            Some(payload) => Some(
+                self.value_deserializer
+                    .deserialize_with_headers(payload, message.headers())
+                    .map_err(|e| context(e, "payload"))?,
+            ),
+            None => None,
+        };
+        Ok(TypedMessage {
+            key,
+            payload,
+            topic: message.topic().to_owned(),
+            partition: message.partition(),
+            offset: message.offset(),
+            timestamp: message.timestamp(),
+            headers: message.headers().map(BorrowedHeaders::detach),
+        })
+    }
+}
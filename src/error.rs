@@ -1,8 +1,10 @@
 //! Error manipulations.
 
+use std::convert::TryFrom;
 use std::error::Error;
 use std::ffi::{self, CStr};
 use std::fmt;
+use std::io;
 use std::ptr;
 use std::sync::Arc;
 
@@ -137,8 +139,9 @@ impl Error for RDKafkaError {}
 pub enum KafkaError {
     /// Creation of admin operation failed.
     AdminOpCreation(String),
-    /// The admin operation itself failed.
-    AdminOp(RDKafkaErrorCode),
+    /// The admin operation itself failed, with the detailed error string
+    /// reported by librdkafka.
+    AdminOp(RDKafkaErrorCode, String),
     /// The client was dropped before the operation completed.
     Canceled,
     /// Invalid client configuration.
@@ -153,22 +156,39 @@ pub enum KafkaError {
     Global(RDKafkaErrorCode),
     /// Group list fetch failed.
     GroupListFetch(RDKafkaErrorCode),
-    /// Message consumption failed.
-    MessageConsumption(RDKafkaErrorCode),
+    /// The consumer was not polled within `max.poll.interval.ms`, and thus
+    /// left its group.
+    MaxPollExceeded(String),
+    /// Message consumption failed, with the detailed error string reported by
+    /// librdkafka.
+    MessageConsumption(RDKafkaErrorCode, String),
     /// Message production error.
     MessageProduction(RDKafkaErrorCode),
     /// Metadata fetch error.
     MetadataFetch(RDKafkaErrorCode),
     /// No message was received.
     NoMessageReceived,
+    /// The static group member was fenced by a newer instance with the same
+    /// `group.instance.id`.
+    InstanceFenced(String),
     /// Unexpected null pointer
     Nul(ffi::NulError),
+    /// Setting the SASL `OAUTHBEARER` token failed.
+    OAuthToken(String),
     /// Offset fetch failed.
     OffsetFetch(RDKafkaErrorCode),
     /// End of partition reached.
     PartitionEOF(i32),
+    /// The partition of the topic has no leader, so messages cannot be sent to
+    /// it. The partition is -1 if the message had no explicit partition, in
+    /// which case no partition of the topic has a leader.
+    PartitionUnavailable(String, i32),
     /// Pause/Resume failed.
     PauseResume(String),
+    /// Decoding the payload of a message failed.
+    PayloadDecoding(String),
+    /// Encoding the payload of a message failed.
+    PayloadEncoding(String),
     /// Seeking a partition failed.
     Seek(String),
     /// Setting partition offset failed.
@@ -177,6 +197,8 @@ pub enum KafkaError {
     StoreOffset(RDKafkaErrorCode),
     /// Subscription creation failed.
     Subscription(String),
+    /// Resolving a topic template failed.
+    TopicTemplate(String),
     /// Transaction error.
     Transaction(RDKafkaError),
 }
@@ -184,7 +206,9 @@ pub enum KafkaError {
 impl fmt::Debug for KafkaError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            KafkaError::AdminOp(err) => write!(f, "KafkaError (Admin operation error: {})", err),
+            KafkaError::AdminOp(err, ref errstr) => {
+                write!(f, "KafkaError (Admin operation error: {}: {})", err, errstr)
+            }
             KafkaError::AdminOpCreation(ref err) => {
                 write!(f, "KafkaError (Admin operation creation error: {})", err)
             }
@@ -205,9 +229,14 @@ impl fmt::Debug for KafkaError {
             KafkaError::GroupListFetch(err) => {
                 write!(f, "KafkaError (Group list fetch error: {})", err)
             }
-            KafkaError::MessageConsumption(err) => {
-                write!(f, "KafkaError (Message consumption error: {})", err)
+            KafkaError::MaxPollExceeded(ref err) => {
+                write!(f, "KafkaError (Max poll interval exceeded: {})", err)
             }
+            KafkaError::MessageConsumption(err, ref errstr) => write!(
+                f,
+                "KafkaError (Message consumption error: {}: {})",
+                err, errstr
+            ),
             KafkaError::MessageProduction(err) => {
                 write!(f, "KafkaError (Message production error: {})", err)
             }
@@ -217,12 +246,29 @@ impl fmt::Debug for KafkaError {
             KafkaError::NoMessageReceived => {
                 write!(f, "No message received within the given poll interval")
             }
+            KafkaError::InstanceFenced(ref err) => {
+                write!(f, "KafkaError (Instance fenced: {})", err)
+            }
             KafkaError::Nul(_) => write!(f, "FFI null error"),
+            KafkaError::OAuthToken(ref err) => {
+                write!(f, "KafkaError (OAuth token error: {})", err)
+            }
             KafkaError::OffsetFetch(err) => write!(f, "KafkaError (Offset fetch error: {})", err),
             KafkaError::PartitionEOF(part_n) => write!(f, "KafkaError (Partition EOF: {})", part_n),
+            KafkaError::PartitionUnavailable(ref topic, part_n) => write!(
+                f,
+                "KafkaError (Partition unavailable: {} [{}])",
+                topic, part_n
+            ),
             KafkaError::PauseResume(ref err) => {
                 write!(f, "KafkaError (Pause/resume error: {})", err)
             }
+            KafkaError::PayloadDecoding(ref err) => {
+                write!(f, "KafkaError (Payload decoding error: {})", err)
+            }
+            KafkaError::PayloadEncoding(ref err) => {
+                write!(f, "KafkaError (Payload encoding error: {})", err)
+            }
             KafkaError::Seek(ref err) => write!(f, "KafkaError (Seek error: {})", err),
             KafkaError::SetPartitionOffset(err) => {
                 write!(f, "KafkaError (Set partition offset error: {})", err)
@@ -231,6 +277,9 @@ impl fmt::Debug for KafkaError {
             KafkaError::Subscription(ref err) => {
                 write!(f, "KafkaError (Subscription error: {})", err)
             }
+            KafkaError::TopicTemplate(ref err) => {
+                write!(f, "KafkaError (Topic template error: {})", err)
+            }
             KafkaError::Transaction(err) => write!(f, "KafkaError (Transaction error: {})", err),
         }
     }
@@ -239,7 +288,9 @@ impl fmt::Debug for KafkaError {
 impl fmt::Display for KafkaError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            KafkaError::AdminOp(err) => write!(f, "Admin operation error: {}", err),
+            KafkaError::AdminOp(err, ref errstr) => {
+                write!(f, "Admin operation error: {}: {}", err, errstr)
+            }
             KafkaError::AdminOpCreation(ref err) => {
                 write!(f, "Admin operation creation error: {}", err)
             }
@@ -252,20 +303,33 @@ impl fmt::Display for KafkaError {
             KafkaError::Flush(err) => write!(f, "Flush error: {}", err),
             KafkaError::Global(err) => write!(f, "Global error: {}", err),
             KafkaError::GroupListFetch(err) => write!(f, "Group list fetch error: {}", err),
-            KafkaError::MessageConsumption(err) => write!(f, "Message consumption error: {}", err),
+            KafkaError::MaxPollExceeded(ref err) => {
+                write!(f, "Max poll interval exceeded: {}", err)
+            }
+            KafkaError::MessageConsumption(err, ref errstr) => {
+                write!(f, "Message consumption error: {}: {}", err, errstr)
+            }
             KafkaError::MessageProduction(err) => write!(f, "Message production error: {}", err),
             KafkaError::MetadataFetch(err) => write!(f, "Meta data fetch error: {}", err),
             KafkaError::NoMessageReceived => {
                 write!(f, "No message received within the given poll interval")
             }
+            KafkaError::InstanceFenced(ref err) => write!(f, "Instance fenced: {}", err),
             KafkaError::Nul(_) => write!(f, "FFI nul error"),
+            KafkaError::OAuthToken(ref err) => write!(f, "OAuth token error: {}", err),
             KafkaError::OffsetFetch(err) => write!(f, "Offset fetch error: {}", err),
             KafkaError::PartitionEOF(part_n) => write!(f, "Partition EOF: {}", part_n),
+            KafkaError::PartitionUnavailable(ref topic, part_n) => {
+                write!(f, "Partition unavailable: {} [{}]", topic, part_n)
+            }
             KafkaError::PauseResume(ref err) => write!(f, "Pause/resume error: {}", err),
+            KafkaError::PayloadDecoding(ref err) => write!(f, "Payload decoding error: {}", err),
+            KafkaError::PayloadEncoding(ref err) => write!(f, "Payload encoding error: {}", err),
             KafkaError::Seek(ref err) => write!(f, "Seek error: {}", err),
             KafkaError::SetPartitionOffset(err) => write!(f, "Set partition offset error: {}", err),
             KafkaError::StoreOffset(err) => write!(f, "Store offset error: {}", err),
             KafkaError::Subscription(ref err) => write!(f, "Subscription error: {}", err),
+            KafkaError::TopicTemplate(ref err) => write!(f, "Topic template error: {}", err),
             KafkaError::Transaction(err) => write!(f, "Transaction error: {}", err),
         }
     }
@@ -274,7 +338,7 @@ impl fmt::Display for KafkaError {
 impl Error for KafkaError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         match self {
-            KafkaError::AdminOp(_) => None,
+            KafkaError::AdminOp(err, _) => Some(err),
             KafkaError::AdminOpCreation(_) => None,
             KafkaError::Canceled => None,
             KafkaError::ClientConfig(..) => None,
@@ -283,35 +347,229 @@ impl Error for KafkaError {
             KafkaError::Flush(err) => Some(err),
             KafkaError::Global(err) => Some(err),
             KafkaError::GroupListFetch(err) => Some(err),
-            KafkaError::MessageConsumption(err) => Some(err),
+            KafkaError::MaxPollExceeded(_) => None,
+            KafkaError::MessageConsumption(err, _) => Some(err),
             KafkaError::MessageProduction(err) => Some(err),
             KafkaError::MetadataFetch(err) => Some(err),
             KafkaError::NoMessageReceived => None,
-            KafkaError::Nul(_) => None,
+            KafkaError::InstanceFenced(_) => None,
+            KafkaError::Nul(err) => Some(err),
+            KafkaError::OAuthToken(_) => None,
             KafkaError::OffsetFetch(err) => Some(err),
             KafkaError::PartitionEOF(_) => None,
+            KafkaError::PartitionUnavailable(..) => None,
             KafkaError::PauseResume(_) => None,
+            KafkaError::PayloadDecoding(_) => None,
+            KafkaError::PayloadEncoding(_) => None,
             KafkaError::Seek(_) => None,
             KafkaError::SetPartitionOffset(err) => Some(err),
             KafkaError::StoreOffset(err) => Some(err),
             KafkaError::Subscription(_) => None,
+            KafkaError::TopicTemplate(_) => None,
             KafkaError::Transaction(err) => Some(err),
         }
     }
 }
 
+/// Reports whether the error code denotes a transient condition.
+fn is_retriable_code(code: RDKafkaErrorCode) -> bool {
+    matches!(
+        code,
+        RDKafkaErrorCode::BrokerTransportFailure
+            | RDKafkaErrorCode::Resolve
+            | RDKafkaErrorCode::MessageTimedOut
+            | RDKafkaErrorCode::AllBrokersDown
+            | RDKafkaErrorCode::OperationTimedOut
+            | RDKafkaErrorCode::QueueFull
+            | RDKafkaErrorCode::WaitingForCoordinator
+            | RDKafkaErrorCode::TimedOutQueue
+            | RDKafkaErrorCode::Retry
+            | RDKafkaErrorCode::UnknownTopicOrPartition
+            | RDKafkaErrorCode::LeaderNotAvailable
+            | RDKafkaErrorCode::NotLeaderForPartition
+            | RDKafkaErrorCode::RequestTimedOut
+            | RDKafkaErrorCode::NetworkException
+            | RDKafkaErrorCode::CoordinatorLoadInProgress
+            | RDKafkaErrorCode::CoordinatorNotAvailable
+            | RDKafkaErrorCode::NotCoordinator
+            | RDKafkaErrorCode::NotEnoughReplicas
+            | RDKafkaErrorCode::NotEnoughReplicasAfterAppend
+            | RDKafkaErrorCode::NotController
+            | RDKafkaErrorCode::KafkaStorageError
+            | RDKafkaErrorCode::FetchSessionIdNotFound
+            | RDKafkaErrorCode::InvalidFetchSessionEpoch
+            | RDKafkaErrorCode::ListenerNotFound
+            | RDKafkaErrorCode::FencedLeaderEpoch
+            | RDKafkaErrorCode::UnknownLeaderEpoch
+            | RDKafkaErrorCode::OffsetNotAvailable
+            | RDKafkaErrorCode::PreferredLeaderNotAvailable
+            | RDKafkaErrorCode::EligibleLeadersNotAvailable
+            | RDKafkaErrorCode::UnstableOffsetCommit
+            | RDKafkaErrorCode::ThrottlingQuotaExceeded
+            | RDKafkaErrorCode::ConcurrentTransactions
+    )
+}
+
+/// Returns the description of the error code, as reported by
+/// `rd_kafka_err2str`.
+pub(crate) fn err2str(code: RDKafkaErrorCode) -> String {
+    let err =
+        RDKafkaRespErr::try_from(code as i32).unwrap_or(RDKafkaRespErr::RD_KAFKA_RESP_ERR_UNKNOWN);
+    unsafe { CStr::from_ptr(rdsys::rd_kafka_err2str(err)) }
+        .to_string_lossy()
+        .into_owned()
+}
+
 impl From<ffi::NulError> for KafkaError {
     fn from(err: ffi::NulError) -> KafkaError {
         KafkaError::Nul(err)
     }
 }
 
+impl From<KafkaError> for io::Error {
+    /// Converts the error into an [`io::Error`] whose [`io::ErrorKind`]
+    /// approximates the nature of the Kafka error. The original error is
+    /// preserved as the inner error.
+    fn from(err: KafkaError) -> io::Error {
+        io::Error::new(err.io_error_kind(), err)
+    }
+}
+
 impl KafkaError {
+    /// Reports whether the error is a fatal error.
+    ///
+    /// A fatal error indicates that the client instance is no longer usable,
+    /// typically because an idempotent or transactional producer can no
+    /// longer guarantee its delivery semantics. The client must be recreated.
+    /// Use [`Client::fatal_error`] to retrieve the underlying error.
+    ///
+    /// [`Client::fatal_error`]: crate::client::Client::fatal_error
+    pub fn is_fatal(&self) -> bool {
+        match self {
+            KafkaError::InstanceFenced(_) => true,
+            KafkaError::Transaction(err) => err.is_fatal(),
+            _ => self.rdkafka_error_code() == Some(RDKafkaErrorCode::Fatal),
+        }
+    }
+
+    /// Reports whether the operation that encountered the error can be
+    /// retried.
+    ///
+    /// For errors backed by a native librdkafka error object, such as
+    /// transaction errors, librdkafka decides. For the other errors, the
+    /// underlying error code is checked against the codes that the Kafka
+    /// protocol and librdkafka define as transient, e.g. a full producer
+    /// queue, a leader election in progress or a request timeout.
+    pub fn is_retriable(&self) -> bool {
+        match self {
+            KafkaError::Transaction(err) => err.is_retriable(),
+            _ => matches!(self.rdkafka_error_code(), Some(code) if is_retriable_code(code)),
+        }
+    }
+
+    /// Reports whether the error is an abortable transaction error, i.e.
+    /// whether the current transaction must be aborted with
+    /// [`Producer::abort_transaction`] before a new one can be started.
+    ///
+    /// [`Producer::abort_transaction`]: crate::producer::Producer::abort_transaction
+    pub fn txn_requires_abort(&self) -> bool {
+        match self {
+            KafkaError::Transaction(err) => err.txn_requires_abort(),
+            _ => false,
+        }
+    }
+
+    /// Returns the numeric librdkafka error code underlying this error, if
+    /// any.
+    ///
+    /// The numeric codes are stable across librdkafka versions, and are the
+    /// same as the ones returned by the C library, which makes them suitable
+    /// for metrics labels and for interoperating with other Kafka clients.
+    pub fn error_code(&self) -> Option<i32> {
+        self.rdkafka_error_code().map(|code| code as i32)
+    }
+
+    /// Returns a human-readable description of the cause of the error.
+    ///
+    /// For errors that carry a detailed string reported by librdkafka, such as
+    /// transaction, admin and consumption errors, this is that string,
+    /// which often names the broker, topic or setting at fault. For errors
+    /// that only carry an error code, this is the description of the code
+    /// returned by `rd_kafka_err2str`. For the other errors, this is the
+    /// message of the error.
+    pub fn error_string(&self) -> String {
+        match self {
+            KafkaError::Transaction(err) => err.string(),
+            KafkaError::AdminOp(_, err)
+            | KafkaError::AdminOpCreation(err)
+            | KafkaError::ClientCreation(err)
+            | KafkaError::InstanceFenced(err)
+            | KafkaError::MaxPollExceeded(err)
+            | KafkaError::MessageConsumption(_, err)
+            | KafkaError::OAuthToken(err)
+            | KafkaError::PauseResume(err)
+            | KafkaError::PayloadDecoding(err)
+            | KafkaError::PayloadEncoding(err)
+            | KafkaError::Seek(err)
+            | KafkaError::Subscription(err)
+            | KafkaError::TopicTemplate(err) => err.clone(),
+            KafkaError::ClientConfig(_, desc, _, _) => desc.clone(),
+            _ => match self.rdkafka_error_code() {
+                Some(code) => err2str(code),
+                None => self.to_string(),
+            },
+        }
+    }
+
+    fn io_error_kind(&self) -> io::ErrorKind {
+        match self {
+            KafkaError::Canceled => return io::ErrorKind::Interrupted,
+            KafkaError::ClientConfig(..)
+            | KafkaError::Nul(_)
+            | KafkaError::PayloadEncoding(_)
+            | KafkaError::TopicTemplate(_) => return io::ErrorKind::InvalidInput,
+            KafkaError::NoMessageReceived => return io::ErrorKind::TimedOut,
+            KafkaError::PartitionEOF(_) => return io::ErrorKind::UnexpectedEof,
+            KafkaError::PartitionUnavailable(..) => return io::ErrorKind::NotConnected,
+            KafkaError::PayloadDecoding(_) => return io::ErrorKind::InvalidData,
+            _ => (),
+        }
+        match self.rdkafka_error_code() {
+            Some(RDKafkaErrorCode::MessageTimedOut)
+            | Some(RDKafkaErrorCode::OperationTimedOut)
+            | Some(RDKafkaErrorCode::RequestTimedOut)
+            | Some(RDKafkaErrorCode::TimedOutQueue) => io::ErrorKind::TimedOut,
+            Some(RDKafkaErrorCode::QueueFull) => io::ErrorKind::WouldBlock,
+            Some(RDKafkaErrorCode::Authentication)
+            | Some(RDKafkaErrorCode::SaslAuthenticationFailed)
+            | Some(RDKafkaErrorCode::TopicAuthorizationFailed)
+            | Some(RDKafkaErrorCode::GroupAuthorizationFailed)
+            | Some(RDKafkaErrorCode::ClusterAuthorizationFailed)
+            | Some(RDKafkaErrorCode::TransactionalIdAuthorizationFailed) => {
+                io::ErrorKind::PermissionDenied
+            }
+            Some(RDKafkaErrorCode::BrokerTransportFailure)
+            | Some(RDKafkaErrorCode::AllBrokersDown)
+            | Some(RDKafkaErrorCode::NetworkException) => io::ErrorKind::NotConnected,
+            Some(RDKafkaErrorCode::UnknownTopicOrPartition)
+            | Some(RDKafkaErrorCode::UnknownTopic)
+            | Some(RDKafkaErrorCode::UnknownPartition) => io::ErrorKind::NotFound,
+            Some(RDKafkaErrorCode::TopicAlreadyExists) => io::ErrorKind::AlreadyExists,
+            Some(RDKafkaErrorCode::InvalidArgument) | Some(RDKafkaErrorCode::InvalidConfig) => {
+                io::ErrorKind::InvalidInput
+            }
+            Some(RDKafkaErrorCode::BadMessage)
+            | Some(RDKafkaErrorCode::InvalidMessage)
+            | Some(RDKafkaErrorCode::MessageSizeTooLarge) => io::ErrorKind::InvalidData,
+            _ => io::ErrorKind::Other,
+        }
+    }
+
     /// Returns the [`RDKafkaErrorCode`] underlying this error, if any.
     #[allow(clippy::match_same_arms)]
     pub fn rdkafka_error_code(&self) -> Option<RDKafkaErrorCode> {
         match self {
-            KafkaError::AdminOp(_) => None,
+            KafkaError::AdminOp(err, _) => Some(*err),
             KafkaError::AdminOpCreation(_) => None,
             KafkaError::Canceled => None,
             KafkaError::ClientConfig(..) => None,
@@ -320,19 +578,82 @@ impl KafkaError {
             KafkaError::Flush(err) => Some(*err),
             KafkaError::Global(err) => Some(*err),
             KafkaError::GroupListFetch(err) => Some(*err),
-            KafkaError::MessageConsumption(err) => Some(*err),
+            KafkaError::MaxPollExceeded(_) => Some(RDKafkaErrorCode::PollExceeded),
+            KafkaError::MessageConsumption(err, _) => Some(*err),
             KafkaError::MessageProduction(err) => Some(*err),
             KafkaError::MetadataFetch(err) => Some(*err),
             KafkaError::NoMessageReceived => None,
+            KafkaError::InstanceFenced(_) => Some(RDKafkaErrorCode::FencedInstanceId),
             KafkaError::Nul(_) => None,
+            KafkaError::OAuthToken(_) => None,
             KafkaError::OffsetFetch(err) => Some(*err),
             KafkaError::PartitionEOF(_) => None,
+            KafkaError::PartitionUnavailable(..) => None,
             KafkaError::PauseResume(_) => None,
+            KafkaError::PayloadDecoding(_) => None,
+            KafkaError::PayloadEncoding(_) => None,
             KafkaError::Seek(_) => None,
             KafkaError::SetPartitionOffset(err) => Some(*err),
             KafkaError::StoreOffset(err) => Some(*err),
             KafkaError::Subscription(_) => None,
+            KafkaError::TopicTemplate(_) => None,
             KafkaError::Transaction(err) => Some(err.code()),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_classification() {
+        let err = KafkaError::MessageProduction(RDKafkaErrorCode::QueueFull);
+        assert!(err.is_retriable());
+        assert!(!err.is_fatal());
+        assert!(!err.txn_requires_abort());
+
+        let err = KafkaError::MessageProduction(RDKafkaErrorCode::MessageSizeTooLarge);
+        assert!(!err.is_retriable());
+
+        let err = KafkaError::Global(RDKafkaErrorCode::Fatal);
+        assert!(err.is_fatal());
+        assert!(!err.is_retriable());
+
+        assert!(!KafkaError::PayloadDecoding("bad".into()).is_retriable());
+
+        let err = KafkaError::InstanceFenced("fenced by a newer instance".into());
+        assert!(err.is_fatal());
+        assert_eq!(
+            err.rdkafka_error_code(),
+            Some(RDKafkaErrorCode::FencedInstanceId)
+        );
+        assert_eq!(err.error_string(), "fenced by a newer instance");
+
+        let err = KafkaError::MaxPollExceeded("processing took too long".into());
+        assert!(!err.is_fatal());
+        assert_eq!(
+            err.rdkafka_error_code(),
+            Some(RDKafkaErrorCode::PollExceeded)
+        );
+        assert_eq!(err.error_string(), "processing took too long");
+    }
+
+    #[test]
+    fn test_error_traits() {
+        fn assert_error<E: Error + Send + Sync + 'static>() {}
+        assert_error::<KafkaError>();
+        assert_error::<RDKafkaError>();
+        assert_error::<RDKafkaErrorCode>();
+
+        let err = KafkaError::MessageProduction(RDKafkaErrorCode::QueueFull);
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "Message production error: QueueFull ({})",
+                err.error_string()
+            )
+        );
+        assert!(err.source().is_some());
+    }
+}
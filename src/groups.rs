@@ -1,12 +1,15 @@
 //! Group membership API.
 
+use std::convert::TryInto;
 use std::ffi::CStr;
 use std::fmt;
 use std::slice;
+use std::str;
 
 use rdkafka_sys as rdsys;
 use rdkafka_sys::types::*;
 
+use crate::topic_partition_list::TopicPartitionList;
 use crate::util::{KafkaDrop, NativePtr};
 
 /// Group member information container.
@@ -67,6 +70,88 @@ impl GroupMemberInfo {
             }
         }
     }
+
+    /// Returns the topics the member is subscribed to, decoded from its
+    /// metadata.
+    ///
+    /// This is only meaningful for the members of groups whose protocol type
+    /// is `consumer`. Returns `None` if the member has no metadata, or if the
+    /// metadata is not encoded with the consumer protocol.
+    pub fn subscribed_topics(&self) -> Option<Vec<String>> {
+        decode_subscription(self.metadata()?)
+    }
+
+    /// Returns the partitions assigned to the member, decoded from its
+    /// assignment.
+    ///
+    /// This is only meaningful for the members of groups whose protocol type
+    /// is `consumer`. Returns `None` if the member has no assignment, or if
+    /// the assignment is not encoded with the consumer protocol.
+    pub fn assigned_partitions(&self) -> Option<TopicPartitionList> {
+        decode_assignment(self.assignment()?)
+    }
+}
+
+/// Reads the big-endian primitives of the Kafka protocol.
+struct ProtocolReader<'a>(&'a [u8]);
+
+impl<'a> ProtocolReader<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        if self.0.len() < n {
+            return None;
+        }
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Some(head)
+    }
+
+    fn i16(&mut self) -> Option<i16> {
+        Some(i16::from_be_bytes(self.take(2)?.try_into().ok()?))
+    }
+
+    fn i32(&mut self) -> Option<i32> {
+        Some(i32::from_be_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    fn string(&mut self) -> Option<&'a str> {
+        let len = self.i16()?;
+        if len < 0 {
+            return None;
+        }
+        str::from_utf8(self.take(len as usize)?).ok()
+    }
+
+    fn array_len(&mut self) -> Option<usize> {
+        // Null arrays are decoded as empty arrays.
+        Some(self.i32()?.max(0) as usize)
+    }
+}
+
+/// Decodes the topics of a consumer protocol subscription. The user data and
+/// the fields of later versions that follow the topics are ignored.
+fn decode_subscription(metadata: &[u8]) -> Option<Vec<String>> {
+    let mut reader = ProtocolReader(metadata);
+    reader.i16()?;
+    let mut topics = Vec::new();
+    for _ in 0..reader.array_len()? {
+        topics.push(reader.string()?.to_owned());
+    }
+    Some(topics)
+}
+
+/// Decodes the partitions of a consumer protocol assignment. The user data
+/// that follows the partitions is ignored.
+fn decode_assignment(assignment: &[u8]) -> Option<TopicPartitionList> {
+    let mut reader = ProtocolReader(assignment);
+    reader.i16()?;
+    let mut tpl = TopicPartitionList::new();
+    for _ in 0..reader.array_len()? {
+        let topic = reader.string()?;
+        for _ in 0..reader.array_len()? {
+            tpl.add_partition(topic, reader.i32()?);
+        }
+    }
+    Some(tpl)
 }
 
 /// Group information container.
@@ -154,3 +239,45 @@ impl GroupList {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_consumer_protocol() {
+        let subscription = [
+            &[0, 1][..],           // version
+            &[0, 0, 0, 2],         // topic count
+            &[0, 1, b'a'],         // topic "a"
+            &[0, 2, b'b', b'c'],   // topic "bc"
+            &[255, 255, 255, 255], // null user data
+            &[0, 0, 0, 0],         // no owned partitions
+        ]
+        .concat();
+        assert_eq!(
+            decode_subscription(&subscription),
+            Some(vec!["a".to_owned(), "bc".to_owned()])
+        );
+        assert_eq!(decode_subscription(&subscription[..8]), None);
+
+        let assignment = [
+            &[0, 0][..],   // version
+            &[0, 0, 0, 1], // topic count
+            &[0, 1, b'a'], // topic "a"
+            &[0, 0, 0, 2], // partition count
+            &[0, 0, 0, 3], // partition 3
+            &[0, 0, 0, 7], // partition 7
+            &[0, 0, 0, 0], // empty user data
+        ]
+        .concat();
+        let tpl = decode_assignment(&assignment).unwrap();
+        let partitions = tpl
+            .elements()
+            .iter()
+            .map(|e| (e.topic().to_owned(), e.partition()))
+            .collect::<Vec<_>>();
+        assert_eq!(partitions, [("a".to_owned(), 3), ("a".to_owned(), 7)]);
+        assert!(decode_assignment(&assignment[..14]).is_none());
+    }
+}
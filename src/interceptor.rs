@@ -0,0 +1,166 @@
+//! Interceptors for the messages sent and received by clients.
+//!
+//! An [`Interceptor`] observes every message that goes through a producer or
+//! a consumer, which allows cross-cutting concerns, such as auditing or the
+//! injection of tracing headers, to be implemented once and shared by all the
+//! clients of an application. Interceptors are installed by returning them
+//! from [`ClientContext::interceptors`], and are invoked in order.
+//!
+//! ```
+//! use std::sync::Arc;
+//!
+//! use rdkafka::client::ClientContext;
+//! use rdkafka::interceptor::{InterceptedRecord, Interceptor};
+//! use rdkafka::message::Header;
+//! use rdkafka::producer::{DeliveryResult, ProducerContext};
+//!
+//! struct SourceHeader;
+//!
+//! impl Interceptor for SourceHeader {
+//!     fn on_send(&self, record: &mut InterceptedRecord<'_>) {
+//!         record.add_header(Header {
+//!             key: "source",
+//!             value: Some("billing"),
+//!         });
+//!     }
+//! }
+//!
+//! struct AppContext {
+//!     interceptors: Vec<Arc<dyn Interceptor>>,
+//! }
+//!
+//! impl ClientContext for AppContext {
+//!     fn interceptors(&self) -> &[Arc<dyn Interceptor>] {
+//!         &self.interceptors
+//!     }
+//! }
+//!
+//! impl ProducerContext for AppContext {
+//!     type DeliveryOpaque = ();
+//!
+//!     fn delivery(&self, _: &DeliveryResult<'_>, _: ()) {}
+//! }
+//! ```
+//!
+//! [`ClientContext::interceptors`]: crate::client::ClientContext::interceptors
+
+use std::sync::Arc;
+
+use crate::client::handle_callback_event;
+use crate::error::KafkaResult;
+use crate::message::{BorrowedMessage, Header, OwnedHeaders, ToBytes};
+use crate::producer::DeliveryResult;
+use crate::topic_partition_list::TopicPartitionList;
+
+/// Observes the messages sent and received by clients.
+///
+/// All the methods have empty default implementations, so that interceptors
+/// only implement the hooks they need. Hooks should be fast and must not
+/// block, since they run on the threads that send and poll messages, or on
+/// the threads that serve the callbacks of the client. Hooks that panic are
+/// accounted for like panicking callbacks, see
+/// [`dropped_callback_events`](crate::client::dropped_callback_events).
+#[allow(unused_variables)]
+pub trait Interceptor: Send + Sync {
+    /// Called before a producer hands a record to librdkafka.
+    ///
+    /// The hook is called once per record sent, after the checks that may
+    /// reject the record without enqueuing it, such as the partition
+    /// availability check. It is not called again when the producer retries
+    /// sending the record, e.g. after its queue was full, or after a failed
+    /// delivery with [`FutureProducer::send_with_retry`].
+    ///
+    /// [`FutureProducer::send_with_retry`]: crate::producer::FutureProducer::send_with_retry
+    fn on_send(&self, record: &mut InterceptedRecord<'_>) {}
+
+    /// Called when a message was delivered, or its delivery failed, before
+    /// the delivery callback of the producer.
+    fn on_acknowledgement(&self, result: &DeliveryResult<'_>) {}
+
+    /// Called when a consumer receives a message, before the message is
+    /// returned to the application.
+    fn on_consume(&self, message: &BorrowedMessage<'_>) {}
+
+    /// Called when offsets were committed, or the commit failed, before
+    /// [`ConsumerContext::commit_callback`].
+    ///
+    /// [`ConsumerContext::commit_callback`]: crate::consumer::ConsumerContext::commit_callback
+    fn on_commit(&self, result: &KafkaResult<()>, offsets: &TopicPartitionList) {}
+}
+
+/// A record about to be sent, as seen by [`Interceptor::on_send`].
+///
+/// The headers of the record can be modified; everything else is read-only.
+pub struct InterceptedRecord<'a> {
+    pub(crate) topic: &'a str,
+    pub(crate) partition: Option<i32>,
+    pub(crate) key: Option<&'a [u8]>,
+    pub(crate) payload: Option<&'a [u8]>,
+    pub(crate) timestamp: Option<i64>,
+    pub(crate) headers: &'a mut Option<OwnedHeaders>,
+}
+
+impl<'a> InterceptedRecord<'a> {
+    /// Returns the destination topic of the record.
+    pub fn topic(&self) -> &str {
+        self.topic
+    }
+
+    /// Returns the destination partition of the record, if specified.
+    pub fn partition(&self) -> Option<i32> {
+        self.partition
+    }
+
+    /// Returns the key of the record, if any.
+    pub fn key(&self) -> Option<&[u8]> {
+        self.key
+    }
+
+    /// Returns the payload of the record, if any.
+    pub fn payload(&self) -> Option<&[u8]> {
+        self.payload
+    }
+
+    /// Returns the timestamp of the record, if specified.
+    pub fn timestamp(&self) -> Option<i64> {
+        self.timestamp
+    }
+
+    /// Returns the headers of the record, if any.
+    pub fn headers(&self) -> Option<&OwnedHeaders> {
+        self.headers.as_ref()
+    }
+
+    /// Replaces the headers of the record.
+    pub fn set_headers(&mut self, headers: OwnedHeaders) {
+        *self.headers = Some(headers);
+    }
+
+    /// Appends a header to the headers of the record.
+    pub fn add_header<V: ToBytes + ?Sized>(&mut self, header: Header<'_, &V>) {
+        let headers = self.headers.take().unwrap_or_default();
+        *self.headers = Some(headers.insert(header));
+    }
+}
+
+/// Passes the record to the `on_send` hook of the interceptors.
+pub(crate) fn intercept_send(
+    interceptors: &[Arc<dyn Interceptor>],
+    record: &mut InterceptedRecord<'_>,
+) {
+    for interceptor in interceptors {
+        handle_callback_event(|| interceptor.on_send(record));
+    }
+}
+
+/// Passes the message, if any, to the `on_consume` hook of the interceptors.
+pub(crate) fn intercept_consume(
+    interceptors: &[Arc<dyn Interceptor>],
+    message: &KafkaResult<BorrowedMessage<'_>>,
+) {
+    if let Ok(message) = message {
+        for interceptor in interceptors {
+            handle_callback_event(|| interceptor.on_consume(message));
+        }
+    }
+}
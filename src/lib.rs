@@ -192,9 +192,13 @@
 //! rdkafka = { version = "0.25", default-features = false }
 //! ```
 //!
-//! If you would like to use an asynchronous runtime besides Tokio, you can
-//! integrate it with rust-rdkafka by providing a shim that implements the
-//! [`AsyncRuntime`] trait. See the following examples for details:
+//! The `async-std` and `smol` features provide the `AsyncStdRuntime` and the
+//! `SmolRuntime`, which can be used instead of Tokio, e.g. as the runtime of a
+//! `FutureProducer<DefaultClientContext, SmolRuntime>`.
+//!
+//! If you would like to use another asynchronous runtime, you can integrate it
+//! with rust-rdkafka by providing a shim that implements the [`AsyncRuntime`]
+//! trait. See the following examples for details:
 //!
 //!   * [smol][runtime-smol]
 //!   * [async-std][runtime-async-std]
@@ -211,7 +215,11 @@
 //!
 //! rust-rdkafka uses the [`log`] crate to handle logging.
 //! Optionally, enable the `tracing` feature to emit [`tracing`]
-//! events as opposed to [`log`] records.
+//! events as opposed to [`log`] records. With this feature, sends, delivery
+//! reports, polls, rebalances and commits are also wrapped in spans, named
+//! `rdkafka.send`, `rdkafka.delivery` and so on, that carry the topic,
+//! partition and offset involved where they are known, so that Kafka
+//! operations show up in distributed traces.
 //!
 //! In test and examples, rust-rdkafka uses the  [`env_logger`] crate
 //! to format logs. In those contexts, logging can be enabled
@@ -275,16 +283,30 @@ mod log;
 pub use rdkafka_sys::types;
 
 pub mod admin;
+#[cfg(feature = "avro")]
+#[cfg_attr(docsrs, doc(cfg(feature = "avro")))]
+pub mod avro;
 pub mod client;
+pub mod codec;
 pub mod config;
 pub mod consumer;
 pub mod error;
 pub mod groups;
+pub mod interceptor;
 pub mod message;
 pub mod metadata;
+#[cfg(feature = "metrics")]
+#[cfg_attr(docsrs, doc(cfg(feature = "metrics")))]
+pub mod metrics;
+#[cfg(feature = "aws-msk-iam")]
+#[cfg_attr(docsrs, doc(cfg(feature = "aws-msk-iam")))]
+pub mod msk_iam;
+pub mod offsets;
 pub mod producer;
+pub mod serialization;
 pub mod statistics;
 pub mod topic_partition_list;
+pub mod transactions;
 pub mod util;
 
 // Re-exports.
@@ -17,3 +17,22 @@ pub const DEBUG: tracing::Level = tracing::Level::DEBUG;
 pub const INFO: tracing::Level = tracing::Level::INFO;
 #[cfg(feature = "tracing")]
 pub const WARN: tracing::Level = tracing::Level::WARN;
+
+/// Enters a `tracing` span with the specified name and fields, until the end
+/// of the enclosing block.
+///
+/// Spans are only emitted with the `tracing` feature; without it, this macro
+/// expands to nothing.
+#[cfg(feature = "tracing")]
+macro_rules! enter_span {
+    ($name:expr $(, $($fields:tt)*)?) => {
+        let _span = tracing::debug_span!($name $(, $($fields)*)?).entered();
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! enter_span {
+    ($($args:tt)*) => {};
+}
+
+pub(crate) use enter_span;
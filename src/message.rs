@@ -2,16 +2,19 @@
 
 use std::ffi::{CStr, CString};
 use std::fmt;
+use std::io::IoSlice;
 use std::marker::PhantomData;
 use std::os::raw::c_void;
 use std::ptr;
 use std::str;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 
 use rdkafka_sys as rdsys;
 use rdkafka_sys::types::*;
+use serde::de::DeserializeOwned;
 
 use crate::error::{IsError, KafkaError, KafkaResult};
+use crate::serialization::{Deserializer, JsonDeserializer};
 use crate::util::{self, millis_to_epoch, KafkaDrop, NativePtr};
 
 /// Timestamp of a Kafka message.
@@ -54,6 +57,24 @@ impl From<SystemTime> for Timestamp {
     }
 }
 
+/// The persistence status of a produced message.
+///
+/// When the delivery of a message fails, the status tells whether the message
+/// may still have been written to the log, e.g. because the request timed out
+/// after it was sent to the broker. Retrying such a message may produce a
+/// duplicate unless idempotence is enabled.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum MessageStatus {
+    /// The message was never transmitted to the broker, or the broker failed
+    /// with an error that indicates it was not written to the log.
+    NotPersisted,
+    /// The message was transmitted to the broker, but no acknowledgement was
+    /// received.
+    PossiblyPersisted,
+    /// The message was written to the log and acknowledged by the broker.
+    Persisted,
+}
+
 // Use TryFrom when stable
 //impl From<Timestamp> for i64 {
 //    fn from(timestamp: Timestamp) -> i64 {
@@ -171,6 +192,19 @@ where
     }
 }
 
+/// The maximum number of payload bytes included in decoding errors.
+const PAYLOAD_PREVIEW_LEN: usize = 64;
+
+/// Formats the beginning of the payload for inclusion in error messages.
+fn payload_preview(payload: &[u8]) -> String {
+    let preview = String::from_utf8_lossy(&payload[..payload.len().min(PAYLOAD_PREVIEW_LEN)]);
+    if payload.len() > PAYLOAD_PREVIEW_LEN {
+        format!("{:?}... ({} bytes)", preview, payload.len())
+    } else {
+        format!("{:?}", preview)
+    }
+}
+
 /// A generic representation of a Kafka message.
 ///
 /// Only read-only methods are provided by this trait, as the underlying storage
@@ -221,6 +255,46 @@ pub trait Message {
         self.key().map(K::from_bytes)
     }
 
+    /// Parses the payload as JSON.
+    ///
+    /// See [`Message::payload_as`] for details about the errors.
+    fn payload_json(&self) -> KafkaResult<serde_json::Value> {
+        self.payload_as()
+    }
+
+    /// Deserializes the payload from JSON into a value of the specified type,
+    /// with the [`JsonDeserializer`].
+    ///
+    /// If the message has no payload, or the payload cannot be deserialized,
+    /// a [`KafkaError::PayloadDecoding`] error is returned. The error message
+    /// includes the topic, partition and offset of the message, as well as
+    /// the beginning of the payload, to make malformed messages easy to
+    /// track down.
+    fn payload_as<T: DeserializeOwned>(&self) -> KafkaResult<T> {
+        let payload = self.payload().ok_or_else(|| {
+            KafkaError::PayloadDecoding(format!(
+                "message has no payload (topic {}, partition {}, offset {})",
+                self.topic(),
+                self.partition(),
+                self.offset()
+            ))
+        })?;
+        JsonDeserializer.deserialize(payload).map_err(|e| {
+            let e = match e {
+                KafkaError::PayloadDecoding(e) => e,
+                e => e.to_string(),
+            };
+            KafkaError::PayloadDecoding(format!(
+                "{} (topic {}, partition {}, offset {}, payload {})",
+                e,
+                self.topic(),
+                self.partition(),
+                self.offset(),
+                payload_preview(payload)
+            ))
+        })
+    }
+
     /// Returns the headers of the message, or `None` if there are no headers.
     fn headers(&self) -> Option<&Self::Headers>;
 }
@@ -336,7 +410,19 @@ impl<'a> BorrowedMessage<'a> {
                 rdsys::rd_kafka_resp_err_t::RD_KAFKA_RESP_ERR__PARTITION_EOF => {
                     KafkaError::PartitionEOF((*ptr).partition)
                 }
-                e => KafkaError::MessageConsumption(e.into()),
+                rdsys::rd_kafka_resp_err_t::RD_KAFKA_RESP_ERR__MAX_POLL_EXCEEDED => {
+                    KafkaError::MaxPollExceeded(
+                        CStr::from_ptr(rdsys::rd_kafka_message_errstr(ptr.ptr()))
+                            .to_string_lossy()
+                            .into_owned(),
+                    )
+                }
+                e => KafkaError::MessageConsumption(
+                    e.into(),
+                    CStr::from_ptr(rdsys::rd_kafka_message_errstr(ptr.ptr()))
+                        .to_string_lossy()
+                        .into_owned(),
+                ),
             };
             Err(err)
         } else {
@@ -380,6 +466,21 @@ impl<'a> BorrowedMessage<'a> {
         self.ptr.rkt
     }
 
+    /// Returns the detailed error string of the message, if it carries an
+    /// error.
+    ///
+    /// For the messages of failed delivery reports, this string, reported by
+    /// librdkafka, is usually more specific than the description of the error
+    /// code returned by [`KafkaError::error_string`].
+    pub fn error_string(&self) -> Option<String> {
+        if self.ptr.err.is_error() {
+            let errstr = unsafe { CStr::from_ptr(rdsys::rd_kafka_message_errstr(self.ptr())) };
+            Some(errstr.to_string_lossy().into_owned())
+        } else {
+            None
+        }
+    }
+
     /// Returns the length of the key field of the message.
     pub fn key_len(&self) -> usize {
         self.ptr.key_len
@@ -390,6 +491,46 @@ impl<'a> BorrowedMessage<'a> {
         self.ptr.len
     }
 
+    /// Returns the ID of the broker the message was produced to or consumed
+    /// from, or `None` if it is not known.
+    pub fn broker_id(&self) -> Option<i32> {
+        match unsafe { rdsys::rd_kafka_message_broker_id(self.ptr()) } {
+            -1 => None,
+            broker_id => Some(broker_id),
+        }
+    }
+
+    /// Returns the persistence status of the message.
+    ///
+    /// This is mostly useful for the messages of failed delivery reports, see
+    /// [`MessageStatus`].
+    pub fn status(&self) -> MessageStatus {
+        match unsafe { rdsys::rd_kafka_message_status(self.ptr()) } {
+            rdsys::rd_kafka_msg_status_t::RD_KAFKA_MSG_STATUS_NOT_PERSISTED => {
+                MessageStatus::NotPersisted
+            }
+            rdsys::rd_kafka_msg_status_t::RD_KAFKA_MSG_STATUS_POSSIBLY_PERSISTED => {
+                MessageStatus::PossiblyPersisted
+            }
+            rdsys::rd_kafka_msg_status_t::RD_KAFKA_MSG_STATUS_PERSISTED => MessageStatus::Persisted,
+        }
+    }
+
+    /// Returns the time elapsed between the moment the message was handed to
+    /// the producer and the moment its delivery was acknowledged by the
+    /// broker, or failed.
+    ///
+    /// Returns `None` if the latency is not available, which is always the
+    /// case for consumed messages.
+    pub fn latency(&self) -> Option<Duration> {
+        let latency = unsafe { rdsys::rd_kafka_message_latency(self.ptr()) };
+        if latency < 0 {
+            None
+        } else {
+            Some(Duration::from_micros(latency as u64))
+        }
+    }
+
     /// Clones the content of the `BorrowedMessage` and returns an
     /// [`OwnedMessage`] that can outlive the consumer.
     ///
@@ -403,6 +544,9 @@ impl<'a> BorrowedMessage<'a> {
             partition: self.partition(),
             offset: self.offset(),
             headers: self.headers().map(BorrowedHeaders::detach),
+            broker_id: self.broker_id(),
+            status: Some(self.status()),
+            latency: self.latency(),
         }
     }
 }
@@ -593,6 +737,9 @@ pub struct OwnedMessage {
     partition: i32,
     offset: i64,
     headers: Option<OwnedHeaders>,
+    broker_id: Option<i32>,
+    status: Option<MessageStatus>,
+    latency: Option<Duration>,
 }
 
 impl OwnedMessage {
@@ -616,13 +763,45 @@ impl OwnedMessage {
             partition,
             offset,
             headers,
+            broker_id: None,
+            status: None,
+            latency: None,
         }
     }
 
+    /// Returns the ID of the broker the message was produced to or consumed
+    /// from, or `None` if it is not known.
+    ///
+    /// See [`BorrowedMessage::broker_id`].
+    pub fn broker_id(&self) -> Option<i32> {
+        self.broker_id
+    }
+
+    /// Returns the persistence status of the message, or `None` if the
+    /// message was not detached from a [`BorrowedMessage`].
+    ///
+    /// See [`BorrowedMessage::status`].
+    pub fn status(&self) -> Option<MessageStatus> {
+        self.status
+    }
+
+    /// Returns the delivery latency of the message, or `None` if it is not
+    /// available.
+    ///
+    /// See [`BorrowedMessage::latency`].
+    pub fn latency(&self) -> Option<Duration> {
+        self.latency
+    }
+
     /// Detaches the [`OwnedHeaders`] from this `OwnedMessage`.
     pub fn detach_headers(&mut self) -> Option<OwnedHeaders> {
         self.headers.take()
     }
+
+    /// Replaces the payload of this `OwnedMessage`.
+    pub fn set_payload(&mut self, payload: Option<Vec<u8>>) {
+        self.payload = payload;
+    }
 }
 
 impl Message for OwnedMessage {
@@ -742,6 +921,12 @@ impl ToBytes for String {
     }
 }
 
+impl<'a> ToBytes for IoSlice<'a> {
+    fn to_bytes(&self) -> &[u8] {
+        self
+    }
+}
+
 impl<'a, T: ToBytes> ToBytes for &'a T {
     fn to_bytes(&self) -> &[u8] {
         (*self).to_bytes()
@@ -772,9 +957,56 @@ array_impls! {
     30 31 32
 }
 
+/// A value that determines the key of the messages it is sent in.
+///
+/// Log compaction retains the latest message for each key, so it only works
+/// if all the messages about the same entity are produced with the same key.
+/// Implementing `KeyedBy` on a domain type defines how its key is derived once,
+/// instead of repeating the derivation wherever the type is produced.
+/// [`TypedProducer::send_keyed`] uses it to key the messages that are sent
+/// without an explicit key.
+///
+/// [`TypedProducer::send_keyed`]: crate::producer::TypedProducer::send_keyed
+///
+/// ```
+/// use rdkafka::message::KeyedBy;
+///
+/// struct Order {
+///     customer_id: u64,
+///     order_id: u64,
+/// }
+///
+/// impl KeyedBy for Order {
+///     type Key = String;
+///
+///     fn key(&self) -> String {
+///         format!("{}/{}", self.customer_id, self.order_id)
+///     }
+/// }
+///
+/// let order = Order { customer_id: 42, order_id: 7 };
+/// assert_eq!(order.key(), "42/7");
+/// ```
+pub trait KeyedBy {
+    /// The type of the key.
+    type Key: ToBytes;
+
+    /// Returns the key of the messages for this value.
+    fn key(&self) -> Self::Key;
+}
+
+impl<T: KeyedBy + ?Sized> KeyedBy for &T {
+    type Key = T::Key;
+
+    fn key(&self) -> T::Key {
+        (*self).key()
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use std::collections::HashMap;
     use std::time::SystemTime;
 
     #[test]
@@ -825,4 +1057,42 @@ mod test {
             })
         );
     }
+
+    #[test]
+    fn test_payload_json() {
+        let message = |payload: &[u8]| {
+            OwnedMessage::new(
+                Some(payload.to_vec()),
+                None,
+                "topic".into(),
+                Timestamp::NotAvailable,
+                3,
+                42,
+                None,
+            )
+        };
+
+        let json = message(br#"{"a": [1, 2]}"#);
+        assert_eq!(
+            json.payload_json().unwrap(),
+            serde_json::json!({"a": [1, 2]})
+        );
+        assert_eq!(
+            json.payload_as::<HashMap<String, Vec<u32>>>().unwrap()["a"],
+            [1, 2]
+        );
+
+        let long = vec![b'x'; 100];
+        match message(&long).payload_json() {
+            Err(KafkaError::PayloadDecoding(e)) => {
+                assert!(e.contains("topic topic, partition 3, offset 42"));
+                assert!(e.contains(&format!("\"{}\"... (100 bytes)", "x".repeat(64))));
+            }
+            _ => panic!("expected a PayloadDecoding error"),
+        }
+        match message(b"{").payload_as::<u32>() {
+            Err(KafkaError::PayloadDecoding(e)) => assert!(e.ends_with("payload \"{\")")),
+            _ => panic!("expected a PayloadDecoding error"),
+        }
+    }
 }
@@ -0,0 +1,622 @@
+//! Prometheus metrics built on the client statistics.
+//!
+//! A [`StatisticsCollector`] turns the [`Statistics`] that librdkafka emits
+//! every `statistics.interval.ms` into Prometheus gauges and counters, such as
+//! the broker round-trip times, the depth of the producer queues, the consumer
+//! lag and the number of bytes sent and received. It is a [`Collector`] of the
+//! [`prometheus`] crate, which is registered against the [`Registry`] of the
+//! application and serves the values of the latest statistics. It can also
+//! serve the [`TopicMetrics`] maintained by the producers.
+//!
+//! The collector can be fed from a custom [`ClientContext::stats`], or through
+//! the [`MetricsContext`], which does nothing else.
+//!
+//! ```no_run
+//! use prometheus::Registry;
+//!
+//! use rdkafka::config::ClientConfig;
+//! use rdkafka::consumer::BaseConsumer;
+//! use rdkafka::metrics::{MetricsContext, StatisticsCollector};
+//!
+//! let registry = Registry::new();
+//! let collector = StatisticsCollector::new();
+//! registry.register(Box::new(collector.clone())).unwrap();
+//! let consumer: BaseConsumer<_> = ClientConfig::new()
+//!     .set("statistics.interval.ms", "5000")
+//!     .create_with_context(MetricsContext::new(collector))
+//!     .unwrap();
+//! // Serve `registry.gather()` on the metrics endpoint.
+//! ```
+//!
+//! This module requires the `metrics` feature.
+//!
+//! [`ClientContext::stats`]: crate::ClientContext::stats
+//! [`Collector`]: prometheus::core::Collector
+//! [`Registry`]: prometheus::Registry
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use prometheus::core::{Collector, Desc};
+use prometheus::proto::{Counter, Gauge, LabelPair, Metric, MetricFamily, MetricType};
+
+use crate::client::ClientContext;
+use crate::consumer::ConsumerContext;
+use crate::producer::{DeliveryResult, ProducerContext, TopicMetrics};
+use crate::statistics::Statistics;
+
+const CLIENT: &[&str] = &["client_id"];
+const BROKER: &[&str] = &["client_id", "broker"];
+const TOPIC: &[&str] = &["client_id", "topic"];
+const PARTITION: &[&str] = &["client_id", "topic", "partition"];
+
+/// The description of a metric, whose name is prefixed by the collector.
+struct Spec {
+    name: &'static str,
+    help: &'static str,
+    kind: MetricType,
+    labels: &'static [&'static str],
+}
+
+const fn counter(name: &'static str, help: &'static str, labels: &'static [&'static str]) -> Spec {
+    Spec {
+        name,
+        help,
+        kind: MetricType::COUNTER,
+        labels,
+    }
+}
+
+const fn gauge(name: &'static str, help: &'static str, labels: &'static [&'static str]) -> Spec {
+    Spec {
+        name,
+        help,
+        kind: MetricType::GAUGE,
+        labels,
+    }
+}
+
+const SPECS: &[Spec] = &[
+    gauge(
+        "queued_messages",
+        "Messages in the producer queues.",
+        CLIENT,
+    ),
+    gauge("queued_bytes", "Bytes in the producer queues.", CLIENT),
+    gauge("reply_queue_ops", "Operations in the reply queue.", CLIENT),
+    counter("tx_requests_total", "Requests sent to brokers.", CLIENT),
+    counter("tx_bytes_total", "Bytes sent to brokers.", CLIENT),
+    counter(
+        "rx_responses_total",
+        "Responses received from brokers.",
+        CLIENT,
+    ),
+    counter("rx_bytes_total", "Bytes received from brokers.", CLIENT),
+    counter("tx_messages_total", "Messages produced.", CLIENT),
+    counter("rx_messages_total", "Messages consumed.", CLIENT),
+    gauge(
+        "broker_up",
+        "Whether the connection to the broker is up.",
+        BROKER,
+    ),
+    gauge(
+        "broker_outbuf_messages",
+        "Messages awaiting transmission to the broker.",
+        BROKER,
+    ),
+    gauge(
+        "broker_waitresp_messages",
+        "Messages in flight to the broker.",
+        BROKER,
+    ),
+    counter("broker_tx_bytes_total", "Bytes sent to the broker.", BROKER),
+    counter(
+        "broker_rx_bytes_total",
+        "Bytes received from the broker.",
+        BROKER,
+    ),
+    counter(
+        "broker_tx_errors_total",
+        "Transmission errors with the broker.",
+        BROKER,
+    ),
+    counter(
+        "broker_rx_errors_total",
+        "Reception errors with the broker.",
+        BROKER,
+    ),
+    counter(
+        "broker_request_timeouts_total",
+        "Requests to the broker that timed out.",
+        BROKER,
+    ),
+    gauge(
+        "broker_rtt_avg_seconds",
+        "Average round-trip time to the broker.",
+        BROKER,
+    ),
+    gauge(
+        "broker_rtt_p99_seconds",
+        "99th percentile of the round-trip time to the broker.",
+        BROKER,
+    ),
+    gauge(
+        "partition_queued_messages",
+        "Messages waiting to be produced to the partition.",
+        PARTITION,
+    ),
+    gauge(
+        "partition_fetchq_messages",
+        "Prefetched messages of the partition.",
+        PARTITION,
+    ),
+    counter(
+        "partition_tx_bytes_total",
+        "Bytes produced to the partition.",
+        PARTITION,
+    ),
+    counter(
+        "partition_rx_bytes_total",
+        "Bytes consumed from the partition.",
+        PARTITION,
+    ),
+    gauge(
+        "partition_consumer_lag",
+        "Messages between the committed offset and the high watermark.",
+        PARTITION,
+    ),
+    counter(
+        "consumer_group_rebalances_total",
+        "Rebalances of the consumer group.",
+        CLIENT,
+    ),
+    gauge(
+        "consumer_group_assigned_partitions",
+        "Partitions assigned to the consumer.",
+        CLIENT,
+    ),
+    counter(
+        "topic_messages_sent_total",
+        "Messages enqueued for the topic.",
+        TOPIC,
+    ),
+    counter(
+        "topic_messages_delivered_total",
+        "Messages delivered to the topic.",
+        TOPIC,
+    ),
+    counter(
+        "topic_messages_failed_total",
+        "Messages that failed to be delivered to the topic.",
+        TOPIC,
+    ),
+    counter(
+        "topic_bytes_sent_total",
+        "Bytes enqueued for the topic.",
+        TOPIC,
+    ),
+    counter(
+        "topic_bytes_delivered_total",
+        "Bytes delivered to the topic.",
+        TOPIC,
+    ),
+];
+
+/// The values of the metrics, in the order of [`SPECS`].
+struct Families<'a> {
+    prefix: &'a str,
+    families: Vec<MetricFamily>,
+}
+
+impl<'a> Families<'a> {
+    fn new(prefix: &'a str) -> Families<'a> {
+        Families {
+            prefix,
+            families: Vec::new(),
+        }
+    }
+
+    fn record(&mut self, name: &str, label_values: &[&str], value: f64) {
+        if self.families.is_empty() {
+            self.families = SPECS
+                .iter()
+                .map(|spec| {
+                    let mut family = MetricFamily::default();
+                    family.set_name(format!("{}_{}", self.prefix, spec.name));
+                    family.set_help(spec.help.to_owned());
+                    family.set_field_type(spec.kind);
+                    family
+                })
+                .collect();
+        }
+        let index = SPECS
+            .iter()
+            .position(|spec| spec.name == name)
+            .expect("metric is specified");
+        let spec = &SPECS[index];
+        debug_assert_eq!(spec.labels.len(), label_values.len());
+        let mut metric = Metric::default();
+        let labels: Vec<LabelPair> = spec
+            .labels
+            .iter()
+            .zip(label_values)
+            .map(|(name, value)| {
+                let mut label = LabelPair::default();
+                label.set_name((*name).to_owned());
+                label.set_value((*value).to_owned());
+                label
+            })
+            .collect();
+        // The labels are a `RepeatedField` when the `protobuf` feature of the
+        // `prometheus` crate is enabled.
+        #[allow(clippy::useless_conversion)]
+        metric.set_label(labels.into());
+        match spec.kind {
+            MetricType::COUNTER => {
+                let mut counter = Counter::default();
+                counter.set_value(value);
+                metric.set_counter(counter);
+            }
+            _ => {
+                let mut gauge = Gauge::default();
+                gauge.set_value(value);
+                metric.set_gauge(gauge);
+            }
+        }
+        self.families[index].mut_metric().push(metric);
+    }
+}
+
+struct CollectorState {
+    prefix: String,
+    descs: Vec<Desc>,
+    statistics: Mutex<Vec<MetricFamily>>,
+    topic_metrics: Mutex<Vec<MetricFamily>>,
+}
+
+/// A Prometheus [`Collector`] that serves the latest statistics of a client.
+///
+/// Every metric is labeled with the `client_id` of the client, and, depending
+/// on the metric, with the `broker`, the `topic` and the `partition` it
+/// refers to. The names of the metrics are prefixed with `rdkafka` by default.
+/// Counters are served with the absolute values maintained by librdkafka.
+///
+/// The collector is cheap to clone, and its clones share their values, so
+/// that one clone can be registered while another one is fed the statistics.
+/// Each collection only serves the series of the latest statistics, so that
+/// partitions that are no longer assigned or produced to disappear.
+///
+/// [`Collector`]: prometheus::core::Collector
+#[derive(Clone)]
+pub struct StatisticsCollector {
+    state: Arc<CollectorState>,
+}
+
+impl StatisticsCollector {
+    /// Creates a collector whose metrics are prefixed with `rdkafka`.
+    pub fn new() -> StatisticsCollector {
+        StatisticsCollector::with_prefix("rdkafka").expect("prefix is valid")
+    }
+
+    /// Creates a collector whose metrics are prefixed with the specified
+    /// prefix.
+    ///
+    /// Returns an error if the prefix makes for invalid metric names.
+    pub fn with_prefix<S: Into<String>>(prefix: S) -> prometheus::Result<StatisticsCollector> {
+        let prefix = prefix.into();
+        let descs = SPECS
+            .iter()
+            .map(|spec| {
+                Desc::new(
+                    format!("{}_{}", prefix, spec.name),
+                    spec.help.to_owned(),
+                    spec.labels.iter().map(|&label| label.to_owned()).collect(),
+                    HashMap::new(),
+                )
+            })
+            .collect::<prometheus::Result<_>>()?;
+        Ok(StatisticsCollector {
+            state: Arc::new(CollectorState {
+                prefix,
+                descs,
+                statistics: Mutex::new(Vec::new()),
+                topic_metrics: Mutex::new(Vec::new()),
+            }),
+        })
+    }
+
+    /// Records the metrics derived from the statistics, replacing the ones
+    /// of the previous statistics.
+    pub fn export(&self, stats: &Statistics) {
+        let mut families = Families::new(&self.state.prefix);
+        let client = [stats.client_id.as_str()];
+        families.record("queued_messages", &client, stats.msg_cnt as f64);
+        families.record("queued_bytes", &client, stats.msg_size as f64);
+        families.record("reply_queue_ops", &client, stats.replyq as f64);
+        families.record("tx_requests_total", &client, stats.tx as f64);
+        families.record("tx_bytes_total", &client, stats.tx_bytes as f64);
+        families.record("rx_responses_total", &client, stats.rx as f64);
+        families.record("rx_bytes_total", &client, stats.rx_bytes as f64);
+        families.record("tx_messages_total", &client, stats.txmsgs as f64);
+        families.record("rx_messages_total", &client, stats.rxmsgs as f64);
+
+        for broker in stats.brokers.values() {
+            let labels = [stats.client_id.as_str(), broker.name.as_str()];
+            let up = if broker.state == "UP" { 1.0 } else { 0.0 };
+            families.record("broker_up", &labels, up);
+            families.record(
+                "broker_outbuf_messages",
+                &labels,
+                broker.outbuf_msg_cnt as f64,
+            );
+            families.record(
+                "broker_waitresp_messages",
+                &labels,
+                broker.waitresp_msg_cnt as f64,
+            );
+            families.record("broker_tx_bytes_total", &labels, broker.txbytes as f64);
+            families.record("broker_rx_bytes_total", &labels, broker.rxbytes as f64);
+            families.record("broker_tx_errors_total", &labels, broker.txerrs as f64);
+            families.record("broker_rx_errors_total", &labels, broker.rxerrs as f64);
+            families.record(
+                "broker_request_timeouts_total",
+                &labels,
+                broker.req_timeouts as f64,
+            );
+            if let Some(rtt) = &broker.rtt {
+                families.record("broker_rtt_avg_seconds", &labels, micros_to_secs(rtt.avg));
+                families.record("broker_rtt_p99_seconds", &labels, micros_to_secs(rtt.p99));
+            }
+        }
+
+        for topic in stats.topics.values() {
+            for partition in topic.partitions.values() {
+                // The internal unassigned partition holds the messages that
+                // have not been partitioned yet.
+                if partition.partition < 0 {
+                    continue;
+                }
+                let partition_id = partition.partition.to_string();
+                let labels = [
+                    stats.client_id.as_str(),
+                    topic.topic.as_str(),
+                    partition_id.as_str(),
+                ];
+                families.record(
+                    "partition_queued_messages",
+                    &labels,
+                    (partition.msgq_cnt + partition.xmit_msgq_cnt) as f64,
+                );
+                families.record(
+                    "partition_fetchq_messages",
+                    &labels,
+                    partition.fetchq_cnt as f64,
+                );
+                families.record(
+                    "partition_tx_bytes_total",
+                    &labels,
+                    partition.txbytes as f64,
+                );
+                families.record(
+                    "partition_rx_bytes_total",
+                    &labels,
+                    partition.rxbytes as f64,
+                );
+                // The lag is -1 until the offsets of the partition are known.
+                if partition.consumer_lag >= 0 {
+                    families.record(
+                        "partition_consumer_lag",
+                        &labels,
+                        partition.consumer_lag as f64,
+                    );
+                }
+            }
+        }
+
+        if let Some(cgrp) = &stats.cgrp {
+            families.record(
+                "consumer_group_rebalances_total",
+                &client,
+                cgrp.rebalance_cnt as f64,
+            );
+            families.record(
+                "consumer_group_assigned_partitions",
+                &client,
+                f64::from(cgrp.assignment_size),
+            );
+        }
+
+        *self.state.statistics.lock().unwrap() = families.families;
+    }
+
+    /// Records the per-topic metrics of a producer, as returned by its
+    /// `metrics` method, replacing the ones previously recorded.
+    ///
+    /// The producers do not report these metrics on their own, so they must
+    /// be recorded periodically, e.g. whenever the statistics are.
+    pub fn export_topic_metrics(&self, client_id: &str, metrics: &HashMap<String, TopicMetrics>) {
+        let mut families = Families::new(&self.state.prefix);
+        for (topic, metrics) in metrics {
+            let labels = [client_id, topic.as_str()];
+            families.record(
+                "topic_messages_sent_total",
+                &labels,
+                metrics.messages_sent as f64,
+            );
+            families.record(
+                "topic_messages_delivered_total",
+                &labels,
+                metrics.messages_delivered as f64,
+            );
+            families.record(
+                "topic_messages_failed_total",
+                &labels,
+                metrics.messages_failed as f64,
+            );
+            families.record("topic_bytes_sent_total", &labels, metrics.bytes_sent as f64);
+            families.record(
+                "topic_bytes_delivered_total",
+                &labels,
+                metrics.bytes_delivered as f64,
+            );
+        }
+        *self.state.topic_metrics.lock().unwrap() = families.families;
+    }
+}
+
+impl Default for StatisticsCollector {
+    fn default() -> StatisticsCollector {
+        StatisticsCollector::new()
+    }
+}
+
+impl Collector for StatisticsCollector {
+    fn desc(&self) -> Vec<&Desc> {
+        self.state.descs.iter().collect()
+    }
+
+    fn collect(&self) -> Vec<MetricFamily> {
+        let mut families = self.state.statistics.lock().unwrap().clone();
+        families.extend(self.state.topic_metrics.lock().unwrap().iter().cloned());
+        families
+    }
+}
+
+impl fmt::Debug for StatisticsCollector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StatisticsCollector")
+            .field("prefix", &self.state.prefix)
+            .finish()
+    }
+}
+
+fn micros_to_secs(micros: i64) -> f64 {
+    micros as f64 / 1_000_000.0
+}
+
+/// A client context that records the statistics of the client with a
+/// [`StatisticsCollector`].
+///
+/// It can be used by consumers and producers alike, and otherwise behaves
+/// like the default contexts.
+#[derive(Debug)]
+pub struct MetricsContext {
+    collector: StatisticsCollector,
+}
+
+impl MetricsContext {
+    /// Creates a context that records the statistics with the collector.
+    pub fn new(collector: StatisticsCollector) -> MetricsContext {
+        MetricsContext { collector }
+    }
+
+    /// Returns the collector of the context.
+    pub fn collector(&self) -> &StatisticsCollector {
+        &self.collector
+    }
+}
+
+impl ClientContext for MetricsContext {
+    fn stats(&self, statistics: Statistics) {
+        self.collector.export(&statistics);
+    }
+}
+
+impl ConsumerContext for MetricsContext {}
+
+impl ProducerContext for MetricsContext {
+    type DeliveryOpaque = ();
+
+    fn delivery(&self, _: &DeliveryResult<'_>, _: Self::DeliveryOpaque) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use prometheus::{Encoder, Registry, TextEncoder};
+
+    use super::*;
+    use crate::statistics::{Broker, Partition, Topic, Window};
+
+    fn render(registry: &Registry) -> String {
+        let mut text = Vec::new();
+        TextEncoder::new()
+            .encode(&registry.gather(), &mut text)
+            .unwrap();
+        String::from_utf8(text).unwrap()
+    }
+
+    #[test]
+    fn test_statistics_collector() {
+        let mut stats = Statistics {
+            client_id: "my\"client".into(),
+            msg_cnt: 3,
+            tx_bytes: 1024,
+            ..Statistics::default()
+        };
+        stats.brokers.insert(
+            "localhost:9092/1".into(),
+            Broker {
+                name: "localhost:9092/1".into(),
+                state: "UP".into(),
+                rtt: Some(Window {
+                    avg: 1500,
+                    p99: 4000,
+                    ..Window::default()
+                }),
+                ..Broker::default()
+            },
+        );
+        let mut topic = Topic {
+            topic: "orders".into(),
+            ..Topic::default()
+        };
+        for &(partition, lag) in &[(-1, 0), (0, 42), (1, -1)] {
+            topic.partitions.insert(
+                partition,
+                Partition {
+                    partition,
+                    consumer_lag: lag,
+                    ..Partition::default()
+                },
+            );
+        }
+        stats.topics.insert("orders".into(), topic);
+
+        let registry = Registry::new();
+        let collector = StatisticsCollector::with_prefix("kafka").unwrap();
+        registry.register(Box::new(collector.clone())).unwrap();
+        assert_eq!(render(&registry), "");
+
+        collector.export(&stats);
+        let text = render(&registry);
+        assert!(text.contains("# TYPE kafka_tx_bytes_total counter\n"));
+        assert!(text.contains("kafka_queued_messages{client_id=\"my\\\"client\"} 3\n"));
+        assert!(text.contains(
+            "kafka_broker_rtt_avg_seconds{client_id=\"my\\\"client\",broker=\"localhost:9092/1\"} 0.0015\n"
+        ));
+        assert!(text.contains(
+            "kafka_partition_consumer_lag{client_id=\"my\\\"client\",topic=\"orders\",partition=\"0\"} 42\n"
+        ));
+        assert!(!text.contains("partition=\"1\"} -1"));
+        assert!(!text.contains("partition=\"-1\""));
+
+        let mut metrics = HashMap::new();
+        metrics.insert(
+            "orders".to_owned(),
+            TopicMetrics {
+                messages_sent: 5,
+                ..TopicMetrics::default()
+            },
+        );
+        collector.export_topic_metrics("producer", &metrics);
+        assert!(render(&registry).contains(
+            "kafka_topic_messages_sent_total{client_id=\"producer\",topic=\"orders\"} 5\n"
+        ));
+
+        collector.export(&Statistics::default());
+        let text = render(&registry);
+        assert!(!text.contains("partition=\"0\""));
+        assert!(StatisticsCollector::with_prefix("not a prefix").is_err());
+    }
+}
@@ -0,0 +1,490 @@
+//! At-least-once offset tracking and committing.
+//!
+//! A consumer that processes messages concurrently, e.g. by handing them to a
+//! pool of workers, finishes processing them out of order. Committing the
+//! offset of a message as soon as it is processed would then skip the
+//! messages before it that are still being processed, which are lost if the
+//! consumer crashes. The [`CommitTracker`] instead computes, for every
+//! partition, the highest offset that is safe to commit, i.e. the offset
+//! below which every message was processed.
+//!
+//! ```no_run
+//! use rdkafka::config::ClientConfig;
+//! use rdkafka::consumer::{BaseConsumer, CommitMode, Consumer};
+//! use rdkafka::offsets::CommitTracker;
+//!
+//! let consumer: BaseConsumer = ClientConfig::new()
+//!     .set("bootstrap.servers", "localhost:9092")
+//!     .set("group.id", "workers")
+//!     .set("enable.auto.commit", "false")
+//!     .create()
+//!     .unwrap();
+//! consumer.subscribe(&["jobs"]).unwrap();
+//! let tracker = CommitTracker::new();
+//! for message in consumer.iter() {
+//!     let message = message.unwrap();
+//!     tracker.track(&message);
+//!     // Hand the message over to a worker, which eventually calls
+//!     // `tracker.ack(&message)`.
+//!     tracker.commit(&consumer, CommitMode::Async).unwrap();
+//! }
+//! ```
+//!
+//! Consumers that process messages in order can instead store the offset of
+//! every processed message and commit them in batches with a
+//! [`PeriodicCommitter`].
+
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::consumer::{CommitMode, Consumer, ConsumerContext};
+use crate::error::KafkaResult;
+use crate::message::{BorrowedMessage, Message};
+use crate::topic_partition_list::{Offset, TopicPartitionList};
+
+#[derive(Debug, Default)]
+struct PartitionState {
+    // The offsets that were tracked but not acknowledged yet.
+    pending: BTreeSet<i64>,
+    // The offset after the highest tracked offset.
+    next: i64,
+    // The safe offset that was last returned by `take_offsets`.
+    taken: Option<i64>,
+}
+
+impl PartitionState {
+    fn safe_offset(&self) -> i64 {
+        self.pending.iter().next().copied().unwrap_or(self.next)
+    }
+}
+
+/// Computes the offsets that are safe to commit when messages are
+/// acknowledged out of order.
+///
+/// Every message must be registered with [`CommitTracker::track`] in the
+/// order in which it was consumed, before it is handed over for processing,
+/// and acknowledged with [`CommitTracker::ack`] once it is processed. The safe
+/// offset of a partition is the offset of its oldest message that is not
+/// acknowledged yet, or the offset after its last tracked message if all of
+/// its messages are acknowledged. Since offsets are only ever derived from
+/// tracked messages, gaps in the offsets of a partition, e.g. due to
+/// compaction or to transaction markers, are handled correctly.
+///
+/// The tracker can be shared between threads. When partitions are revoked,
+/// they should be removed with [`CommitTracker::forget`], so that messages
+/// that are still being processed do not hold back the partition if it is
+/// assigned again.
+#[derive(Debug, Default)]
+pub struct CommitTracker {
+    partitions: Mutex<HashMap<String, HashMap<i32, PartitionState>>>,
+}
+
+impl CommitTracker {
+    /// Creates a new, empty tracker.
+    pub fn new() -> CommitTracker {
+        CommitTracker::default()
+    }
+
+    /// Registers a message that is about to be processed.
+    pub fn track<M: Message>(&self, message: &M) {
+        self.track_offset(message.topic(), message.partition(), message.offset());
+    }
+
+    /// Registers the message at the specified offset, like
+    /// [`CommitTracker::track`].
+    pub fn track_offset(&self, topic: &str, partition: i32, offset: i64) {
+        let mut partitions = self.partitions.lock().unwrap();
+        if !partitions.contains_key(topic) {
+            partitions.insert(topic.to_owned(), HashMap::new());
+        }
+        let state = partitions
+            .get_mut(topic)
+            .unwrap()
+            .entry(partition)
+            .or_insert_with(|| PartitionState {
+                next: offset,
+                ..Default::default()
+            });
+        if offset >= state.next {
+            state.pending.insert(offset);
+            state.next = offset + 1;
+        }
+    }
+
+    /// Acknowledges that a tracked message was processed.
+    ///
+    /// Acknowledging a message that is not tracked, e.g. because its
+    /// partition was forgotten, has no effect.
+    pub fn ack<M: Message>(&self, message: &M) {
+        self.ack_offset(message.topic(), message.partition(), message.offset());
+    }
+
+    /// Acknowledges the message at the specified offset, like
+    /// [`CommitTracker::ack`].
+    pub fn ack_offset(&self, topic: &str, partition: i32, offset: i64) {
+        let mut partitions = self.partitions.lock().unwrap();
+        if let Some(state) = partitions
+            .get_mut(topic)
+            .and_then(|partitions| partitions.get_mut(&partition))
+        {
+            state.pending.remove(&offset);
+        }
+    }
+
+    /// Returns the number of tracked messages that are not acknowledged yet.
+    pub fn pending(&self) -> usize {
+        let partitions = self.partitions.lock().unwrap();
+        partitions
+            .values()
+            .flat_map(|partitions| partitions.values())
+            .map(|state| state.pending.len())
+            .sum()
+    }
+
+    /// Returns the safe offset of every tracked partition.
+    pub fn safe_offsets(&self) -> TopicPartitionList {
+        self.offsets(false)
+    }
+
+    /// Returns the safe offset of the tracked partitions whose safe offset
+    /// changed since the previous call, and is thus worth committing.
+    pub fn take_offsets(&self) -> TopicPartitionList {
+        let tpl = self.offsets(true);
+        self.mark_taken(&tpl);
+        tpl
+    }
+
+    fn offsets(&self, changed_only: bool) -> TopicPartitionList {
+        let partitions = self.partitions.lock().unwrap();
+        let mut tpl = TopicPartitionList::new();
+        for (topic, partitions) in partitions.iter() {
+            for (&partition, state) in partitions.iter() {
+                let offset = state.safe_offset();
+                if changed_only && state.taken == Some(offset) {
+                    continue;
+                }
+                tpl.add_partition_offset(topic, partition, Offset::Offset(offset))
+                    .expect("offset is valid");
+            }
+        }
+        tpl
+    }
+
+    // Records the offsets of `tpl` as taken, so that they are not returned by
+    // `take_offsets` again until they change.
+    fn mark_taken(&self, tpl: &TopicPartitionList) {
+        let mut partitions = self.partitions.lock().unwrap();
+        for elem in tpl.elements() {
+            if let (Some(state), Offset::Offset(offset)) = (
+                partitions
+                    .get_mut(elem.topic())
+                    .and_then(|partitions| partitions.get_mut(&elem.partition())),
+                elem.offset(),
+            ) {
+                state.taken = Some(offset);
+            }
+        }
+    }
+
+    /// Stops tracking the specified partitions, e.g. because they were
+    /// revoked from the consumer.
+    pub fn forget(&self, tpl: &TopicPartitionList) {
+        let mut partitions = self.partitions.lock().unwrap();
+        for elem in tpl.elements() {
+            if let Some(topic) = partitions.get_mut(elem.topic()) {
+                topic.remove(&elem.partition());
+                if topic.is_empty() {
+                    partitions.remove(elem.topic());
+                }
+            }
+        }
+    }
+
+    /// Stores the safe offsets that changed since they were last taken with
+    /// the consumer, to be committed by the next automatic commit.
+    ///
+    /// This requires `enable.auto.offset.store` to be set to `false`. If the
+    /// offsets cannot be stored, they are not marked as taken, and are stored
+    /// again by the next call.
+    pub fn store<C, T>(&self, consumer: &T) -> KafkaResult<()>
+    where
+        C: ConsumerContext,
+        T: Consumer<C>,
+    {
+        let tpl = self.offsets(true);
+        if tpl.count() == 0 {
+            return Ok(());
+        }
+        consumer.store_offsets(&tpl)?;
+        self.mark_taken(&tpl);
+        Ok(())
+    }
+
+    /// Commits the safe offsets that changed since they were last taken with
+    /// the consumer.
+    ///
+    /// If the commit fails, the offsets are not marked as taken, and are
+    /// committed again by the next call. With [`CommitMode::Async`], only
+    /// the failure to enqueue the commit is detected; the result of the
+    /// commit itself is passed to [`ConsumerContext::commit_callback`].
+    pub fn commit<C, T>(&self, consumer: &T, mode: CommitMode) -> KafkaResult<()>
+    where
+        C: ConsumerContext,
+        T: Consumer<C>,
+    {
+        let tpl = self.offsets(true);
+        if tpl.count() == 0 {
+            return Ok(());
+        }
+        consumer.commit(&tpl, mode)?;
+        self.mark_taken(&tpl);
+        Ok(())
+    }
+}
+
+/// Stores the offsets of processed messages, and commits them every N
+/// messages or every interval, whichever comes first.
+///
+/// This covers the most common manual commit pattern, where messages are
+/// processed in order and their offsets are only stored once they are
+/// processed, but committing every message would be too expensive. It
+/// requires `enable.auto.offset.store` to be set to `false`, and
+/// `enable.auto.commit` to be set to `false` too, since committing is then
+/// left to the committer.
+///
+/// The interval is only checked when [`PeriodicCommitter::store`] or
+/// [`PeriodicCommitter::tick`] is called, so consumers that can go idle
+/// should call `tick` whenever their poll times out, and
+/// [`PeriodicCommitter::commit`] before shutting down.
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use rdkafka::config::ClientConfig;
+/// use rdkafka::consumer::{BaseConsumer, Consumer};
+/// use rdkafka::offsets::PeriodicCommitter;
+///
+/// let consumer: BaseConsumer = ClientConfig::new()
+///     .set("bootstrap.servers", "localhost:9092")
+///     .set("group.id", "workers")
+///     .set("enable.auto.commit", "false")
+///     .set("enable.auto.offset.store", "false")
+///     .create()
+///     .unwrap();
+/// consumer.subscribe(&["jobs"]).unwrap();
+/// let mut committer = PeriodicCommitter::new()
+///     .every_messages(1000)
+///     .every(Duration::from_secs(5));
+/// loop {
+///     match consumer.poll(Duration::from_secs(1)) {
+///         Some(Ok(message)) => {
+///             // Process the message.
+///             committer.store(&consumer, &message).unwrap();
+///         }
+///         Some(Err(e)) => eprintln!("Kafka error: {}", e),
+///         None => committer.tick(&consumer).unwrap(),
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct PeriodicCommitter {
+    every_messages: Option<u64>,
+    every: Option<Duration>,
+    mode: CommitMode,
+    // The number of offsets stored since the last commit.
+    stored: u64,
+    last_commit: Instant,
+}
+
+impl PeriodicCommitter {
+    /// Creates a committer that commits asynchronously every 5 seconds, and
+    /// has no limit on the number of messages between commits.
+    pub fn new() -> PeriodicCommitter {
+        PeriodicCommitter {
+            every_messages: None,
+            every: Some(Duration::from_secs(5)),
+            mode: CommitMode::Async,
+            stored: 0,
+            last_commit: Instant::now(),
+        }
+    }
+
+    /// Commits once `n` messages were stored since the last commit, or
+    /// removes the limit on the number of messages if `n` is zero.
+    pub fn every_messages(mut self, n: u64) -> PeriodicCommitter {
+        self.every_messages = if n == 0 { None } else { Some(n) };
+        self
+    }
+
+    /// Commits once `interval` elapsed since the last commit.
+    pub fn every(mut self, interval: Duration) -> PeriodicCommitter {
+        self.every = Some(interval);
+        self
+    }
+
+    /// Removes the interval between commits, so that commits are only
+    /// triggered by the number of messages.
+    pub fn never_by_time(mut self) -> PeriodicCommitter {
+        self.every = None;
+        self
+    }
+
+    /// Sets the mode of the commits, which defaults to [`CommitMode::Async`].
+    pub fn mode(mut self, mode: CommitMode) -> PeriodicCommitter {
+        self.mode = mode;
+        self
+    }
+
+    /// Returns the number of offsets that were stored since the last commit.
+    pub fn stored(&self) -> u64 {
+        self.stored
+    }
+
+    /// Stores the offset of a processed message, and commits the stored
+    /// offsets if a commit is due.
+    ///
+    /// Returns whether the offsets were committed.
+    pub fn store<C, T>(&mut self, consumer: &T, message: &BorrowedMessage<'_>) -> KafkaResult<bool>
+    where
+        C: ConsumerContext,
+        T: Consumer<C>,
+    {
+        consumer.store_offset_from_message(message)?;
+        self.stored += 1;
+        self.tick(consumer)
+    }
+
+    /// Commits the stored offsets if a commit is due.
+    ///
+    /// Returns whether the offsets were committed.
+    pub fn tick<C, T>(&mut self, consumer: &T) -> KafkaResult<bool>
+    where
+        C: ConsumerContext,
+        T: Consumer<C>,
+    {
+        if !self.is_due(Instant::now()) {
+            return Ok(false);
+        }
+        self.commit(consumer)?;
+        Ok(true)
+    }
+
+    /// Commits the offsets that were stored since the last commit, if any.
+    pub fn commit<C, T>(&mut self, consumer: &T) -> KafkaResult<()>
+    where
+        C: ConsumerContext,
+        T: Consumer<C>,
+    {
+        if self.stored == 0 {
+            return Ok(());
+        }
+        consumer.commit_consumer_state(self.mode)?;
+        self.stored = 0;
+        self.last_commit = Instant::now();
+        Ok(())
+    }
+
+    fn is_due(&self, now: Instant) -> bool {
+        if self.stored == 0 {
+            return false;
+        }
+        let by_messages = matches!(self.every_messages, Some(n) if self.stored >= n);
+        let by_time =
+            matches!(self.every, Some(every) if now.duration_since(self.last_commit) >= every);
+        by_messages || by_time
+    }
+}
+
+impl Default for PeriodicCommitter {
+    fn default() -> PeriodicCommitter {
+        PeriodicCommitter::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offset(tpl: &TopicPartitionList, topic: &str, partition: i32) -> Option<Offset> {
+        tpl.find_partition(topic, partition)
+            .map(|elem| elem.offset())
+    }
+
+    #[test]
+    fn test_commit_tracker() {
+        let tracker = CommitTracker::new();
+        for &o in &[10, 11, 13, 14] {
+            tracker.track_offset("jobs", 0, o);
+        }
+        tracker.track_offset("jobs", 1, 5);
+        assert_eq!(tracker.pending(), 5);
+
+        let tpl = tracker.take_offsets();
+        assert_eq!(offset(&tpl, "jobs", 0), Some(Offset::Offset(10)));
+        assert_eq!(offset(&tpl, "jobs", 1), Some(Offset::Offset(5)));
+        assert_eq!(tracker.take_offsets().count(), 0);
+
+        tracker.ack_offset("jobs", 0, 11);
+        tracker.ack_offset("jobs", 0, 13);
+        assert_eq!(tracker.take_offsets().count(), 0);
+
+        // The gap at offset 12 does not hold the partition back.
+        tracker.ack_offset("jobs", 0, 10);
+        let tpl = tracker.take_offsets();
+        assert_eq!(tpl.count(), 1);
+        assert_eq!(offset(&tpl, "jobs", 0), Some(Offset::Offset(14)));
+
+        tracker.ack_offset("jobs", 0, 14);
+        tracker.ack_offset("jobs", 0, 99);
+        assert_eq!(
+            offset(&tracker.safe_offsets(), "jobs", 0),
+            Some(Offset::Offset(15))
+        );
+
+        let mut revoked = TopicPartitionList::new();
+        revoked.add_partition("jobs", 1);
+        tracker.forget(&revoked);
+        assert_eq!(tracker.pending(), 0);
+        assert_eq!(offset(&tracker.safe_offsets(), "jobs", 1), None);
+    }
+
+    #[test]
+    fn test_commit_tracker_mark_taken() {
+        let tracker = CommitTracker::new();
+        tracker.track_offset("jobs", 0, 10);
+        tracker.track_offset("jobs", 1, 5);
+
+        // Offsets that failed to be committed are returned again.
+        let tpl = tracker.offsets(true);
+        assert_eq!(tpl.count(), 2);
+        assert_eq!(tracker.offsets(true).count(), 2);
+
+        let mut committed = TopicPartitionList::new();
+        committed
+            .add_partition_offset("jobs", 0, Offset::Offset(10))
+            .unwrap();
+        tracker.mark_taken(&committed);
+        let tpl = tracker.offsets(true);
+        assert_eq!(tpl.count(), 1);
+        assert_eq!(offset(&tpl, "jobs", 1), Some(Offset::Offset(5)));
+    }
+
+    #[test]
+    fn test_periodic_committer_is_due() {
+        let mut committer = PeriodicCommitter::new()
+            .every_messages(3)
+            .every(Duration::from_secs(60));
+        let start = committer.last_commit;
+        assert!(!committer.is_due(start + Duration::from_secs(120)));
+        committer.stored = 2;
+        assert!(!committer.is_due(start));
+        assert!(committer.is_due(start + Duration::from_secs(60)));
+        committer.stored = 3;
+        assert!(committer.is_due(start));
+
+        let mut committer = committer.every_messages(0).never_by_time();
+        committer.stored = 1000;
+        assert!(!committer.is_due(start + Duration::from_secs(3600)));
+    }
+}
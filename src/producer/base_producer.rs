@@ -41,31 +41,70 @@
 //! acknowledge messages quickly enough. If this error is returned, the caller
 //! should wait and try again.
 
-use std::ffi::CString;
-use std::mem;
+use std::any::Any;
+use std::cmp;
+use std::collections::HashMap;
+use std::ffi::{CStr, CString};
+use std::mem::{self, ManuallyDrop};
 use std::os::raw::c_void;
 use std::ptr;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
-use std::thread::{self, JoinHandle};
-use std::time::Duration;
+use std::slice;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 
 use rdkafka_sys as rdsys;
 use rdkafka_sys::rd_kafka_vtype_t::*;
 use rdkafka_sys::types::*;
 
-use crate::client::Client;
-use crate::config::{ClientConfig, FromClientConfig, FromClientConfigAndContext};
+use crate::client::{handle_callback_event, Client, ClientOpaque, NativeTopic};
+use crate::config::{
+    ClientConfig, ConfigClients, FromClientConfig, FromClientConfigAndContext, NativeTopicConfig,
+    TopicConfig,
+};
 use crate::consumer::ConsumerGroupMetadata;
 use crate::error::{IsError, KafkaError, KafkaResult, RDKafkaError};
-use crate::log::{trace, warn};
+use crate::interceptor::{intercept_send, InterceptedRecord};
+use crate::log::{enter_span, trace, warn};
 use crate::message::{BorrowedMessage, OwnedHeaders, ToBytes};
-use crate::producer::{DefaultProducerContext, Producer, ProducerContext, PurgeConfig};
+use crate::producer::delivery_handle::{delivery_handle, DeliveryHandle, DeliveryReporter};
+#[cfg(feature = "watchdog")]
+use crate::producer::watchdog::Watchdog;
+use crate::producer::{
+    DefaultProducerContext, Producer, ProducerContext, PurgeConfig, QueueDepth, QueueWatermark,
+    QueueWatermarks, TopicMetrics,
+};
 use crate::topic_partition_list::TopicPartitionList;
-use crate::util::{IntoOpaque, Timeout};
+use crate::util::{CallbackThread, IntoOpaque, Timeout};
 
 pub use crate::message::DeliveryResult;
 
+/// Callback that gets called from librdkafka to pick the partition of messages
+/// that do not specify one, when a custom partitioner is enabled.
+unsafe extern "C" fn partitioner_cb<C: ProducerContext>(
+    topic: *const RDKafkaTopic,
+    keydata: *const c_void,
+    keylen: usize,
+    partition_cnt: i32,
+    rkt_opaque: *mut c_void,
+    _msg_opaque: *mut c_void,
+) -> i32 {
+    let topic_name = CStr::from_ptr(rdsys::rd_kafka_topic_name(topic)).to_string_lossy();
+    let key = if keydata.is_null() {
+        None
+    } else {
+        Some(slice::from_raw_parts(keydata as *const u8, keylen))
+    };
+    let producer_context = &*(rkt_opaque as *const C);
+    // A panicking partitioner fails the message, like an invalid partition.
+    let mut partition = -1;
+    handle_callback_event(|| {
+        partition = producer_context.partition(&topic_name, key, partition_cnt)
+    });
+    partition
+}
+
 /// Callback that gets called from librdkafka every time a message succeeds or fails to be
 /// delivered.
 unsafe extern "C" fn delivery_cb<C: ProducerContext>(
@@ -73,24 +112,250 @@ unsafe extern "C" fn delivery_cb<C: ProducerContext>(
     msg: *const RDKafkaMessage,
     opaque: *mut c_void,
 ) {
-    let producer_context = &mut *(opaque as *mut C);
+    let bytes = ((*msg).len + (*msg).key_len) as u64;
+    let client_opaque = ClientOpaque::<C>::from_ptr(opaque);
+    if let Some(state) = &client_opaque.producer {
+        state.remove_queued(1, bytes);
+        state.check_queue_watermarks(&*client_opaque.context);
+    }
+    if let Some(state) = &client_opaque.producer {
+        let topic = CStr::from_ptr(rdsys::rd_kafka_topic_name((*msg).rkt)).to_string_lossy();
+        state.with_topic_counters(&topic, |counters| {
+            if (*msg).err.is_error() {
+                counters.messages_failed.fetch_add(1, Ordering::Relaxed);
+            } else {
+                counters.messages_delivered.fetch_add(1, Ordering::Relaxed);
+                counters.bytes_delivered.fetch_add(bytes, Ordering::Relaxed);
+            }
+        });
+    }
+    let producer_context = &client_opaque.context;
+    // The payload of a message sent with `send_zero_copy` is referenced by
+    // the delivery result, so its owner is only dropped once the callback
+    // returns.
+    let _payload_owner = client_opaque
+        .producer
+        .as_ref()
+        .and_then(|state| state.take_zero_copy_payload((*msg).payload));
     let delivery_opaque = C::DeliveryOpaque::from_ptr((*msg)._private);
     let owner = 42u8;
     // Wrap the message pointer into a BorrowedMessage that will only live for the body of this
     // function.
     let delivery_result = BorrowedMessage::from_dr_callback(msg as *mut RDKafkaMessage, &owner);
     trace!("Delivery event received: {:?}", delivery_result);
-    producer_context.delivery(&delivery_result, delivery_opaque);
+    enter_span!(
+        "rdkafka.delivery",
+        topic = (match &delivery_result {
+            Ok(message) | Err((_, message)) => crate::message::Message::topic(message),
+        }),
+        partition = (*msg).partition,
+        offset = (*msg).offset,
+        success = delivery_result.is_ok(),
+    );
+    for interceptor in producer_context.interceptors() {
+        handle_callback_event(|| interceptor.on_acknowledgement(&delivery_result));
+    }
+    handle_callback_event(|| producer_context.delivery(&delivery_result, delivery_opaque));
     match delivery_result {
         // Do not free the message, librdkafka will do it for us
         Ok(message) | Err((_, message)) => mem::forget(message),
     }
 }
 
+/// The state of a producer that its delivery callback has access to.
+#[derive(Default)]
+pub(crate) struct ProducerState {
+    // The owners of the payloads of the messages sent with `send_zero_copy`
+    // whose delivery has not been reported yet, indexed by the address of the
+    // payload. Payloads that share an address, e.g. clones of the same `Arc`,
+    // are interchangeable. The owners are stored as `Any`, so that an owner
+    // can be handed back when its message fails to be enqueued.
+    zero_copy_payloads: Mutex<HashMap<usize, Vec<Box<dyn Any + Send>>>>,
+    // The number of owners in `zero_copy_payloads`, which spares the delivery
+    // callback the lookup when no message is sent with `send_zero_copy`.
+    zero_copy_count: AtomicUsize,
+    // The messages and bytes that were enqueued and whose delivery has not
+    // been reported yet. They are added before the messages are enqueued, so
+    // that a delivery reported right away never brings them below zero.
+    queued_messages: AtomicU64,
+    queued_bytes: AtomicU64,
+    queue_watermarks: Option<QueueWatermarks>,
+    above_high_watermark: AtomicBool,
+    topic_metrics: RwLock<HashMap<String, TopicCounters>>,
+}
+
+impl ProducerState {
+    fn queue_depth(&self) -> QueueDepth {
+        QueueDepth {
+            messages: self.queued_messages.load(Ordering::Relaxed),
+            bytes: self.queued_bytes.load(Ordering::Relaxed),
+        }
+    }
+
+    fn add_queued(&self, messages: u64, bytes: u64) {
+        self.queued_messages.fetch_add(messages, Ordering::Relaxed);
+        self.queued_bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    fn remove_queued(&self, messages: u64, bytes: u64) {
+        self.queued_messages.fetch_sub(messages, Ordering::Relaxed);
+        self.queued_bytes.fetch_sub(bytes, Ordering::Relaxed);
+    }
+
+    fn check_queue_watermarks<C: ProducerContext>(&self, context: &C) {
+        let watermarks = match self.queue_watermarks {
+            Some(watermarks) => watermarks,
+            None => return,
+        };
+        let depth = self.queue_depth();
+        let crossed = if watermarks.is_above_high(depth) {
+            self.above_high_watermark
+                .compare_exchange(false, true, Ordering::Relaxed, Ordering::Relaxed)
+                .ok()
+                .map(|_| QueueWatermark::High)
+        } else if watermarks.is_below_low(depth) {
+            self.above_high_watermark
+                .compare_exchange(true, false, Ordering::Relaxed, Ordering::Relaxed)
+                .ok()
+                .map(|_| QueueWatermark::Low)
+        } else {
+            None
+        };
+        if let Some(watermark) = crossed {
+            context.queue_watermark(watermark, depth);
+        }
+    }
+
+    fn take_zero_copy_payload(&self, payload: *mut c_void) -> Option<Box<dyn Any + Send>> {
+        if self.zero_copy_count.load(Ordering::SeqCst) == 0 {
+            return None;
+        }
+        let mut payloads = self.zero_copy_payloads.lock().unwrap();
+        let owners = payloads.get_mut(&(payload as usize))?;
+        let owner = owners.pop();
+        if owners.is_empty() {
+            payloads.remove(&(payload as usize));
+        }
+        self.zero_copy_count.fetch_sub(1, Ordering::SeqCst);
+        owner
+    }
+
+    /// Removes an owner of type `O` of the payload at `key`, which was
+    /// registered for a message that could not be enqueued.
+    fn take_zero_copy_payload_of<O: Any>(&self, key: usize) -> Option<O> {
+        let mut payloads = self.zero_copy_payloads.lock().unwrap();
+        let owners = payloads.get_mut(&key)?;
+        let position = owners.iter().rposition(|owner| owner.is::<O>())?;
+        let owner = owners.swap_remove(position);
+        if owners.is_empty() {
+            payloads.remove(&key);
+        }
+        self.zero_copy_count.fetch_sub(1, Ordering::SeqCst);
+        owner.downcast().ok().map(|owner| *owner)
+    }
+
+    /// Calls `f` with the counters of the topic, which are created if needed.
+    fn with_topic_counters<F: FnOnce(&TopicCounters)>(&self, topic: &str, f: F) {
+        if let Some(counters) = self.topic_metrics.read().unwrap().get(topic) {
+            return f(counters);
+        }
+        let mut topic_metrics = self.topic_metrics.write().unwrap();
+        f(topic_metrics.entry(topic.to_owned()).or_default())
+    }
+}
+
+/// The counters of a topic, which back its [`TopicMetrics`].
+#[derive(Default)]
+struct TopicCounters {
+    messages_sent: AtomicU64,
+    messages_delivered: AtomicU64,
+    messages_failed: AtomicU64,
+    bytes_sent: AtomicU64,
+    bytes_delivered: AtomicU64,
+}
+
+impl TopicCounters {
+    fn snapshot(&self) -> TopicMetrics {
+        TopicMetrics {
+            messages_sent: self.messages_sent.load(Ordering::Relaxed),
+            messages_delivered: self.messages_delivered.load(Ordering::Relaxed),
+            messages_failed: self.messages_failed.load(Ordering::Relaxed),
+            bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
+            bytes_delivered: self.bytes_delivered.load(Ordering::Relaxed),
+        }
+    }
+}
+
 //
 // ********** BASE PRODUCER **********
 //
 
+/// How long `send` waits for partition metadata when the partition
+/// availability check is enabled.
+const PARTITION_METADATA_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The availability of the partitions of a topic.
+struct PartitionAvailability {
+    fetched_at: Instant,
+    /// The partitions that have a leader, or `None` if the topic is not known
+    /// to the cluster.
+    available: Option<Vec<i32>>,
+}
+
+/// How long to poll for between retries when the producer queue is full.
+const QUEUE_FULL_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+fn as_bytes(opt: Option<&(impl ?Sized + ToBytes)>) -> (*mut c_void, usize) {
+    match opt.map(ToBytes::to_bytes) {
+        None => (ptr::null_mut(), 0),
+        Some(p) => (p.as_ptr() as *mut c_void, p.len()),
+    }
+}
+
+/// Borrows the bytes referenced by a pointer and a length returned by
+/// [`as_bytes`] or by an [`OwnedPayload`].
+unsafe fn bytes_from_raw<'a>(ptr: *mut c_void, len: usize) -> Option<&'a [u8]> {
+    if ptr.is_null() {
+        None
+    } else {
+        Some(slice::from_raw_parts(ptr as *const u8, len))
+    }
+}
+
+/// A value that owns the payload of a message.
+///
+/// Used by [`BaseProducer::send_zero_copy`] to produce messages without
+/// copying their payload into librdkafka's memory. The producer keeps the
+/// owner alive until the delivery callback of the message returns.
+///
+/// # Safety
+///
+/// The slice returned by [`OwnedPayload::payload`] must remain valid, and at
+/// the same address, when the value is moved, and until it is dropped.
+pub unsafe trait OwnedPayload: Send {
+    /// Returns the payload bytes.
+    fn payload(&self) -> &[u8];
+}
+
+// The contents of a `Box`, an `Arc` or a `Vec` are not moved with it.
+unsafe impl<T: AsRef<[u8]> + Send + Sync + ?Sized> OwnedPayload for Box<T> {
+    fn payload(&self) -> &[u8] {
+        (**self).as_ref()
+    }
+}
+
+unsafe impl<T: AsRef<[u8]> + Send + Sync + ?Sized> OwnedPayload for Arc<T> {
+    fn payload(&self) -> &[u8] {
+        (**self).as_ref()
+    }
+}
+
+unsafe impl OwnedPayload for Vec<u8> {
+    fn payload(&self) -> &[u8] {
+        self
+    }
+}
+
 /// A record for the [`BaseProducer`] and [`ThreadedProducer`].
 ///
 /// The `BaseRecord` is a structure that can be used to provide a new record to
@@ -189,6 +454,19 @@ impl<'a, K: ToBytes + ?Sized, P: ToBytes + ?Sized, D: IntoOpaque> BaseRecord<'a,
         self.headers = Some(headers);
         self
     }
+
+    fn replace_opaque<E: IntoOpaque>(self, delivery_opaque: E) -> (BaseRecord<'a, K, P, E>, D) {
+        let record = BaseRecord {
+            topic: self.topic,
+            partition: self.partition,
+            payload: self.payload,
+            key: self.key,
+            timestamp: self.timestamp,
+            headers: self.headers,
+            delivery_opaque,
+        };
+        (record, self.delivery_opaque)
+    }
 }
 
 impl<'a, K: ToBytes + ?Sized, P: ToBytes + ?Sized> BaseRecord<'a, K, P, ()> {
@@ -215,20 +493,75 @@ impl FromClientConfig for BaseProducer<DefaultProducerContext> {
 
 impl<C> FromClientConfigAndContext<C> for BaseProducer<C>
 where
-    C: ProducerContext,
+    C: ProducerContext + 'static,
 {
     /// Creates a new `BaseProducer` starting from a configuration and a
     /// context.
     fn from_config_and_context(config: &ClientConfig, context: C) -> KafkaResult<BaseProducer<C>> {
+        BaseProducer::create(config, context, false)
+    }
+}
+
+impl<C> BaseProducer<C>
+where
+    C: ProducerContext + 'static,
+{
+    /// Creates a producer, which is `polled_internally` if it is owned by a
+    /// producer that polls it from a background thread, like the
+    /// [`ThreadedProducer`].
+    #[cfg_attr(not(feature = "watchdog"), allow(unused_variables))]
+    fn create(
+        config: &ClientConfig,
+        context: C,
+        polled_internally: bool,
+    ) -> KafkaResult<BaseProducer<C>> {
+        config.validate_if_strict(ConfigClients::Producer)?;
         let native_config = config.create_native_config()?;
+        let context = Arc::new(context);
         unsafe { rdsys::rd_kafka_conf_set_dr_msg_cb(native_config.ptr(), Some(delivery_cb::<C>)) };
-        let client = Client::new(
+        if C::ENABLE_CUSTOM_PARTITIONER {
+            unsafe {
+                let mut topic_conf =
+                    rdsys::rd_kafka_conf_get_default_topic_conf(native_config.ptr());
+                if topic_conf.is_null() {
+                    topic_conf = rdsys::rd_kafka_topic_conf_new();
+                    rdsys::rd_kafka_conf_set_default_topic_conf(native_config.ptr(), topic_conf);
+                }
+                rdsys::rd_kafka_topic_conf_set_opaque(
+                    topic_conf,
+                    Arc::as_ptr(&context) as *mut c_void,
+                );
+                rdsys::rd_kafka_topic_conf_set_partitioner_cb(
+                    topic_conf,
+                    Some(partitioner_cb::<C>),
+                );
+            }
+        }
+        let state = Arc::new(ProducerState {
+            queue_watermarks: context.queue_watermarks(),
+            ..ProducerState::default()
+        });
+        let client = Client::new_context_arc(
             config,
             native_config,
             RDKafkaType::RD_KAFKA_PRODUCER,
             context,
+            Some(state.clone()),
         )?;
-        Ok(BaseProducer::from_client(client))
+        // Producers that are polled internally cannot stall, so they do not
+        // need a watchdog thread.
+        #[cfg(feature = "watchdog")]
+        let watchdog = if polled_internally {
+            None
+        } else {
+            Watchdog::start(client.context())
+        };
+        Ok(BaseProducer::from_client(
+            client,
+            state,
+            #[cfg(feature = "watchdog")]
+            watchdog,
+        ))
     }
 }
 
@@ -274,7 +607,26 @@ pub struct BaseProducer<C = DefaultProducerContext>
 where
     C: ProducerContext,
 {
+    // Topic handles must be destroyed before the client, so this field must
+    // come first.
+    topics: RwLock<HashMap<String, Arc<NativeTopic>>>,
+    // Whether `topics` is not empty, which spares sends the lookup when no
+    // topic is registered.
+    has_topics: AtomicBool,
+    // The watchdog holds a reference to the context, which must not outlive
+    // the client.
+    #[cfg(feature = "watchdog")]
+    watchdog: Option<Watchdog>,
     client: Client<C>,
+    state: Arc<ProducerState>,
+    poll_overdue_threshold: Option<Duration>,
+    created_at: Instant,
+    // Milliseconds since `created_at`.
+    last_poll_ms: AtomicU64,
+    last_poll_overdue_ms: AtomicU64,
+    partition_availability_check: Option<Duration>,
+    partition_availability: Mutex<HashMap<String, PartitionAvailability>>,
+    flush_on_drop: Mutex<Option<Duration>>,
 }
 
 impl<C> BaseProducer<C>
@@ -282,8 +634,28 @@ where
     C: ProducerContext,
 {
     /// Creates a base producer starting from a Client.
-    fn from_client(client: Client<C>) -> BaseProducer<C> {
-        BaseProducer { client }
+    fn from_client(
+        client: Client<C>,
+        state: Arc<ProducerState>,
+        #[cfg(feature = "watchdog")] watchdog: Option<Watchdog>,
+    ) -> BaseProducer<C> {
+        let poll_overdue_threshold = client.context().poll_overdue_threshold();
+        let partition_availability_check = client.context().partition_availability_check();
+        BaseProducer {
+            topics: RwLock::new(HashMap::new()),
+            has_topics: AtomicBool::new(false),
+            #[cfg(feature = "watchdog")]
+            watchdog,
+            client,
+            state,
+            poll_overdue_threshold,
+            created_at: Instant::now(),
+            last_poll_ms: AtomicU64::new(0),
+            last_poll_overdue_ms: AtomicU64::new(0),
+            partition_availability_check,
+            partition_availability: Mutex::new(HashMap::new()),
+            flush_on_drop: Mutex::new(None),
+        }
     }
 
     /// Polls the producer, returning the number of events served.
@@ -291,7 +663,230 @@ where
     /// Regular calls to `poll` are required to process the events and execute
     /// the message delivery callbacks.
     pub fn poll<T: Into<Timeout>>(&self, timeout: T) -> i32 {
-        unsafe { rdsys::rd_kafka_poll(self.native_ptr(), timeout.into().as_millis()) }
+        enter_span!("rdkafka.poll");
+        let ret = unsafe { rdsys::rd_kafka_poll(self.native_ptr(), timeout.into().as_millis()) };
+        self.record_poll();
+        ret
+    }
+
+    /// Installs a configuration specific to `topic`.
+    ///
+    /// The properties of `config` override, for this topic only, the topic
+    /// properties set on the [`ClientConfig`] the producer was created from.
+    /// This must be called before the first message is sent to the topic:
+    /// librdkafka ignores the configuration of topics that are already known
+    /// to the producer.
+    pub fn configure_topic(&self, topic: &str, config: &TopicConfig) -> KafkaResult<()> {
+        let topic_c = CString::new(topic)?;
+        let topic_conf = unsafe {
+            NativeTopicConfig::from_ptr(rdsys::rd_kafka_default_topic_conf_dup(self.native_ptr()))
+        };
+        config.apply(&topic_conf)?;
+        let native_topic = unsafe {
+            // librdkafka takes ownership of the configuration, even on failure.
+            let topic_conf = ManuallyDrop::new(topic_conf);
+            NativeTopic::from_ptr(rdsys::rd_kafka_topic_new(
+                self.native_ptr(),
+                topic_c.as_ptr(),
+                topic_conf.ptr(),
+            ))
+        };
+        match native_topic {
+            Some(native_topic) => {
+                self.topics
+                    .write()
+                    .unwrap()
+                    .insert(topic.to_string(), Arc::new(native_topic));
+                self.has_topics.store(true, Ordering::Release);
+                Ok(())
+            }
+            None => Err(KafkaError::Global(
+                unsafe { rdsys::rd_kafka_last_error() }.into(),
+            )),
+        }
+    }
+
+    /// Caches the native handle of `topic`, to speed up sending messages to
+    /// it.
+    ///
+    /// Messages sent to the topics that are registered with this method or
+    /// with [`BaseProducer::configure_topic`] reuse the cached handle, while
+    /// for other topics the topic name is copied into a C string on every
+    /// send. Registering the topics that receive most of the messages thus
+    /// saves an allocation and a lookup in librdkafka per message. Handles
+    /// are only released when the producer is dropped. Registering a topic
+    /// that is registered already has no effect; since the topic then
+    /// becomes known to the producer, it can no longer be configured.
+    pub fn register_topic(&self, topic: &str) -> KafkaResult<()> {
+        let mut topics = self.topics.write().unwrap();
+        if !topics.contains_key(topic) {
+            let native_topic = self.client.native_topic(topic)?;
+            topics.insert(topic.to_string(), Arc::new(native_topic));
+            self.has_topics.store(true, Ordering::Release);
+        }
+        Ok(())
+    }
+
+    fn cached_topic(&self, topic: &str) -> Option<Arc<NativeTopic>> {
+        if !self.has_topics.load(Ordering::Acquire) {
+            return None;
+        }
+        self.topics.read().unwrap().get(topic).cloned()
+    }
+
+    /// Makes the producer flush outstanding messages for up to `timeout` when
+    /// it is dropped, or disables flushing on drop if `timeout` is `None`.
+    ///
+    /// By default, dropping the producer purges the messages that are still
+    /// queued or in flight, and reports them as failed to the delivery
+    /// callback. Flushing on drop instead gives them a chance to be
+    /// delivered, which is useful for short-lived programs that exit right
+    /// after sending their last message. The messages that could not be
+    /// delivered within the timeout are purged as before, and a warning is
+    /// logged.
+    pub fn set_flush_on_drop(&self, timeout: Option<Duration>) {
+        *self.flush_on_drop.lock().unwrap() = timeout;
+    }
+
+    /// Returns the current depth of the producer queue.
+    ///
+    /// See [`QueueDepth`] for details on what is counted.
+    pub fn queue_depth(&self) -> QueueDepth {
+        self.state.queue_depth()
+    }
+
+    /// Returns a snapshot of the counters of every topic that messages were
+    /// sent to.
+    ///
+    /// See [`TopicMetrics`] for details on what is counted. The counters are
+    /// maintained by the producer itself, so they are available without
+    /// enabling statistics or implementing a [`ProducerContext`].
+    pub fn metrics(&self) -> HashMap<String, TopicMetrics> {
+        self.state
+            .topic_metrics
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(topic, counters)| (topic.clone(), counters.snapshot()))
+            .collect()
+    }
+
+    fn check_partition_available(&self, topic: &str, partition: Option<i32>) -> KafkaResult<()> {
+        let max_age = match self.partition_availability_check {
+            Some(max_age) => max_age,
+            None => return Ok(()),
+        };
+        let check = |availability: &PartitionAvailability| {
+            let available = match &availability.available {
+                Some(available) => available,
+                None => return Ok(()),
+            };
+            let is_available = match partition {
+                Some(partition) => available.contains(&partition),
+                None => !available.is_empty(),
+            };
+            if is_available {
+                Ok(())
+            } else {
+                Err(KafkaError::PartitionUnavailable(
+                    topic.to_owned(),
+                    partition.unwrap_or(-1),
+                ))
+            }
+        };
+        if let Some(availability) = self.partition_availability.lock().unwrap().get(topic) {
+            if availability.fetched_at.elapsed() < max_age {
+                return check(availability);
+            }
+        }
+        // The metadata is fetched without holding the lock, since the fetch
+        // can block for up to `PARTITION_METADATA_TIMEOUT`. Failures are not
+        // cached, so the next send fetches the metadata again.
+        let availability = match self.fetch_partition_availability(topic) {
+            Some(availability) => availability,
+            None => return Ok(()),
+        };
+        let result = check(&availability);
+        self.partition_availability
+            .lock()
+            .unwrap()
+            .insert(topic.to_owned(), availability);
+        result
+    }
+
+    fn fetch_partition_availability(&self, topic: &str) -> Option<PartitionAvailability> {
+        let metadata = match self
+            .client
+            .fetch_metadata(Some(topic), PARTITION_METADATA_TIMEOUT)
+        {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                warn!("Failed to fetch metadata for topic {}: {}", topic, e);
+                return None;
+            }
+        };
+        let available = metadata
+            .topics()
+            .iter()
+            .find(|t| t.name() == topic && t.error().is_none() && !t.partitions().is_empty())
+            .map(|t| {
+                t.partitions()
+                    .iter()
+                    .filter(|p| p.leader() >= 0 && p.error().is_none())
+                    .map(|p| p.id())
+                    .collect()
+            });
+        Some(PartitionAvailability {
+            fetched_at: Instant::now(),
+            available,
+        })
+    }
+
+    /// Must be called after a message was enqueued.
+    fn record_send(&self, topic: &str, bytes: u64) {
+        #[cfg(feature = "watchdog")]
+        if let Some(watchdog) = &self.watchdog {
+            watchdog.record_send();
+        }
+        self.state.with_topic_counters(topic, |counters| {
+            counters.messages_sent.fetch_add(1, Ordering::Relaxed);
+            counters.bytes_sent.fetch_add(bytes, Ordering::Relaxed);
+        });
+        self.state.check_queue_watermarks(&**self.client.context());
+    }
+
+    fn elapsed_ms(&self) -> u64 {
+        self.created_at.elapsed().as_millis() as u64
+    }
+
+    /// Must be called after every librdkafka call that may serve delivery
+    /// callbacks.
+    fn record_poll(&self) {
+        #[cfg(feature = "watchdog")]
+        if let Some(watchdog) = &self.watchdog {
+            watchdog.record_poll();
+        }
+        if self.poll_overdue_threshold.is_some() {
+            self.last_poll_ms
+                .store(self.elapsed_ms(), Ordering::Relaxed);
+        }
+    }
+
+    fn check_poll_overdue(&self) {
+        let threshold = match self.poll_overdue_threshold {
+            Some(threshold) => threshold.as_millis() as u64,
+            None => return,
+        };
+        let now = self.elapsed_ms();
+        let since_last_poll = now.saturating_sub(self.last_poll_ms.load(Ordering::Relaxed));
+        let since_last_overdue =
+            now.saturating_sub(self.last_poll_overdue_ms.load(Ordering::Relaxed));
+        if since_last_poll > threshold && since_last_overdue > threshold {
+            self.last_poll_overdue_ms.store(now, Ordering::Relaxed);
+            self.client
+                .context()
+                .poll_overdue(Duration::from_millis(since_last_poll));
+        }
     }
 
     /// Returns a pointer to the native Kafka client.
@@ -320,31 +915,452 @@ where
     // unstable.
     pub fn send<'a, K, P>(
         &self,
-        mut record: BaseRecord<'a, K, P, C::DeliveryOpaque>,
+        record: BaseRecord<'a, K, P, C::DeliveryOpaque>,
+    ) -> Result<(), (KafkaError, BaseRecord<'a, K, P, C::DeliveryOpaque>)>
+    where
+        K: ToBytes + ?Sized,
+        P: ToBytes + ?Sized,
+    {
+        let payload = as_bytes(record.payload);
+        self.send_raw(record, payload, rdsys::RD_KAFKA_MSG_F_COPY)
+    }
+
+    /// Sends a message to Kafka, retrying for up to `queue_timeout` if the
+    /// producer queue is full.
+    ///
+    /// While the queue is full, the producer is polled in order to serve
+    /// delivery callbacks and free up space in the queue. Set `queue_timeout`
+    /// to `Timeout::Never` to retry forever or `Timeout::After(0)` to behave
+    /// like [`BaseProducer::send`]. If the timeout is reached and the queue is
+    /// still full, an [`RDKafkaErrorCode::QueueFull`] error is returned
+    /// alongside the original record.
+    ///
+    /// Keep in mind that `queue_timeout` only applies to enqueuing the
+    /// message. Once the message is queued, the underlying librdkafka client
+    /// has separate timeout parameters that apply, like
+    /// `delivery.timeout.ms`.
+    pub fn send_with_timeout<'a, K, P, T>(
+        &self,
+        record: BaseRecord<'a, K, P, C::DeliveryOpaque>,
+        queue_timeout: T,
+    ) -> Result<(), (KafkaError, BaseRecord<'a, K, P, C::DeliveryOpaque>)>
+    where
+        K: ToBytes + ?Sized,
+        P: ToBytes + ?Sized,
+        T: Into<Timeout>,
+    {
+        let start_time = Instant::now();
+        let queue_timeout = queue_timeout.into();
+        let mut result = self.send(record);
+        loop {
+            match result {
+                Err((e, r)) if e == KafkaError::MessageProduction(RDKafkaErrorCode::QueueFull) => {
+                    let poll_timeout = match queue_timeout {
+                        Timeout::Never => QUEUE_FULL_POLL_INTERVAL,
+                        Timeout::After(t) => match t.checked_sub(start_time.elapsed()) {
+                            Some(remaining) if remaining > Duration::ZERO => {
+                                cmp::min(remaining, QUEUE_FULL_POLL_INTERVAL)
+                            }
+                            _ => return Err((e, r)),
+                        },
+                    };
+                    self.poll(poll_timeout);
+                    // The record was intercepted by the first attempt.
+                    result = self.send_intercepted(r);
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Sends a message to Kafka, blocking while the producer queue is full.
+    ///
+    /// Instead of returning an [`RDKafkaErrorCode::QueueFull`] error, librdkafka
+    /// blocks the calling thread until there is room in the queue, which
+    /// requires the producer to be polled from another thread in the meantime.
+    /// Calling this method from the only thread that polls the producer, or
+    /// from a delivery callback, will therefore deadlock once the queue is
+    /// full. When in doubt, use [`BaseProducer::send_with_timeout`] instead.
+    ///
+    /// In every other respect, this method behaves like
+    /// [`BaseProducer::send`].
+    pub fn send_blocking<'a, K, P>(
+        &self,
+        record: BaseRecord<'a, K, P, C::DeliveryOpaque>,
     ) -> Result<(), (KafkaError, BaseRecord<'a, K, P, C::DeliveryOpaque>)>
     where
         K: ToBytes + ?Sized,
         P: ToBytes + ?Sized,
     {
-        fn as_bytes(opt: Option<&(impl ?Sized + ToBytes)>) -> (*mut c_void, usize) {
-            match opt.map(ToBytes::to_bytes) {
-                None => (ptr::null_mut(), 0),
-                Some(p) => (p.as_ptr() as *mut c_void, p.len()),
+        let payload = as_bytes(record.payload);
+        self.send_raw(
+            record,
+            payload,
+            rdsys::RD_KAFKA_MSG_F_COPY | rdsys::RD_KAFKA_MSG_F_BLOCK,
+        )
+    }
+
+    /// Sends a message to Kafka, returning a handle to its delivery result.
+    ///
+    /// This requires a context that reports delivery results to
+    /// [`DeliveryReporter`]s, such as the [`DeliveryHandleContext`]. The
+    /// returned [`DeliveryHandle`] can be polled or waited on for the result,
+    /// which is only reported once the producer is polled. In every other
+    /// respect, this method behaves like [`BaseProducer::send`].
+    ///
+    /// [`DeliveryHandleContext`]: crate::producer::delivery_handle::DeliveryHandleContext
+    pub fn send_with_handle<'a, K, P>(
+        &self,
+        record: BaseRecord<'a, K, P>,
+    ) -> Result<DeliveryHandle, (KafkaError, BaseRecord<'a, K, P>)>
+    where
+        K: ToBytes + ?Sized,
+        P: ToBytes + ?Sized,
+        C: ProducerContext<DeliveryOpaque = DeliveryReporter>,
+    {
+        let (reporter, handle) = delivery_handle();
+        let (record, ()) = record.replace_opaque(reporter);
+        match self.send(record) {
+            Ok(()) => Ok(handle),
+            Err((e, record)) => Err((e, record.replace_opaque(()).0)),
+        }
+    }
+
+    /// Sends a message to Kafka without copying its payload.
+    ///
+    /// The payload of the message is provided by `payload`, which owns it.
+    /// librdkafka references the payload bytes directly until the message is
+    /// delivered or fails. The producer takes ownership of `payload` and
+    /// drops it once [`ProducerContext::delivery`] returns for the message,
+    /// which is useful when the payload is a chunk of a memory-mapped file:
+    /// the delivery callback can still read the payload from the delivery
+    /// result, and the chunk is released right afterwards. To hand buffers
+    /// over to the producer, pass a `Vec<u8>` or a `Box<[u8]>`.
+    ///
+    /// The payload is never passed to librdkafka with `RD_KAFKA_MSG_F_FREE`,
+    /// since librdkafka would release it with its own allocator rather than
+    /// the Rust one.
+    ///
+    /// The `payload` field of the record is ignored. If the message cannot
+    /// be enqueued, `payload` is returned alongside the error and the record.
+    /// In every other respect, this method behaves like
+    /// [`BaseProducer::send`].
+    pub fn send_zero_copy<'a, K, O>(
+        &self,
+        mut record: BaseRecord<'a, K, (), C::DeliveryOpaque>,
+        payload: O,
+    ) -> Result<(), (KafkaError, BaseRecord<'a, K, (), C::DeliveryOpaque>, O)>
+    where
+        K: ToBytes + ?Sized,
+        O: OwnedPayload + 'static,
+    {
+        let bytes = payload.payload();
+        let raw_payload = (bytes.as_ptr() as *mut c_void, bytes.len());
+        enter_span!(
+            "rdkafka.send",
+            topic = record.topic,
+            partition = record.partition.unwrap_or(-1),
+        );
+        if let Err(e) = self.prepare_send(&mut record, raw_payload) {
+            return Err((e, record, payload));
+        }
+        // The owner is registered before the message is enqueued, since the
+        // delivery callback may run on another thread right after. The lock
+        // is not held while the message is enqueued, as enqueuing it may call
+        // back into the context, which may send other messages.
+        let key = raw_payload.0 as usize;
+        self.state.zero_copy_count.fetch_add(1, Ordering::SeqCst);
+        self.state
+            .zero_copy_payloads
+            .lock()
+            .unwrap()
+            .entry(key)
+            .or_default()
+            .push(Box::new(payload));
+        self.produce(record, raw_payload, 0).map_err(|(e, record)| {
+            let payload = self
+                .state
+                .take_zero_copy_payload_of::<O>(key)
+                .expect("zero-copy payload owner missing after failed send");
+            (e, record, payload)
+        })
+    }
+
+    /// Sends a batch of messages to a topic with a single call to librdkafka.
+    ///
+    /// This avoids the overhead of calling [`BaseProducer::send`] for every
+    /// message, which is measurable in high-throughput pipelines. If
+    /// `partition` is specified, all the messages are sent to that partition;
+    /// otherwise, the partition of each record is used, or the partitioner
+    /// picks one if the record does not specify it.
+    ///
+    /// A result is returned for every record, in order. Records that fail to
+    /// be enqueued are returned alongside their error, like with `send`. The
+    /// librdkafka batch API does not support timestamps, headers or multiple
+    /// topics, so records that set a timestamp, that have headers, including
+    /// headers added by interceptors, or whose topic is not `topic`, are
+    /// enqueued on their own, in order with the rest of the batch.
+    pub fn send_batch<'a, K, P>(
+        &self,
+        topic: &str,
+        partition: Option<i32>,
+        records: Vec<BaseRecord<'a, K, P, C::DeliveryOpaque>>,
+    ) -> Vec<Result<(), (KafkaError, BaseRecord<'a, K, P, C::DeliveryOpaque>)>>
+    where
+        K: ToBytes + ?Sized,
+        P: ToBytes + ?Sized,
+    {
+        enter_span!(
+            "rdkafka.send_batch",
+            topic = topic,
+            messages = records.len()
+        );
+        self.check_poll_overdue();
+        let native_topic = match self.cached_topic(topic) {
+            Some(native_topic) => Ok(native_topic),
+            None => self.client.native_topic(topic).map(Arc::new),
+        };
+        let native_topic = match native_topic {
+            Ok(native_topic) => native_topic,
+            Err(e) => {
+                let mut results = Vec::with_capacity(records.len());
+                for record in records {
+                    results.push(Err((e.clone(), record)));
+                }
+                return results;
             }
+        };
+
+        let mut results = Vec::with_capacity(records.len());
+        let mut batch = Vec::new();
+        let mut messages = Vec::new();
+        for (i, mut record) in records.into_iter().enumerate() {
+            let message_partition = partition.or(record.partition);
+            if let Err(e) = self.check_partition_available(record.topic, message_partition) {
+                results.push(Some(Err((e, record))));
+                continue;
+            }
+            let (payload_ptr, payload_len) = as_bytes(record.payload);
+            self.intercept(&mut record, (payload_ptr, payload_len));
+            if record.headers.is_some() || record.timestamp.is_some() || record.topic != topic {
+                // The messages batched so far are enqueued first, so that the
+                // order of the records is preserved.
+                self.produce_batch(
+                    &native_topic,
+                    partition,
+                    &mut messages,
+                    &mut batch,
+                    &mut results,
+                );
+                let record_partition = mem::replace(&mut record.partition, message_partition);
+                let result = self
+                    .produce(
+                        record,
+                        (payload_ptr, payload_len),
+                        rdsys::RD_KAFKA_MSG_F_COPY,
+                    )
+                    .map_err(|(e, mut record)| {
+                        record.partition = record_partition;
+                        (e, record)
+                    });
+                results.push(Some(result));
+                continue;
+            }
+            let (key_ptr, key_len) = as_bytes(record.key);
+            let (record, delivery_opaque) = record.replace_opaque(());
+            messages.push(RDKafkaMessage {
+                err: RDKafkaRespErr::RD_KAFKA_RESP_ERR_NO_ERROR,
+                rkt: ptr::null_mut(),
+                partition: message_partition.unwrap_or(-1),
+                payload: payload_ptr,
+                len: payload_len,
+                key: key_ptr,
+                key_len,
+                offset: 0,
+                _private: delivery_opaque.into_ptr(),
+            });
+            batch.push((i, record));
+            results.push(None);
+        }
+        self.produce_batch(
+            &native_topic,
+            partition,
+            &mut messages,
+            &mut batch,
+            &mut results,
+        );
+        results.into_iter().map(Option::unwrap).collect()
+    }
+
+    /// Enqueues the batched messages with a single call to librdkafka, and
+    /// records their results.
+    fn produce_batch<'a, K, P>(
+        &self,
+        native_topic: &NativeTopic,
+        partition: Option<i32>,
+        messages: &mut Vec<RDKafkaMessage>,
+        batch: &mut Vec<(usize, BaseRecord<'a, K, P>)>,
+        results: &mut [Option<Result<(), (KafkaError, BaseRecord<'a, K, P, C::DeliveryOpaque>)>>],
+    ) where
+        K: ToBytes + ?Sized,
+        P: ToBytes + ?Sized,
+    {
+        if messages.is_empty() {
+            return;
         }
-        let (payload_ptr, payload_len) = as_bytes(record.payload);
+        let msg_flags = match partition {
+            Some(_) => rdsys::RD_KAFKA_MSG_F_COPY,
+            None => rdsys::RD_KAFKA_MSG_F_COPY | rdsys::RD_KAFKA_MSG_F_PARTITION,
+        };
+        let message_bytes = |message: &RDKafkaMessage| (message.len + message.key_len) as u64;
+        let bytes = messages.iter().map(message_bytes).sum();
+        self.state.add_queued(messages.len() as u64, bytes);
+        unsafe {
+            rdsys::rd_kafka_produce_batch(
+                native_topic.ptr(),
+                partition.unwrap_or(-1),
+                msg_flags,
+                messages.as_mut_ptr(),
+                messages.len() as i32,
+            )
+        };
+
+        for (message, (i, record)) in messages.drain(..).zip(batch.drain(..)) {
+            results[i] = Some(if message.err.is_error() {
+                self.state.remove_queued(1, message_bytes(&message));
+                let delivery_opaque = unsafe { C::DeliveryOpaque::from_ptr(message._private) };
+                let (record, ()) = record.replace_opaque(delivery_opaque);
+                Err((KafkaError::MessageProduction(message.err.into()), record))
+            } else {
+                self.record_send(record.topic, message_bytes(&message));
+                Ok(())
+            });
+        }
+    }
+
+    fn send_raw<'a, K, P>(
+        &self,
+        mut record: BaseRecord<'a, K, P, C::DeliveryOpaque>,
+        payload: (*mut c_void, usize),
+        msg_flags: i32,
+    ) -> Result<(), (KafkaError, BaseRecord<'a, K, P, C::DeliveryOpaque>)>
+    where
+        K: ToBytes + ?Sized,
+        P: ToBytes + ?Sized,
+    {
+        enter_span!(
+            "rdkafka.send",
+            topic = record.topic,
+            partition = record.partition.unwrap_or(-1),
+        );
+        if let Err(e) = self.prepare_send(&mut record, payload) {
+            return Err((e, record));
+        }
+        self.produce(record, payload, msg_flags)
+    }
+
+    /// Like [`BaseProducer::send`], but without calling the interceptors,
+    /// for records that were intercepted by an earlier attempt.
+    pub(crate) fn send_intercepted<'a, K, P>(
+        &self,
+        record: BaseRecord<'a, K, P, C::DeliveryOpaque>,
+    ) -> Result<(), (KafkaError, BaseRecord<'a, K, P, C::DeliveryOpaque>)>
+    where
+        K: ToBytes + ?Sized,
+        P: ToBytes + ?Sized,
+    {
+        enter_span!(
+            "rdkafka.send",
+            topic = record.topic,
+            partition = record.partition.unwrap_or(-1),
+        );
+        self.check_poll_overdue();
+        if let Err(e) = self.check_partition_available(record.topic, record.partition) {
+            return Err((e, record));
+        }
+        let payload = as_bytes(record.payload);
+        self.produce(record, payload, rdsys::RD_KAFKA_MSG_F_COPY)
+    }
+
+    /// Performs the checks that precede enqueuing a message, and calls the
+    /// interceptors if the message passes them.
+    fn prepare_send<K, P>(
+        &self,
+        record: &mut BaseRecord<'_, K, P, C::DeliveryOpaque>,
+        payload: (*mut c_void, usize),
+    ) -> KafkaResult<()>
+    where
+        K: ToBytes + ?Sized,
+        P: ToBytes + ?Sized,
+    {
+        self.check_poll_overdue();
+        self.check_partition_available(record.topic, record.partition)?;
+        self.intercept(record, payload);
+        Ok(())
+    }
+
+    /// Passes the record to the interceptors, if any.
+    fn intercept<K, P, D>(
+        &self,
+        record: &mut BaseRecord<'_, K, P, D>,
+        (payload_ptr, payload_len): (*mut c_void, usize),
+    ) where
+        K: ToBytes + ?Sized,
+        P: ToBytes + ?Sized,
+        D: IntoOpaque,
+    {
+        let interceptors = self.context().interceptors();
+        if !interceptors.is_empty() {
+            let mut intercepted = InterceptedRecord {
+                topic: record.topic,
+                partition: record.partition,
+                key: record.key.map(ToBytes::to_bytes),
+                payload: unsafe { bytes_from_raw(payload_ptr, payload_len) },
+                timestamp: record.timestamp,
+                headers: &mut record.headers,
+            };
+            intercept_send(interceptors, &mut intercepted);
+        }
+    }
+
+    /// Enqueues a message.
+    fn produce<'a, K, P>(
+        &self,
+        mut record: BaseRecord<'a, K, P, C::DeliveryOpaque>,
+        (payload_ptr, payload_len): (*mut c_void, usize),
+        msg_flags: i32,
+    ) -> Result<(), (KafkaError, BaseRecord<'a, K, P, C::DeliveryOpaque>)>
+    where
+        K: ToBytes + ?Sized,
+        P: ToBytes + ?Sized,
+    {
         let (key_ptr, key_len) = as_bytes(record.key);
-        let topic_cstring = CString::new(record.topic.to_owned()).unwrap();
+        // The topic name is only copied when the topic is not cached. The
+        // cached handle and the C string must live until the call returns.
+        let native_topic = self.cached_topic(record.topic);
+        let topic_cstring;
+        let (topic_vtype, topic_ptr) = match &native_topic {
+            Some(native_topic) => (RD_KAFKA_VTYPE_RKT, native_topic.ptr() as *const c_void),
+            None => {
+                topic_cstring = CString::new(record.topic).unwrap();
+                (
+                    RD_KAFKA_VTYPE_TOPIC,
+                    topic_cstring.as_ptr() as *const c_void,
+                )
+            }
+        };
         let opaque_ptr = record.delivery_opaque.into_ptr();
+        let bytes = (payload_len + key_len) as u64;
+        self.state.add_queued(1, bytes);
         let produce_error = unsafe {
             rdsys::rd_kafka_producev(
                 self.native_ptr(),
-                RD_KAFKA_VTYPE_TOPIC,
-                topic_cstring.as_ptr(),
+                topic_vtype,
+                topic_ptr,
                 RD_KAFKA_VTYPE_PARTITION,
                 record.partition.unwrap_or(-1),
                 RD_KAFKA_VTYPE_MSGFLAGS,
-                rdsys::RD_KAFKA_MSG_F_COPY,
+                msg_flags,
                 RD_KAFKA_VTYPE_VALUE,
                 payload_ptr,
                 payload_len,
@@ -364,11 +1380,13 @@ where
             )
         };
         if produce_error.is_error() {
+            self.state.remove_queued(1, bytes);
             record.delivery_opaque = unsafe { C::DeliveryOpaque::from_ptr(opaque_ptr) };
             Err((KafkaError::MessageProduction(produce_error.into()), record))
         } else {
             // The kafka producer now owns the headers
             mem::forget(record.headers);
+            self.record_send(record.topic, bytes);
             Ok(())
         }
     }
@@ -384,6 +1402,7 @@ where
 
     fn flush<T: Into<Timeout>>(&self, timeout: T) -> KafkaResult<()> {
         let ret = unsafe { rdsys::rd_kafka_flush(self.native_ptr(), timeout.into().as_millis()) };
+        self.record_poll();
         if ret.is_error() {
             Err(KafkaError::Flush(ret.into()))
         } else {
@@ -458,6 +1477,7 @@ where
                 timeout.into().as_millis(),
             ))
         };
+        self.record_poll();
         if ret.is_error() {
             Err(KafkaError::Transaction(ret))
         } else {
@@ -472,6 +1492,7 @@ where
                 timeout.into().as_millis(),
             ))
         };
+        self.record_poll();
         if ret.is_error() {
             Err(KafkaError::Transaction(ret))
         } else {
@@ -485,6 +1506,16 @@ where
     C: ProducerContext,
 {
     fn drop(&mut self) {
+        let flush_on_drop = *self.flush_on_drop.lock().unwrap();
+        if let Some(timeout) = flush_on_drop {
+            if let Err(e) = self.flush(timeout) {
+                warn!(
+                    "Failed to flush producer on drop, purging {} messages: {}",
+                    self.in_flight_count(),
+                    e
+                );
+            }
+        }
         self.purge(PurgeConfig::default().queue().inflight());
         // Still have to poll after purging to get the results that have been made ready by the purge
         self.poll(Timeout::After(Duration::ZERO));
@@ -525,13 +1556,16 @@ where
         config: &ClientConfig,
         context: C,
     ) -> KafkaResult<ThreadedProducer<C>> {
-        let producer = Arc::new(BaseProducer::from_config_and_context(config, context)?);
+        let producer = Arc::new(BaseProducer::create(config, context, true)?);
         let should_stop = Arc::new(AtomicBool::new(false));
+        let polling_thread = producer
+            .context()
+            .polling_thread()
+            .unwrap_or_else(|| CallbackThread::new("producer polling thread"));
         let thread = {
             let producer = Arc::clone(&producer);
             let should_stop = should_stop.clone();
-            thread::Builder::new()
-                .name("producer polling thread".to_string())
+            polling_thread
                 .spawn(move || {
                     trace!("Polling thread loop started");
                     loop {
@@ -548,7 +1582,9 @@ where
                     }
                     trace!("Polling thread loop terminated");
                 })
-                .expect("Failed to start polling thread")
+                .map_err(|e| {
+                    KafkaError::ClientCreation(format!("failed to start polling thread: {}", e))
+                })?
         };
         Ok(ThreadedProducer {
             producer,
@@ -578,6 +1614,84 @@ where
         self.producer.send(record)
     }
 
+    pub(crate) fn send_intercepted<'a, K, P>(
+        &self,
+        record: BaseRecord<'a, K, P, C::DeliveryOpaque>,
+    ) -> Result<(), (KafkaError, BaseRecord<'a, K, P, C::DeliveryOpaque>)>
+    where
+        K: ToBytes + ?Sized,
+        P: ToBytes + ?Sized,
+    {
+        self.producer.send_intercepted(record)
+    }
+
+    /// Sends a message to Kafka, retrying for up to `queue_timeout` if the
+    /// producer queue is full.
+    ///
+    /// See the documentation for [`BaseProducer::send_with_timeout`] for
+    /// details.
+    pub fn send_with_timeout<'a, K, P, T>(
+        &self,
+        record: BaseRecord<'a, K, P, C::DeliveryOpaque>,
+        queue_timeout: T,
+    ) -> Result<(), (KafkaError, BaseRecord<'a, K, P, C::DeliveryOpaque>)>
+    where
+        K: ToBytes + ?Sized,
+        P: ToBytes + ?Sized,
+        T: Into<Timeout>,
+    {
+        self.producer.send_with_timeout(record, queue_timeout)
+    }
+
+    /// Sends a message to Kafka, blocking while the producer queue is full.
+    ///
+    /// The internal polling thread keeps serving delivery callbacks while the
+    /// caller is blocked; calling this method from a delivery callback will
+    /// deadlock once the queue is full. See the documentation for
+    /// [`BaseProducer::send_blocking`] for details.
+    pub fn send_blocking<'a, K, P>(
+        &self,
+        record: BaseRecord<'a, K, P, C::DeliveryOpaque>,
+    ) -> Result<(), (KafkaError, BaseRecord<'a, K, P, C::DeliveryOpaque>)>
+    where
+        K: ToBytes + ?Sized,
+        P: ToBytes + ?Sized,
+    {
+        self.producer.send_blocking(record)
+    }
+
+    /// Sends a message to Kafka, returning a handle to its delivery result.
+    ///
+    /// Since the internal polling thread serves delivery callbacks, the
+    /// handle can be waited on from any thread but the polling thread. See
+    /// the documentation for [`BaseProducer::send_with_handle`] for details.
+    pub fn send_with_handle<'a, K, P>(
+        &self,
+        record: BaseRecord<'a, K, P>,
+    ) -> Result<DeliveryHandle, (KafkaError, BaseRecord<'a, K, P>)>
+    where
+        K: ToBytes + ?Sized,
+        P: ToBytes + ?Sized,
+        C: ProducerContext<DeliveryOpaque = DeliveryReporter>,
+    {
+        self.producer.send_with_handle(record)
+    }
+
+    /// Sends a message to Kafka without copying its payload.
+    ///
+    /// See the documentation for [`BaseProducer::send_zero_copy`] for details.
+    pub fn send_zero_copy<'a, K, O>(
+        &self,
+        record: BaseRecord<'a, K, (), C::DeliveryOpaque>,
+        payload: O,
+    ) -> Result<(), (KafkaError, BaseRecord<'a, K, (), C::DeliveryOpaque>, O)>
+    where
+        K: ToBytes + ?Sized,
+        O: OwnedPayload + 'static,
+    {
+        self.producer.send_zero_copy(record, payload)
+    }
+
     /// Polls the internal producer.
     ///
     /// This is not normally required since the `ThreadedProducer` has a thread
@@ -585,6 +1699,60 @@ where
     pub fn poll<T: Into<Timeout>>(&self, timeout: T) {
         self.producer.poll(timeout);
     }
+
+    /// Sends a batch of messages to a topic with a single call to librdkafka.
+    ///
+    /// See the documentation for [`BaseProducer::send_batch`] for details.
+    pub fn send_batch<'a, K, P>(
+        &self,
+        topic: &str,
+        partition: Option<i32>,
+        records: Vec<BaseRecord<'a, K, P, C::DeliveryOpaque>>,
+    ) -> Vec<Result<(), (KafkaError, BaseRecord<'a, K, P, C::DeliveryOpaque>)>>
+    where
+        K: ToBytes + ?Sized,
+        P: ToBytes + ?Sized,
+    {
+        self.producer.send_batch(topic, partition, records)
+    }
+
+    /// Installs a configuration specific to `topic`.
+    ///
+    /// See the documentation for [`BaseProducer::configure_topic`] for
+    /// details.
+    pub fn configure_topic(&self, topic: &str, config: &TopicConfig) -> KafkaResult<()> {
+        self.producer.configure_topic(topic, config)
+    }
+
+    /// Caches the native handle of `topic`.
+    ///
+    /// See the documentation for [`BaseProducer::register_topic`] for
+    /// details.
+    pub fn register_topic(&self, topic: &str) -> KafkaResult<()> {
+        self.producer.register_topic(topic)
+    }
+
+    /// Makes the producer flush outstanding messages when it is dropped.
+    ///
+    /// See the documentation for [`BaseProducer::set_flush_on_drop`] for
+    /// details. The messages are flushed once the polling thread is stopped.
+    pub fn set_flush_on_drop(&self, timeout: Option<Duration>) {
+        self.producer.set_flush_on_drop(timeout)
+    }
+
+    /// Returns the current depth of the producer queue.
+    ///
+    /// See the documentation for [`BaseProducer::queue_depth`] for details.
+    pub fn queue_depth(&self) -> QueueDepth {
+        self.producer.queue_depth()
+    }
+
+    /// Returns a snapshot of the counters of every topic.
+    ///
+    /// See the documentation for [`BaseProducer::metrics`] for details.
+    pub fn metrics(&self) -> HashMap<String, TopicMetrics> {
+        self.producer.metrics()
+    }
 }
 
 impl<C> Producer<C> for ThreadedProducer<C>
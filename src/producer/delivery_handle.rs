@@ -0,0 +1,159 @@
+//! Per-message delivery handles for the low-level producers.
+//!
+//! A [`DeliveryHandle`] gives access to the delivery result of a single
+//! message sent with [`BaseProducer::send_with_handle`] or
+//! [`ThreadedProducer::send_with_handle`], without the need for an async
+//! runtime. The result is reported by the [`DeliveryReporter`] that the
+//! producer passes to the delivery callback as the delivery opaque of the
+//! message.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//!
+//! use rdkafka::config::ClientConfig;
+//! use rdkafka::producer::delivery_handle::DeliveryHandleContext;
+//! use rdkafka::producer::{BaseRecord, ThreadedProducer};
+//!
+//! let producer: ThreadedProducer<DeliveryHandleContext> = ClientConfig::new()
+//!     .set("bootstrap.servers", "localhost:9092")
+//!     .create_with_context(DeliveryHandleContext)
+//!     .unwrap();
+//! let handle = producer
+//!     .send_with_handle(BaseRecord::<(), _>::to("events").payload("payload"))
+//!     .map_err(|(e, _)| e)
+//!     .unwrap();
+//! match handle.wait(Duration::from_secs(10)) {
+//!     Some(Ok((partition, offset))) => println!("delivered to {}:{}", partition, offset),
+//!     Some(Err((e, _))) => println!("delivery failed: {}", e),
+//!     None => println!("delivery not reported yet"),
+//! }
+//! ```
+//!
+//! [`BaseProducer::send_with_handle`]: crate::producer::BaseProducer::send_with_handle
+//! [`ThreadedProducer::send_with_handle`]: crate::producer::ThreadedProducer::send_with_handle
+
+use std::os::raw::c_void;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender};
+
+use crate::client::ClientContext;
+use crate::message::Message;
+use crate::producer::future_producer::OwnedDeliveryResult;
+use crate::producer::{DeliveryResult, ProducerContext};
+use crate::util::{IntoOpaque, Timeout};
+
+/// Reports the delivery result of a message to its [`DeliveryHandle`].
+///
+/// Contexts whose [`DeliveryOpaque`](ProducerContext::DeliveryOpaque) is a
+/// `DeliveryReporter` must call [`DeliveryReporter::report`] from their
+/// delivery callback. [`DeliveryHandleContext`] does nothing else.
+#[derive(Debug)]
+pub struct DeliveryReporter {
+    tx: Box<SyncSender<OwnedDeliveryResult>>,
+}
+
+impl DeliveryReporter {
+    /// Passes the delivery result to the handle of the message.
+    pub fn report(self, delivery_result: &DeliveryResult<'_>) {
+        let owned_delivery_result = match *delivery_result {
+            Ok(ref message) => Ok((message.partition(), message.offset())),
+            Err((ref error, ref message)) => Err((error.clone(), message.detach())),
+        };
+        // The handle may have been dropped, in which case the result is not
+        // needed anymore.
+        let _ = self.tx.send(owned_delivery_result);
+    }
+}
+
+impl IntoOpaque for DeliveryReporter {
+    fn into_ptr(self) -> *mut c_void {
+        self.tx.into_ptr()
+    }
+
+    unsafe fn from_ptr(ptr: *mut c_void) -> Self {
+        DeliveryReporter {
+            tx: IntoOpaque::from_ptr(ptr),
+        }
+    }
+}
+
+/// A handle to the delivery result of a single message.
+///
+/// The result is only reported once the producer serves the delivery
+/// callback of the message, so a [`BaseProducer`](crate::producer::BaseProducer)
+/// must keep being polled while waiting for it.
+#[derive(Debug)]
+pub struct DeliveryHandle {
+    rx: Receiver<OwnedDeliveryResult>,
+}
+
+impl DeliveryHandle {
+    /// Returns the delivery result if it was reported already, without
+    /// blocking.
+    ///
+    /// The result is only returned once; later calls return `None`.
+    pub fn try_result(&self) -> Option<OwnedDeliveryResult> {
+        self.rx.try_recv().ok()
+    }
+
+    /// Blocks until the delivery result is reported, for up to `timeout`.
+    ///
+    /// Returns `None` if the timeout expires, or if the message was dropped
+    /// without its delivery being reported, e.g. because the delivery
+    /// callback panicked. The result is only returned once; later calls
+    /// return `None`.
+    pub fn wait<T: Into<Timeout>>(&self, timeout: T) -> Option<OwnedDeliveryResult> {
+        match timeout.into() {
+            Timeout::Never => self.rx.recv().ok(),
+            Timeout::After(timeout) => match self.rx.recv_timeout(timeout) {
+                Ok(result) => Some(result),
+                Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => None,
+            },
+        }
+    }
+}
+
+/// Creates a connected reporter and handle.
+pub fn delivery_handle() -> (DeliveryReporter, DeliveryHandle) {
+    let (tx, rx) = mpsc::sync_channel(1);
+    (DeliveryReporter { tx: Box::new(tx) }, DeliveryHandle { rx })
+}
+
+/// A producer context that reports delivery results to the handles returned
+/// by `send_with_handle`.
+#[derive(Clone, Debug, Default)]
+pub struct DeliveryHandleContext;
+
+impl ClientContext for DeliveryHandleContext {}
+
+impl ProducerContext for DeliveryHandleContext {
+    type DeliveryOpaque = DeliveryReporter;
+
+    fn delivery(&self, delivery_result: &DeliveryResult<'_>, reporter: DeliveryReporter) {
+        reporter.report(delivery_result);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_delivery_handle() {
+        let (reporter, handle) = delivery_handle();
+        assert!(handle.try_result().is_none());
+        assert!(handle.wait(Duration::from_millis(1)).is_none());
+        let reporter = unsafe { DeliveryReporter::from_ptr(reporter.into_ptr()) };
+        let _ = reporter.tx.send(Ok((1, 42)));
+        assert_eq!(
+            handle.wait(Timeout::Never).map(Result::ok),
+            Some(Some((1, 42)))
+        );
+        assert!(handle.try_result().is_none());
+
+        let (reporter, handle) = delivery_handle();
+        drop(reporter);
+        assert!(handle.wait(Timeout::Never).is_none());
+    }
+}
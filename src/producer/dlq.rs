@@ -0,0 +1,268 @@
+//! Forwarding of failed messages to a dead letter queue.
+//!
+//! See the [`DlqProducer`] for details.
+
+use std::fmt::Display;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::client::{ClientContext, DefaultClientContext};
+use crate::error::{KafkaError, KafkaResult, RDKafkaErrorCode};
+use crate::message::{Header, Message, OwnedHeaders};
+use crate::producer::future_producer::OwnedDeliveryResult;
+use crate::producer::prepared_record::copy_headers;
+use crate::producer::{FutureProducer, FutureRecord};
+use crate::util::{AsyncRuntime, DefaultRuntime, Timeout};
+
+/// The header that holds the topic of the original message.
+pub const DLQ_TOPIC_HEADER: &str = "dlq.original.topic";
+
+/// The header that holds the partition of the original message.
+pub const DLQ_PARTITION_HEADER: &str = "dlq.original.partition";
+
+/// The header that holds the offset of the original message.
+pub const DLQ_OFFSET_HEADER: &str = "dlq.original.offset";
+
+/// The header that holds the error that caused the message to be forwarded.
+pub const DLQ_ERROR_HEADER: &str = "dlq.error";
+
+/// The header that holds the number of times processing the message was
+/// retried before it was forwarded.
+pub const DLQ_RETRY_COUNT_HEADER: &str = "dlq.retry.count";
+
+/// How long [`DlqProducer::forward_blocking`] waits for before retrying when
+/// the producer queue is full.
+const QUEUE_FULL_RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A producer that forwards messages that could not be processed to a dead
+/// letter queue.
+///
+/// The forwarded messages keep the key, payload, timestamp and headers of the
+/// original message, and gain the `dlq.*` headers, which record where the
+/// message comes from and why it was forwarded. The partition, offset and
+/// retry count are encoded as decimal strings.
+///
+/// ```no_run
+/// use rdkafka::config::ClientConfig;
+/// use rdkafka::consumer::{Consumer, StreamConsumer};
+/// use rdkafka::message::Message;
+/// use rdkafka::producer::DlqProducer;
+///
+/// # async fn run() {
+/// let consumer: StreamConsumer = ClientConfig::new()
+///     .set("bootstrap.servers", "localhost:9092")
+///     .set("group.id", "orders")
+///     .create()
+///     .unwrap();
+/// consumer.subscribe(&["orders"]).unwrap();
+/// let dlq = DlqProducer::new(
+///     ClientConfig::new()
+///         .set("bootstrap.servers", "localhost:9092")
+///         .create()
+///         .unwrap(),
+///     "orders.dlq",
+/// );
+/// loop {
+///     let message = consumer.recv().await.unwrap();
+///     if let Err(e) = std::str::from_utf8(message.payload().unwrap_or_default()) {
+///         dlq.forward(&message, &e, 0).await.unwrap();
+///     }
+/// }
+/// # }
+/// ```
+pub struct DlqProducer<C = DefaultClientContext, R = DefaultRuntime>
+where
+    C: ClientContext + 'static,
+{
+    producer: FutureProducer<C, R>,
+    topic: String,
+    queue_timeout: Timeout,
+}
+
+impl<C, R> DlqProducer<C, R>
+where
+    C: ClientContext + 'static,
+    R: AsyncRuntime,
+{
+    /// Creates a producer that forwards messages to the specified topic.
+    pub fn new<S: Into<String>>(producer: FutureProducer<C, R>, topic: S) -> DlqProducer<C, R> {
+        DlqProducer {
+            producer,
+            topic: topic.into(),
+            queue_timeout: Timeout::Never,
+        }
+    }
+
+    /// Sets how long to retry for if the producer queue is full, as in
+    /// [`FutureProducer::send`]. Defaults to `Timeout::Never`, so that failed
+    /// messages are never dropped because the queue is full.
+    pub fn queue_timeout<T: Into<Timeout>>(mut self, queue_timeout: T) -> DlqProducer<C, R> {
+        self.queue_timeout = queue_timeout.into();
+        self
+    }
+
+    /// Returns the topic of the dead letter queue.
+    pub fn topic(&self) -> &str {
+        &self.topic
+    }
+
+    /// Returns the underlying producer.
+    pub fn producer(&self) -> &FutureProducer<C, R> {
+        &self.producer
+    }
+
+    /// Forwards the message to the dead letter queue, and waits for it to be
+    /// delivered.
+    ///
+    /// The `error` is the reason why the message could not be processed, and
+    /// `retry_count` the number of times processing it was retried.
+    pub async fn forward<M, E>(
+        &self,
+        message: &M,
+        error: &E,
+        retry_count: u32,
+    ) -> OwnedDeliveryResult
+    where
+        M: Message,
+        E: Display + ?Sized,
+    {
+        let headers = dlq_headers(message, &error.to_string(), retry_count);
+        let mut record = FutureRecord::<[u8], [u8]>::to(&self.topic).headers(headers);
+        record.key = message.key();
+        record.payload = message.payload();
+        record.timestamp = message.timestamp().to_millis();
+        self.producer.send(record, self.queue_timeout).await
+    }
+
+    /// Like [`DlqProducer::forward`], but blocks the calling thread until the
+    /// message is delivered, and returns its partition and offset.
+    ///
+    /// The `timeout` bounds both the wait for room in the producer queue, in
+    /// place of the queue timeout, and the wait for the delivery. If it
+    /// expires, an `OperationTimedOut` error is returned, although the
+    /// message may still be delivered later.
+    pub fn forward_blocking<M, E, T>(
+        &self,
+        message: &M,
+        error: &E,
+        retry_count: u32,
+        timeout: T,
+    ) -> KafkaResult<(i32, i64)>
+    where
+        M: Message,
+        E: Display + ?Sized,
+        T: Into<Timeout>,
+    {
+        let timeout = timeout.into();
+        let start = Instant::now();
+        let remaining = || match timeout {
+            Timeout::After(t) => t.checked_sub(start.elapsed()).map(Timeout::After),
+            Timeout::Never => Some(Timeout::Never),
+        };
+        let timed_out = || KafkaError::MessageProduction(RDKafkaErrorCode::OperationTimedOut);
+        let headers = dlq_headers(message, &error.to_string(), retry_count);
+        let mut record = FutureRecord::<[u8], [u8]>::to(&self.topic).headers(headers);
+        record.key = message.key();
+        record.payload = message.payload();
+        record.timestamp = message.timestamp().to_millis();
+        let delivery = loop {
+            match self.producer.send_result(record) {
+                Ok(delivery) => break delivery,
+                Err((KafkaError::MessageProduction(RDKafkaErrorCode::QueueFull), r)) => {
+                    // The queue is emptied by the polling thread of the
+                    // producer.
+                    let interval = match remaining().ok_or_else(timed_out)? {
+                        Timeout::After(t) => t.min(QUEUE_FULL_RETRY_INTERVAL),
+                        Timeout::Never => QUEUE_FULL_RETRY_INTERVAL,
+                    };
+                    thread::sleep(interval);
+                    record = r;
+                }
+                Err((e, _)) => return Err(e),
+            }
+        };
+        match delivery.wait(remaining().ok_or_else(timed_out)?) {
+            Some(Ok(Ok(position))) => Ok(position),
+            Some(Ok(Err((e, _)))) => Err(e),
+            Some(Err(_)) => Err(KafkaError::Canceled),
+            None => Err(timed_out()),
+        }
+    }
+}
+
+/// Returns the headers of the message, followed by the `dlq.*` headers.
+fn dlq_headers<M: Message>(message: &M, error: &str, retry_count: u32) -> OwnedHeaders {
+    let headers = match message.headers() {
+        Some(headers) => copy_headers(headers),
+        None => OwnedHeaders::new_with_capacity(5),
+    };
+    let topic = message.topic();
+    let partition = message.partition().to_string();
+    let offset = message.offset().to_string();
+    let retry_count = retry_count.to_string();
+    headers
+        .insert(Header {
+            key: DLQ_TOPIC_HEADER,
+            value: Some(topic),
+        })
+        .insert(Header {
+            key: DLQ_PARTITION_HEADER,
+            value: Some(&partition),
+        })
+        .insert(Header {
+            key: DLQ_OFFSET_HEADER,
+            value: Some(&offset),
+        })
+        .insert(Header {
+            key: DLQ_ERROR_HEADER,
+            value: Some(error),
+        })
+        .insert(Header {
+            key: DLQ_RETRY_COUNT_HEADER,
+            value: Some(&retry_count),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{Headers, OwnedMessage, Timestamp};
+
+    #[test]
+    fn test_dlq_headers() {
+        let headers = OwnedHeaders::new().insert(Header {
+            key: "origin",
+            value: Some("web"),
+        });
+        let message = OwnedMessage::new(
+            Some(b"payload".to_vec()),
+            None,
+            "orders".into(),
+            Timestamp::CreateTime(42),
+            2,
+            17,
+            Some(headers),
+        );
+        let headers = dlq_headers(&message, "invalid payload", 3);
+        let headers = (0..headers.count())
+            .map(|i| {
+                let header = headers.get_as::<str>(i).unwrap();
+                (header.key.to_owned(), header.value.unwrap().to_owned())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            headers,
+            [
+                ("origin", "web"),
+                (DLQ_TOPIC_HEADER, "orders"),
+                (DLQ_PARTITION_HEADER, "2"),
+                (DLQ_OFFSET_HEADER, "17"),
+                (DLQ_ERROR_HEADER, "invalid payload"),
+                (DLQ_RETRY_COUNT_HEADER, "3"),
+            ]
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect::<Vec<_>>()
+        );
+    }
+}
@@ -3,28 +3,40 @@
 //! See the [`FutureProducer`] for details.
 // TODO: extend docs
 
+use std::collections::HashMap;
 use std::error::Error;
 use std::future::Future;
 use std::marker::PhantomData;
+use std::mem;
+use std::os::raw::c_void;
 use std::pin::Pin;
-use std::sync::Arc;
-use std::task::{Context, Poll};
+use std::ptr;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
 use std::time::{Duration, Instant};
 
 use futures_channel::oneshot;
-use futures_util::FutureExt;
+use futures_util::future::{self, Either};
+use futures_util::pin_mut;
 
-use crate::client::{Client, ClientContext, DefaultClientContext, OAuthToken};
-use crate::config::{ClientConfig, FromClientConfig, FromClientConfigAndContext, RDKafkaLogLevel};
+use crate::client::{Client, ClientContext, DefaultClientContext, OAuthToken, SslCertVerification};
+use crate::config::{
+    ClientConfig, FromClientConfig, FromClientConfigAndContext, RDKafkaLogLevel, TopicConfig,
+};
 use crate::consumer::ConsumerGroupMetadata;
 use crate::error::{KafkaError, KafkaResult, RDKafkaErrorCode};
+use crate::interceptor::{intercept_send, InterceptedRecord, Interceptor};
+use crate::log::debug;
 use crate::message::{Message, OwnedHeaders, OwnedMessage, Timestamp, ToBytes};
+use crate::metadata::Metadata;
 use crate::producer::{
-    BaseRecord, DeliveryResult, Producer, ProducerContext, PurgeConfig, ThreadedProducer,
+    BaseRecord, DeliveryResult, Producer, ProducerContext, PurgeConfig, QueueDepth, RetryPolicy,
+    ThreadedProducer, TopicMetrics,
 };
 use crate::statistics::Statistics;
 use crate::topic_partition_list::TopicPartitionList;
-use crate::util::{AsyncRuntime, DefaultRuntime, IntoOpaque, Timeout};
+use crate::util::{self, AsyncRuntime, CallbackThread, DefaultRuntime, IntoOpaque, Timeout};
 
 //
 // ********** FUTURE PRODUCER **********
@@ -107,6 +119,25 @@ impl<'a, K: ToBytes + ?Sized, P: ToBytes + ?Sized> FutureRecord<'a, K, P> {
         self
     }
 
+    fn into_owned_message(self) -> OwnedMessage {
+        OwnedMessage::new(
+            self.payload.map(|p| p.to_bytes().to_vec()),
+            self.key.map(|k| k.to_bytes().to_vec()),
+            self.topic.to_owned(),
+            self.timestamp
+                .map_or(Timestamp::NotAvailable, Timestamp::CreateTime),
+            self.partition.unwrap_or(-1),
+            0,
+            self.headers,
+        )
+    }
+
+    fn size(&self) -> u64 {
+        let payload_len = self.payload.map_or(0, |p| p.to_bytes().len());
+        let key_len = self.key.map_or(0, |k| k.to_bytes().len());
+        (payload_len + key_len) as u64
+    }
+
     fn into_base_record<D: IntoOpaque>(self, delivery_opaque: D) -> BaseRecord<'a, K, P, D> {
         BaseRecord {
             topic: self.topic,
@@ -129,6 +160,115 @@ pub struct FutureProducerContext<C: ClientContext + 'static> {
     wrapped_context: C,
 }
 
+/// Tracks the messages sent by a [`FutureProducer`] whose delivery has not
+/// been reported yet, and makes senders wait while they exceed the limit.
+#[derive(Default)]
+struct InFlight {
+    state: Mutex<InFlightState>,
+}
+
+#[derive(Default)]
+struct InFlightState {
+    limit: Option<QueueDepth>,
+    depth: QueueDepth,
+    waiters: Vec<Waker>,
+}
+
+impl InFlight {
+    fn set_limit(&self, limit: Option<QueueDepth>) {
+        let mut state = self.state.lock().unwrap();
+        state.limit = limit;
+        for waker in state.waiters.drain(..) {
+            waker.wake();
+        }
+    }
+
+    fn depth(&self) -> QueueDepth {
+        self.state.lock().unwrap().depth
+    }
+
+    /// Records a message without waiting for the limit.
+    fn add(self: &Arc<Self>, bytes: u64) -> InFlightPermit {
+        let mut state = self.state.lock().unwrap();
+        state.depth.messages += 1;
+        state.depth.bytes += bytes;
+        InFlightPermit {
+            in_flight: self.clone(),
+            bytes,
+        }
+    }
+
+    /// Waits until a message of the specified size fits within the limit, and
+    /// records it.
+    fn acquire(self: &Arc<Self>, bytes: u64) -> Acquire {
+        Acquire {
+            in_flight: self.clone(),
+            bytes,
+        }
+    }
+
+    fn release(&self, bytes: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.depth.messages = state.depth.messages.saturating_sub(1);
+        state.depth.bytes = state.depth.bytes.saturating_sub(bytes);
+        for waker in state.waiters.drain(..) {
+            waker.wake();
+        }
+    }
+}
+
+/// A message recorded by [`InFlight`], which is released when the permit is
+/// dropped.
+///
+/// The permit of a message that was enqueued is held by its
+/// [`DeliverySender`], so that it is released once the delivery of the
+/// message is reported, or as soon as the message is dropped without being
+/// enqueued, e.g. because the future of the send was dropped.
+struct InFlightPermit {
+    in_flight: Arc<InFlight>,
+    bytes: u64,
+}
+
+impl Drop for InFlightPermit {
+    fn drop(&mut self) {
+        self.in_flight.release(self.bytes);
+    }
+}
+
+struct Acquire {
+    in_flight: Arc<InFlight>,
+    bytes: u64,
+}
+
+impl Future for Acquire {
+    type Output = InFlightPermit;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<InFlightPermit> {
+        let mut state = self.in_flight.state.lock().unwrap();
+        // A message larger than the limit is let through when nothing else
+        // is in flight, as it would otherwise wait forever.
+        let fits = match state.limit {
+            None => true,
+            Some(limit) => {
+                state.depth.messages == 0
+                    || (state.depth.messages < limit.messages
+                        && state.depth.bytes + self.bytes <= limit.bytes)
+            }
+        };
+        if fits {
+            state.depth.messages += 1;
+            state.depth.bytes += self.bytes;
+            Poll::Ready(InFlightPermit {
+                in_flight: self.in_flight.clone(),
+                bytes: self.bytes,
+            })
+        } else {
+            state.waiters.push(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
 /// Represents the result of message production as performed from the
 /// `FutureProducer`.
 ///
@@ -141,6 +281,9 @@ pub type OwnedDeliveryResult = Result<(i32, i64), (KafkaError, OwnedMessage)>;
 // Delegates all the methods calls to the wrapped context.
 impl<C: ClientContext + 'static> ClientContext for FutureProducerContext<C> {
     const ENABLE_REFRESH_OAUTH_TOKEN: bool = C::ENABLE_REFRESH_OAUTH_TOKEN;
+    const ENABLE_SSL_CERT_VERIFY: bool = C::ENABLE_SSL_CERT_VERIFY;
+    const ENABLE_SSL_CERT_OVERRIDE: bool = C::ENABLE_SSL_CERT_OVERRIDE;
+    const ENABLE_BROKER_STATES: bool = C::ENABLE_BROKER_STATES;
 
     fn log(&self, level: RDKafkaLogLevel, fac: &str, log_message: &str) {
         self.wrapped_context.log(level, fac, log_message);
@@ -165,21 +308,29 @@ impl<C: ClientContext + 'static> ClientContext for FutureProducerContext<C> {
         self.wrapped_context
             .generate_oauth_token(oauthbearer_config)
     }
+
+    fn verify_ssl_cert(&self, cert: &SslCertVerification<'_>) -> Result<(), String> {
+        self.wrapped_context.verify_ssl_cert(cert)
+    }
+
+    fn polling_thread(&self) -> Option<CallbackThread> {
+        self.wrapped_context.polling_thread()
+    }
+
+    fn interceptors(&self) -> &[Arc<dyn Interceptor>] {
+        self.wrapped_context.interceptors()
+    }
 }
 
 impl<C: ClientContext + 'static> ProducerContext for FutureProducerContext<C> {
-    type DeliveryOpaque = Box<oneshot::Sender<OwnedDeliveryResult>>;
+    type DeliveryOpaque = DeliverySender;
 
-    fn delivery(
-        &self,
-        delivery_result: &DeliveryResult<'_>,
-        tx: Box<oneshot::Sender<OwnedDeliveryResult>>,
-    ) {
+    fn delivery(&self, delivery_result: &DeliveryResult<'_>, tx: DeliverySender) {
         let owned_delivery_result = match *delivery_result {
             Ok(ref message) => Ok((message.partition(), message.offset())),
             Err((ref error, ref message)) => Err((error.clone(), message.detach())),
         };
-        let _ = tx.send(owned_delivery_result); // TODO: handle error
+        tx.send(owned_delivery_result);
     }
 }
 
@@ -194,12 +345,19 @@ impl<C: ClientContext + 'static> ProducerContext for FutureProducerContext<C> {
 /// be polled. It can be cheaply cloned to get a reference to the same
 /// underlying producer. The internal polling thread will be terminated when the
 /// `FutureProducer` goes out of scope.
+///
+/// The number and size of the messages in flight, i.e. sent but whose
+/// delivery has not been reported yet, can be bounded with
+/// [`FutureProducer::set_in_flight_limit`]. Once the limit is reached,
+/// [`FutureProducer::send`] waits for deliveries to free up space instead of
+/// filling up the librdkafka queue.
 #[must_use = "Producer polling thread will stop immediately if unused"]
 pub struct FutureProducer<C = DefaultClientContext, R = DefaultRuntime>
 where
     C: ClientContext + 'static,
 {
     producer: Arc<ThreadedProducer<FutureProducerContext<C>>>,
+    in_flight: Arc<InFlight>,
     _runtime: PhantomData<R>,
 }
 
@@ -210,6 +368,7 @@ where
     fn clone(&self) -> FutureProducer<C, R> {
         FutureProducer {
             producer: self.producer.clone(),
+            in_flight: self.in_flight.clone(),
             _runtime: PhantomData,
         }
     }
@@ -233,17 +392,92 @@ where
         config: &ClientConfig,
         context: C,
     ) -> KafkaResult<FutureProducer<C, R>> {
+        let in_flight = Arc::new(InFlight::default());
         let future_context = FutureProducerContext {
             wrapped_context: context,
         };
         let threaded_producer = ThreadedProducer::from_config_and_context(config, future_context)?;
         Ok(FutureProducer {
             producer: Arc::new(threaded_producer),
+            in_flight,
             _runtime: PhantomData,
         })
     }
 }
 
+struct DeliveryState {
+    result: Option<OwnedDeliveryResult>,
+    waker: Option<Waker>,
+    closed: bool,
+    // Released when the sender is dropped.
+    permit: Option<InFlightPermit>,
+}
+
+/// The state shared by a [`DeliverySender`] and its [`DeliveryFuture`].
+///
+/// The state is allocated once per message, and its pointer is the opaque
+/// of the message.
+struct DeliverySlot {
+    state: Mutex<DeliveryState>,
+}
+
+/// The delivery opaque of the messages sent by a [`FutureProducer`], which
+/// completes the [`DeliveryFuture`] of the message.
+pub struct DeliverySender {
+    slot: Arc<DeliverySlot>,
+}
+
+fn delivery_channel(permit: InFlightPermit) -> (DeliverySender, DeliveryFuture) {
+    let slot = Arc::new(DeliverySlot {
+        state: Mutex::new(DeliveryState {
+            result: None,
+            waker: None,
+            closed: false,
+            permit: Some(permit),
+        }),
+    });
+    let future = DeliveryFuture { slot: slot.clone() };
+    (DeliverySender { slot }, future)
+}
+
+impl DeliverySender {
+    /// Completes the future with the result. The future is woken when the
+    /// sender is dropped.
+    fn send(self, result: OwnedDeliveryResult) {
+        self.slot.state.lock().unwrap().result = Some(result);
+    }
+}
+
+impl Drop for DeliverySender {
+    fn drop(&mut self) {
+        let (waker, _permit) = {
+            let mut state = self.slot.state.lock().unwrap();
+            state.closed = true;
+            (state.waker.take(), state.permit.take())
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl IntoOpaque for DeliverySender {
+    fn into_ptr(self) -> *mut c_void {
+        // The sender is not dropped, so that the slot is closed when the
+        // sender is restored from the pointer instead.
+        let sender = mem::ManuallyDrop::new(self);
+        // SAFETY: the slot is moved out of a sender that is never dropped.
+        let slot = unsafe { ptr::read(&sender.slot) };
+        Arc::into_raw(slot) as *mut c_void
+    }
+
+    unsafe fn from_ptr(ptr: *mut c_void) -> Self {
+        DeliverySender {
+            slot: Arc::from_raw(ptr as *const DeliverySlot),
+        }
+    }
+}
+
 /// A [`Future`] wrapping the result of the message production.
 ///
 /// Once completed, the future will contain an `OwnedDeliveryResult` with
@@ -251,14 +485,61 @@ where
 /// dropped before the delivery status is received, the future will instead
 /// resolve with [`oneshot::Canceled`].
 pub struct DeliveryFuture {
-    rx: oneshot::Receiver<OwnedDeliveryResult>,
+    slot: Arc<DeliverySlot>,
 }
 
 impl Future for DeliveryFuture {
     type Output = Result<OwnedDeliveryResult, oneshot::Canceled>;
 
-    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        self.rx.poll_unpin(cx)
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.slot.state.lock().unwrap();
+        if let Some(result) = state.result.take() {
+            Poll::Ready(Ok(result))
+        } else if state.closed {
+            Poll::Ready(Err(oneshot::Canceled))
+        } else {
+            state.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+impl DeliveryFuture {
+    /// Blocks the calling thread until the delivery is reported or the
+    /// timeout expires, in which case `None` is returned.
+    pub(crate) fn wait(
+        &self,
+        timeout: Timeout,
+    ) -> Option<Result<OwnedDeliveryResult, oneshot::Canceled>> {
+        let start = Instant::now();
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        loop {
+            {
+                let mut state = self.slot.state.lock().unwrap();
+                if let Some(result) = state.result.take() {
+                    return Some(Ok(result));
+                } else if state.closed {
+                    return Some(Err(oneshot::Canceled));
+                }
+                state.waker = Some(waker.clone());
+            }
+            match timeout {
+                Timeout::After(t) => match t.checked_sub(start.elapsed()) {
+                    Some(remaining) => thread::park_timeout(remaining),
+                    None => return None,
+                },
+                Timeout::Never => thread::park(),
+            }
+        }
+    }
+}
+
+/// Wakes a thread blocked in [`DeliveryFuture::wait`].
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
     }
 }
 
@@ -275,6 +556,11 @@ where
     /// and the queue is still full, an [`RDKafkaErrorCode::QueueFull`] error will
     /// be reported in the [`OwnedDeliveryResult`].
     ///
+    /// If an in-flight limit is set with [`FutureProducer::set_in_flight_limit`],
+    /// the send first waits for the message to fit within the limit, without
+    /// blocking the executor. The wait counts towards `queue_timeout`, and a
+    /// [`RDKafkaErrorCode::QueueFull`] error is reported if it expires.
+    ///
     /// Keep in mind that `queue_timeout` only applies to the first phase of the
     /// send operation. Once the message is queued, the underlying librdkafka
     /// client has separate timeout parameters that apply, like
@@ -291,20 +577,61 @@ where
         K: ToBytes + ?Sized,
         P: ToBytes + ?Sized,
         T: Into<Timeout>,
+    {
+        self.send_attempt(record, queue_timeout.into(), true).await
+    }
+
+    /// Sends a message, calling the interceptors first if `intercept` is
+    /// true.
+    async fn send_attempt<K, P>(
+        &self,
+        record: FutureRecord<'_, K, P>,
+        queue_timeout: Timeout,
+        mut intercept: bool,
+    ) -> OwnedDeliveryResult
+    where
+        K: ToBytes + ?Sized,
+        P: ToBytes + ?Sized,
     {
         let start_time = Instant::now();
-        let queue_timeout = queue_timeout.into();
         let can_retry = || match queue_timeout {
             Timeout::Never => true,
             Timeout::After(t) if start_time.elapsed() < t => true,
             _ => false,
         };
 
-        let (tx, rx) = oneshot::channel();
-        let mut base_record = record.into_base_record(Box::new(tx));
+        let acquire = self.in_flight.acquire(record.size());
+        let permit = match queue_timeout {
+            Timeout::Never => acquire.await,
+            Timeout::After(t) => {
+                let delay = R::delay_for(t.saturating_sub(start_time.elapsed()));
+                pin_mut!(delay);
+                match future::select(acquire, delay).await {
+                    Either::Left((permit, _)) => permit,
+                    Either::Right(_) => {
+                        return Err((
+                            KafkaError::MessageProduction(RDKafkaErrorCode::QueueFull),
+                            record.into_owned_message(),
+                        ));
+                    }
+                }
+            }
+        };
+
+        // The permit is released with the sender, i.e. when the delivery is
+        // reported, or when the record is dropped without being enqueued.
+        let (tx, delivery_future) = delivery_channel(permit);
+        let mut base_record = record.into_base_record(tx);
 
         loop {
-            match self.producer.send(base_record) {
+            let result = if intercept {
+                self.producer.send(base_record)
+            } else {
+                self.producer.send_intercepted(base_record)
+            };
+            // The record is intercepted at most once, even if it is retried.
+            intercept = false;
+            match result {
                 Err((e, record))
                     if e == KafkaError::MessageProduction(RDKafkaErrorCode::QueueFull)
                         && can_retry() =>
@@ -315,23 +642,73 @@ where
                 Ok(_) => {
                     // We hold a reference to the producer, so it should not be
                     // possible for the producer to vanish and cancel the
-                    // oneshot.
-                    break rx.await.expect("producer unexpectedly dropped");
+                    // delivery future.
+                    break delivery_future
+                        .await
+                        .expect("producer unexpectedly dropped");
                 }
                 Err((e, record)) => {
-                    let owned_message = OwnedMessage::new(
-                        record.payload.map(|p| p.to_bytes().to_vec()),
-                        record.key.map(|k| k.to_bytes().to_vec()),
-                        record.topic.to_owned(),
-                        record
-                            .timestamp
-                            .map_or(Timestamp::NotAvailable, Timestamp::CreateTime),
-                        record.partition.unwrap_or(-1),
-                        0,
-                        record.headers,
+                    let record = FutureRecord::from_base_record(record);
+                    break Err((e, record.into_owned_message()));
+                }
+            }
+        }
+    }
+
+    /// Like [`FutureProducer::send`], but retries sends that fail with a
+    /// transient error, as described by the [`RetryPolicy`].
+    ///
+    /// The record is sent again, after the backoff of the policy, every time
+    /// its enqueuing or its delivery fails with an error that the policy
+    /// retries, until the attempts of the policy are exhausted. The
+    /// `queue_timeout` applies to every attempt. Note that a message whose
+    /// delivery timed out may have been written to the topic nonetheless, so
+    /// retries may produce duplicates unless the producer is idempotent.
+    pub async fn send_with_retry<K, P, T>(
+        &self,
+        record: FutureRecord<'_, K, P>,
+        queue_timeout: T,
+        policy: &RetryPolicy,
+    ) -> OwnedDeliveryResult
+    where
+        K: ToBytes + ?Sized,
+        P: ToBytes + ?Sized,
+        T: Into<Timeout>,
+    {
+        let queue_timeout = queue_timeout.into();
+        // The record is intercepted once, rather than by every attempt.
+        let mut headers = record.headers;
+        intercept_send(
+            self.producer.context().interceptors(),
+            &mut InterceptedRecord {
+                topic: record.topic,
+                partition: record.partition,
+                key: record.key.map(ToBytes::to_bytes),
+                payload: record.payload.map(ToBytes::to_bytes),
+                timestamp: record.timestamp,
+                headers: &mut headers,
+            },
+        );
+        let record = FutureRecord { headers, ..record };
+        let mut attempt = 1;
+        loop {
+            let attempt_record = FutureRecord {
+                headers: record.headers.clone(),
+                ..record
+            };
+            match self
+                .send_attempt(attempt_record, queue_timeout, false)
+                .await
+            {
+                Err((e, _)) if policy.should_retry(attempt, &e) => {
+                    debug!(
+                        "Retrying send to {} after attempt {} failed: {}",
+                        record.topic, attempt, e
                     );
-                    break Err((e, owned_message));
+                    R::delay_for(policy.backoff(attempt)).await;
+                    attempt += 1;
                 }
+                result => break result,
             }
         }
     }
@@ -346,14 +723,90 @@ where
         K: ToBytes + ?Sized,
         P: ToBytes + ?Sized,
     {
-        let (tx, rx) = oneshot::channel();
-        let base_record = record.into_base_record(Box::new(tx));
+        let permit = self.in_flight.add(record.size());
+        let (tx, delivery_future) = delivery_channel(permit);
+        let base_record = record.into_base_record(tx);
         self.producer
             .send(base_record)
-            .map(|()| DeliveryFuture { rx })
+            .map(|()| delivery_future)
             .map_err(|(e, record)| (e, FutureRecord::from_base_record(record)))
     }
 
+    /// Bounds the messages in flight, i.e. sent but whose delivery has not
+    /// been reported yet, or removes the bound if `limit` is `None`.
+    ///
+    /// Bytes are the sum of the key and payload sizes of the messages. The
+    /// limit applies to [`FutureProducer::send`], which waits for deliveries
+    /// to bring the messages in flight under the limit; messages sent with
+    /// [`FutureProducer::send_result`] count towards the limit but never wait.
+    /// There is no limit by default.
+    ///
+    /// The limit is shared by all the clones of the producer.
+    pub fn set_in_flight_limit(&self, limit: Option<QueueDepth>) {
+        self.in_flight.set_limit(limit);
+    }
+
+    /// Returns the number and size of the messages in flight.
+    ///
+    /// See [`FutureProducer::set_in_flight_limit`] for details.
+    pub fn in_flight_depth(&self) -> QueueDepth {
+        self.in_flight.depth()
+    }
+
+    /// Returns a snapshot of the counters of every topic.
+    ///
+    /// See the documentation for [`BaseProducer::metrics`] for details.
+    ///
+    /// [`BaseProducer::metrics`]: crate::producer::BaseProducer::metrics
+    pub fn metrics(&self) -> HashMap<String, TopicMetrics> {
+        self.producer.metrics()
+    }
+
+    /// Like [`Client::fetch_metadata`], but asynchronous.
+    ///
+    /// The metadata request runs with [`AsyncRuntime::spawn_blocking`], so
+    /// that the executor thread of the calling task is not blocked while
+    /// waiting for the brokers.
+    pub async fn fetch_metadata_async<T>(
+        &self,
+        topic: Option<&str>,
+        timeout: T,
+    ) -> KafkaResult<Metadata>
+    where
+        T: Into<Timeout>,
+    {
+        let producer = self.producer.clone();
+        let topic = topic.map(str::to_owned);
+        let timeout = timeout.into();
+        util::run_blocking::<R, _, _>(move || {
+            producer.client().fetch_metadata(topic.as_deref(), timeout)
+        })
+        .await
+    }
+
+    /// Like [`Client::fetch_watermarks`], but asynchronous.
+    ///
+    /// See [`FutureProducer::fetch_metadata_async`] for details.
+    pub async fn fetch_watermarks_async<T>(
+        &self,
+        topic: &str,
+        partition: i32,
+        timeout: T,
+    ) -> KafkaResult<(i64, i64)>
+    where
+        T: Into<Timeout>,
+    {
+        let producer = self.producer.clone();
+        let topic = topic.to_owned();
+        let timeout = timeout.into();
+        util::run_blocking::<R, _, _>(move || {
+            producer
+                .client()
+                .fetch_watermarks(&topic, partition, timeout)
+        })
+        .await
+    }
+
     /// Polls the internal producer.
     ///
     /// This is not normally required since the `FutureProducer` has a thread
@@ -361,6 +814,37 @@ where
     pub fn poll<T: Into<Timeout>>(&self, timeout: T) {
         self.producer.poll(timeout);
     }
+
+    /// Installs a configuration specific to `topic`.
+    ///
+    /// See the documentation for [`BaseProducer::configure_topic`] for
+    /// details.
+    ///
+    /// [`BaseProducer::configure_topic`]: crate::producer::BaseProducer::configure_topic
+    pub fn configure_topic(&self, topic: &str, config: &TopicConfig) -> KafkaResult<()> {
+        self.producer.configure_topic(topic, config)
+    }
+
+    /// Caches the native handle of `topic`.
+    ///
+    /// See the documentation for [`BaseProducer::register_topic`] for
+    /// details.
+    ///
+    /// [`BaseProducer::register_topic`]: crate::producer::BaseProducer::register_topic
+    pub fn register_topic(&self, topic: &str) -> KafkaResult<()> {
+        self.producer.register_topic(topic)
+    }
+
+    /// Makes the producer flush outstanding messages when the last of its
+    /// clones is dropped.
+    ///
+    /// See the documentation for [`BaseProducer::set_flush_on_drop`] for
+    /// details. The setting is shared by all the clones of the producer.
+    ///
+    /// [`BaseProducer::set_flush_on_drop`]: crate::producer::BaseProducer::set_flush_on_drop
+    pub fn set_flush_on_drop(&self, timeout: Option<Duration>) {
+        self.producer.set_flush_on_drop(timeout)
+    }
 }
 
 impl<C, R> Producer<FutureProducerContext<C>> for FutureProducer<C, R>
@@ -445,4 +929,61 @@ mod tests {
             .unwrap();
         let _producer_clone = producer.clone();
     }
+
+    #[test]
+    fn test_delivery_channel() {
+        use futures::FutureExt;
+
+        let in_flight = Arc::new(InFlight::default());
+        let (tx, mut future) = delivery_channel(in_flight.add(10));
+        assert!((&mut future).now_or_never().is_none());
+        let tx = unsafe { DeliverySender::from_ptr(tx.into_ptr()) };
+        assert_eq!(in_flight.depth().bytes, 10);
+        tx.send(Ok((1, 42)));
+        assert!(matches!(future.now_or_never(), Some(Ok(Ok((1, 42))))));
+        assert_eq!(in_flight.depth(), QueueDepth::default());
+
+        let (tx, future) = delivery_channel(in_flight.add(10));
+        drop(unsafe { DeliverySender::from_ptr(tx.into_ptr()) });
+        assert!(matches!(
+            future.now_or_never(),
+            Some(Err(oneshot::Canceled))
+        ));
+        assert_eq!(in_flight.depth(), QueueDepth::default());
+    }
+
+    #[test]
+    fn test_delivery_wait() {
+        let in_flight = Arc::new(InFlight::default());
+        let (tx, future) = delivery_channel(in_flight.add(10));
+        assert!(future
+            .wait(Timeout::After(Duration::from_millis(10)))
+            .is_none());
+        let sender = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            tx.send(Ok((1, 42)));
+        });
+        assert!(matches!(future.wait(Timeout::Never), Some(Ok(Ok((1, 42))))));
+        sender.join().unwrap();
+    }
+
+    #[test]
+    fn test_in_flight_limit() {
+        use futures::FutureExt;
+
+        let in_flight = Arc::new(InFlight::default());
+        in_flight.set_limit(Some(QueueDepth {
+            messages: 1,
+            bytes: 100,
+        }));
+        let permit = in_flight.acquire(10).now_or_never().unwrap();
+        let mut acquire = in_flight.acquire(10);
+        assert!((&mut acquire).now_or_never().is_none());
+        // Dropping a waiting acquisition does not record the message.
+        drop(acquire);
+        assert_eq!(in_flight.depth().messages, 1);
+        drop(permit);
+        assert_eq!(in_flight.depth(), QueueDepth::default());
+        assert!(in_flight.acquire(10).now_or_never().is_some());
+    }
 }
@@ -160,20 +160,42 @@
 //! [Transactional Producer]: https://github.com/edenhill/librdkafka/blob/master/INTRODUCTION.md#transactional-producer
 
 use std::sync::Arc;
+use std::time::Duration;
 
 use crate::client::{Client, ClientContext};
 use crate::consumer::ConsumerGroupMetadata;
-use crate::error::KafkaResult;
+use crate::error::{KafkaResult, RDKafkaErrorCode};
+use crate::log::warn;
 use crate::topic_partition_list::TopicPartitionList;
 use crate::util::{IntoOpaque, Timeout};
 
 pub mod base_producer;
+pub mod delivery_handle;
+pub mod dlq;
 pub mod future_producer;
+pub mod prepared_record;
+pub mod retry;
+pub mod topic_template;
+pub mod typed_producer;
+#[cfg(feature = "watchdog")]
+mod watchdog;
 
 #[doc(inline)]
-pub use self::base_producer::{BaseProducer, BaseRecord, DeliveryResult, ThreadedProducer};
+pub use self::base_producer::{
+    BaseProducer, BaseRecord, DeliveryResult, OwnedPayload, ThreadedProducer,
+};
+#[doc(inline)]
+pub use self::dlq::DlqProducer;
 #[doc(inline)]
 pub use self::future_producer::{DeliveryFuture, FutureProducer, FutureRecord};
+#[doc(inline)]
+pub use self::prepared_record::PreparedRecord;
+#[doc(inline)]
+pub use self::retry::RetryPolicy;
+#[doc(inline)]
+pub use self::topic_template::{TopicParams, TopicTemplate};
+#[doc(inline)]
+pub use self::typed_producer::{TypedProducer, TypedRecord};
 
 //
 // ********** PRODUCER CONTEXT **********
@@ -195,10 +217,201 @@ pub trait ProducerContext: ClientContext {
     /// method once the message has been delivered, or failed to.
     type DeliveryOpaque: IntoOpaque;
 
+    /// Whether to pick the partition of messages that do not specify one by
+    /// calling [`ProducerContext::partition`].
+    ///
+    /// If disabled, the partitioner configured with the `partitioner` topic
+    /// property is used instead.
+    const ENABLE_CUSTOM_PARTITIONER: bool = false;
+
     /// This method will be called once the message has been delivered (or
     /// failed to). The `DeliveryOpaque` will be the one provided by the user
     /// when calling send.
     fn delivery(&self, delivery_result: &DeliveryResult<'_>, delivery_opaque: Self::DeliveryOpaque);
+
+    /// Returns how long messages can be sent without the producer being
+    /// polled before [`ProducerContext::poll_overdue`] is called.
+    ///
+    /// Forgetting to call [`BaseProducer::poll`] is a common misuse of the
+    /// `BaseProducer`: delivery callbacks are never executed, and the producer
+    /// queue eventually fills up. The check is performed on every call to
+    /// `send`, and is disabled by default.
+    fn poll_overdue_threshold(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Called by `send` when the producer has not been polled for longer than
+    /// [`ProducerContext::poll_overdue_threshold`].
+    ///
+    /// The callback is invoked at most once per threshold interval. The default
+    /// implementation logs a warning.
+    fn poll_overdue(&self, since_last_poll: Duration) {
+        warn!(
+            "Producer has not been polled for {:?}, delivery callbacks are not being served",
+            since_last_poll
+        );
+    }
+
+    /// Returns how long messages can be sent without the producer being
+    /// polled or flushed before [`ProducerContext::queue_stalled`] is called,
+    /// or `None`, the default, to disable the watchdog.
+    ///
+    /// The watchdog starts a background thread for every [`BaseProducer`]
+    /// whose context enables it. Unlike
+    /// [`ProducerContext::poll_overdue_threshold`], which is checked when
+    /// messages are sent and needs no thread, the watchdog also detects
+    /// producers that sent a burst of messages and were never polled
+    /// afterwards. Producers that poll themselves, like the
+    /// [`ThreadedProducer`] and the [`FutureProducer`], never start it.
+    ///
+    /// This method requires the `watchdog` feature.
+    #[cfg(feature = "watchdog")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "watchdog")))]
+    fn watchdog_threshold(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Called by the watchdog, at most once per producer, when `sent`
+    /// messages were sent and the producer was not polled or flushed for
+    /// longer than [`ProducerContext::watchdog_threshold`].
+    ///
+    /// The callback is invoked on the watchdog thread. The default
+    /// implementation logs a warning.
+    ///
+    /// This method requires the `watchdog` feature.
+    #[cfg(feature = "watchdog")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "watchdog")))]
+    fn queue_stalled(&self, sent: u64, since_last_poll: Duration) {
+        warn!(
+            "{} messages were sent but the producer has not been polled or flushed for {:?}; \
+             they will be lost when the producer is dropped",
+            sent, since_last_poll
+        );
+    }
+
+    /// Returns how long partition metadata can be cached by the partition
+    /// availability check, or `None` to disable the check.
+    ///
+    /// When the check is enabled, `send` fails immediately with
+    /// [`KafkaError::PartitionUnavailable`] if the target partition has no
+    /// leader, instead of queueing a message that would only fail once
+    /// `message.timeout.ms` expires. Messages without an explicit partition
+    /// are only rejected if no partition of the topic has a leader, and
+    /// messages for topics unknown to the cluster are never rejected, so that
+    /// topics can still be created automatically.
+    ///
+    /// The metadata of a topic is fetched from the cluster when its cached
+    /// copy is older than the returned duration, which blocks the call to
+    /// `send` for up to five seconds. If the metadata cannot be fetched, the
+    /// message is not rejected, and the metadata is fetched again by the next
+    /// `send` to the topic. The check is disabled by default.
+    ///
+    /// [`KafkaError::PartitionUnavailable`]: crate::error::KafkaError::PartitionUnavailable
+    fn partition_availability_check(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Returns the watermarks on the depth of the producer queue that trigger
+    /// [`ProducerContext::queue_watermark`].
+    ///
+    /// By default, no watermarks are configured.
+    fn queue_watermarks(&self) -> Option<QueueWatermarks> {
+        None
+    }
+
+    /// Called when the depth of the producer queue crosses one of the
+    /// [`ProducerContext::queue_watermarks`].
+    ///
+    /// [`QueueWatermark::High`] is reported once the queue reaches the high
+    /// watermark, and [`QueueWatermark::Low`] once it subsequently drains
+    /// below the low watermark. This callback can be used to stop accepting
+    /// new work while the queue is too full, and resume afterwards.
+    #[allow(unused_variables)]
+    fn queue_watermark(&self, watermark: QueueWatermark, depth: QueueDepth) {}
+
+    /// Picks the partition of a message sent to `topic` without an explicit
+    /// partition.
+    ///
+    /// For this method to be called, you must also set
+    /// [`ProducerContext::ENABLE_CUSTOM_PARTITIONER`] to true. The method must
+    /// return a partition between `0` and `partition_count - 1`; messages for
+    /// which any other value is returned fail with
+    /// [`RDKafkaErrorCode::UnknownPartition`]. The method can be called from
+    /// librdkafka's internal threads, and should therefore be fast and must
+    /// not block. If the method panics, the message fails as if an invalid
+    /// partition was returned.
+    ///
+    /// The default implementation always returns an invalid partition and is
+    /// meant to be overridden.
+    #[allow(unused_variables)]
+    fn partition(&self, topic: &str, key: Option<&[u8]>, partition_count: i32) -> i32 {
+        -1
+    }
+}
+
+/// The depth of a producer queue.
+///
+/// Only messages that were enqueued by the Rust producer and whose delivery
+/// has not been reported yet are counted. Bytes are the sum of the key and
+/// payload sizes of those messages.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct QueueDepth {
+    /// The number of messages in the queue.
+    pub messages: u64,
+    /// The number of bytes in the queue.
+    pub bytes: u64,
+}
+
+/// The counters that a producer maintains for each topic.
+///
+/// Bytes are the sum of the key and payload sizes of the messages. Messages
+/// that could not be enqueued, and whose error was thus returned by `send`,
+/// are not counted. Deliveries are only counted once the producer serves
+/// their delivery callbacks, i.e. once it is polled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TopicMetrics {
+    /// The number of messages enqueued for the topic.
+    pub messages_sent: u64,
+    /// The number of messages successfully delivered to the topic.
+    pub messages_delivered: u64,
+    /// The number of messages that failed to be delivered to the topic.
+    pub messages_failed: u64,
+    /// The number of bytes enqueued for the topic.
+    pub bytes_sent: u64,
+    /// The number of bytes successfully delivered to the topic.
+    pub bytes_delivered: u64,
+}
+
+/// High and low watermarks on the depth of a producer queue.
+///
+/// The queue is considered above the high watermark when either its message
+/// count or its byte size reaches the corresponding high value, and below the
+/// low watermark when both are under the corresponding low values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QueueWatermarks {
+    /// The high watermark.
+    pub high: QueueDepth,
+    /// The low watermark.
+    pub low: QueueDepth,
+}
+
+impl QueueWatermarks {
+    pub(crate) fn is_above_high(&self, depth: QueueDepth) -> bool {
+        depth.messages >= self.high.messages || depth.bytes >= self.high.bytes
+    }
+
+    pub(crate) fn is_below_low(&self, depth: QueueDepth) -> bool {
+        depth.messages < self.low.messages && depth.bytes < self.low.bytes
+    }
+}
+
+/// A watermark crossed by the depth of a producer queue.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QueueWatermark {
+    /// The queue reached the high watermark.
+    High,
+    /// The queue drained below the low watermark.
+    Low,
 }
 
 /// An inert producer context that can be used when customizations are not
@@ -227,6 +440,14 @@ where
         self.client().context()
     }
 
+    /// Returns the first fatal error raised by this producer, or `None` if no
+    /// fatal error has occurred.
+    ///
+    /// See [`Client::fatal_error`] for details.
+    fn fatal_error(&self) -> Option<(RDKafkaErrorCode, String)> {
+        self.client().fatal_error()
+    }
+
     /// Returns the number of messages that are either waiting to be sent or are
     /// sent but are waiting to be acknowledged.
     fn in_flight_count(&self) -> i32;
@@ -0,0 +1,190 @@
+//! Records that can be sent multiple times.
+//!
+//! See the [`PreparedRecord`] for details.
+
+use std::sync::Arc;
+
+use crate::message::{Header, Headers, Message, OwnedHeaders, ToBytes};
+use crate::producer::{BaseRecord, FutureRecord};
+use crate::util::IntoOpaque;
+
+/// A record whose key, payload and headers are already encoded.
+///
+/// Unlike a [`BaseRecord`] or a [`FutureRecord`], which borrow their key and
+/// payload and own their headers, a `PreparedRecord` shares its encoded data
+/// behind [`Arc`]s, so it can be cheaply cloned and sent any number of times,
+/// to any number of topics, without serializing it again. This is useful to
+/// retry a message, to forward it to a dead letter queue, or to mirror it to
+/// several topics.
+///
+/// A `PreparedRecord` is not tied to a topic; the destination topic is
+/// specified every time a [`BaseRecord`] or a [`FutureRecord`] is derived
+/// from it.
+#[derive(Clone, Debug, Default)]
+pub struct PreparedRecord {
+    partition: Option<i32>,
+    payload: Option<Arc<[u8]>>,
+    key: Option<Arc<[u8]>>,
+    timestamp: Option<i64>,
+    headers: Option<Arc<OwnedHeaders>>,
+}
+
+impl PreparedRecord {
+    /// Creates a new, empty record.
+    pub fn new() -> PreparedRecord {
+        PreparedRecord::default()
+    }
+
+    /// Creates a record with the key, payload, timestamp and headers of the
+    /// message, e.g. to republish a consumed message.
+    pub fn from_message<M: Message>(message: &M) -> PreparedRecord {
+        PreparedRecord {
+            partition: None,
+            payload: message.payload().map(Arc::from),
+            key: message.key().map(Arc::from),
+            timestamp: message.timestamp().to_millis(),
+            headers: message
+                .headers()
+                .map(|headers| Arc::new(copy_headers(headers))),
+        }
+    }
+
+    /// Sets the destination partition of the record.
+    pub fn partition(mut self, partition: i32) -> PreparedRecord {
+        self.partition = Some(partition);
+        self
+    }
+
+    /// Sets the payload of the record, copying its bytes.
+    pub fn payload<P: ToBytes + ?Sized>(mut self, payload: &P) -> PreparedRecord {
+        self.payload = Some(Arc::from(payload.to_bytes()));
+        self
+    }
+
+    /// Sets the key of the record, copying its bytes.
+    pub fn key<K: ToBytes + ?Sized>(mut self, key: &K) -> PreparedRecord {
+        self.key = Some(Arc::from(key.to_bytes()));
+        self
+    }
+
+    /// Sets the timestamp of the record.
+    pub fn timestamp(mut self, timestamp: i64) -> PreparedRecord {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Sets the headers of the record.
+    pub fn headers(mut self, headers: OwnedHeaders) -> PreparedRecord {
+        self.headers = Some(Arc::new(headers));
+        self
+    }
+
+    /// Returns the payload of the record, if any.
+    pub fn payload_bytes(&self) -> Option<&[u8]> {
+        self.payload.as_deref()
+    }
+
+    /// Returns the key of the record, if any.
+    pub fn key_bytes(&self) -> Option<&[u8]> {
+        self.key.as_deref()
+    }
+
+    /// Derives a [`BaseRecord`] to the specified topic.
+    ///
+    /// The key and payload are borrowed from this record. The headers, which
+    /// are handed over to librdkafka when the record is sent, are copied.
+    pub fn base_record<'a>(&'a self, topic: &'a str) -> BaseRecord<'a, [u8], [u8]> {
+        self.base_record_with_opaque(topic, ())
+    }
+
+    /// Like [`PreparedRecord::base_record`], but with the specified delivery
+    /// opaque.
+    pub fn base_record_with_opaque<'a, D: IntoOpaque>(
+        &'a self,
+        topic: &'a str,
+        delivery_opaque: D,
+    ) -> BaseRecord<'a, [u8], [u8], D> {
+        BaseRecord {
+            topic,
+            partition: self.partition,
+            payload: self.payload.as_deref(),
+            key: self.key.as_deref(),
+            timestamp: self.timestamp,
+            headers: self.headers.as_deref().cloned(),
+            delivery_opaque,
+        }
+    }
+
+    /// Derives a [`FutureRecord`] to the specified topic.
+    ///
+    /// See [`PreparedRecord::base_record`] for details.
+    pub fn future_record<'a>(&'a self, topic: &'a str) -> FutureRecord<'a, [u8], [u8]> {
+        FutureRecord {
+            topic,
+            partition: self.partition,
+            payload: self.payload.as_deref(),
+            key: self.key.as_deref(),
+            timestamp: self.timestamp,
+            headers: self.headers.as_deref().cloned(),
+        }
+    }
+}
+
+pub(crate) fn copy_headers<H: Headers>(headers: &H) -> OwnedHeaders {
+    let mut owned = OwnedHeaders::new_with_capacity(headers.count());
+    for i in 0..headers.count() {
+        let header = headers.get(i);
+        owned = owned.insert(Header {
+            key: header.key,
+            value: header.value,
+        });
+    }
+    owned
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{OwnedMessage, Timestamp};
+
+    #[test]
+    fn test_prepared_record() {
+        let headers = OwnedHeaders::new().insert(Header {
+            key: "origin",
+            value: Some("input"),
+        });
+        let message = OwnedMessage::new(
+            Some(b"payload".to_vec()),
+            Some(b"key".to_vec()),
+            "input".into(),
+            Timestamp::CreateTime(42),
+            0,
+            7,
+            Some(headers),
+        );
+        let prepared = PreparedRecord::from_message(&message).partition(3);
+        let copy = prepared.clone();
+        assert_eq!(copy.payload_bytes(), Some(&b"payload"[..]));
+        assert!(std::ptr::eq(
+            prepared.payload_bytes().unwrap(),
+            copy.payload_bytes().unwrap()
+        ));
+
+        for topic in &["retry", "dlq"] {
+            let record = copy.base_record(topic);
+            assert_eq!(record.topic, *topic);
+            assert_eq!(record.partition, Some(3));
+            assert_eq!(record.key, Some(&b"key"[..]));
+            assert_eq!(record.timestamp, Some(42));
+            let headers = record.headers.unwrap();
+            assert_eq!(headers.count(), 1);
+            assert_eq!(headers.get_as::<str>(0).unwrap().value, Some("input"));
+        }
+
+        let prepared = PreparedRecord::new().key("k");
+        let record = prepared.future_record("output");
+        assert_eq!(record.key, Some(&b"k"[..]));
+        assert_eq!(record.payload, None);
+        assert!(record.headers.is_none());
+    }
+}
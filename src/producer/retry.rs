@@ -0,0 +1,157 @@
+//! Retry policies for message production.
+//!
+//! See the [`RetryPolicy`] for details.
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::error::KafkaError;
+
+type Predicate = Arc<dyn Fn(&KafkaError) -> bool + Send + Sync>;
+
+/// Describes how to retry sends that fail with a transient error.
+///
+/// A policy allows a bounded number of attempts, waits for an exponentially
+/// increasing backoff between them, and only retries the errors accepted by
+/// its predicate, which defaults to [`KafkaError::is_retriable`]. It is used
+/// by [`FutureProducer::send_with_retry`].
+///
+/// Note that librdkafka already retries failed produce requests internally,
+/// as configured by the `retries` and `retry.backoff.ms` settings, until
+/// `delivery.timeout.ms` expires. A retry policy covers the errors that are
+/// only reported once those are exhausted, such as message timeouts during a
+/// prolonged broker outage.
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use rdkafka::producer::RetryPolicy;
+///
+/// let policy = RetryPolicy::new()
+///     .max_attempts(5)
+///     .initial_backoff(Duration::from_millis(50))
+///     .max_backoff(Duration::from_secs(2));
+/// assert_eq!(policy.backoff(1), Duration::from_millis(50));
+/// assert_eq!(policy.backoff(2), Duration::from_millis(100));
+/// assert_eq!(policy.backoff(10), Duration::from_secs(2));
+/// ```
+///
+/// [`FutureProducer::send_with_retry`]: crate::producer::FutureProducer::send_with_retry
+#[derive(Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    multiplier: f64,
+    retriable: Predicate,
+}
+
+impl RetryPolicy {
+    /// Creates a policy that makes up to 3 attempts, with a backoff starting
+    /// at 100 milliseconds, doubling after every attempt, and capped at 10
+    /// seconds.
+    pub fn new() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            multiplier: 2.0,
+            retriable: Arc::new(KafkaError::is_retriable),
+        }
+    }
+
+    /// Sets the maximum number of attempts, including the first one. A value
+    /// of zero is treated as one, i.e. as never retrying.
+    pub fn max_attempts(mut self, max_attempts: u32) -> RetryPolicy {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Sets the backoff before the first retry.
+    pub fn initial_backoff(mut self, initial_backoff: Duration) -> RetryPolicy {
+        self.initial_backoff = initial_backoff;
+        self
+    }
+
+    /// Sets the maximum backoff between two attempts.
+    pub fn max_backoff(mut self, max_backoff: Duration) -> RetryPolicy {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Sets the factor by which the backoff grows after every retry.
+    pub fn multiplier(mut self, multiplier: f64) -> RetryPolicy {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// Sets the predicate that decides which errors are retried.
+    pub fn retry_if<F>(mut self, retriable: F) -> RetryPolicy
+    where
+        F: Fn(&KafkaError) -> bool + Send + Sync + 'static,
+    {
+        self.retriable = Arc::new(retriable);
+        self
+    }
+
+    /// Reports whether a send that failed with the error on the specified
+    /// attempt, starting at 1, should be retried.
+    pub fn should_retry(&self, attempt: u32, error: &KafkaError) -> bool {
+        attempt < self.max_attempts && (self.retriable)(error)
+    }
+
+    /// Returns the backoff before retrying a send that failed on the
+    /// specified attempt, starting at 1.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let backoff = self.initial_backoff.as_secs_f64() * self.multiplier.powi(exponent);
+        if backoff.is_finite() && backoff < self.max_backoff.as_secs_f64() {
+            Duration::from_secs_f64(backoff)
+        } else {
+            self.max_backoff
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy::new()
+    }
+}
+
+impl fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("max_attempts", &self.max_attempts)
+            .field("initial_backoff", &self.initial_backoff)
+            .field("max_backoff", &self.max_backoff)
+            .field("multiplier", &self.multiplier)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::RDKafkaErrorCode;
+
+    #[test]
+    fn test_retry_policy() {
+        let policy = RetryPolicy::new();
+        let timed_out = KafkaError::MessageProduction(RDKafkaErrorCode::MessageTimedOut);
+        let too_large = KafkaError::MessageProduction(RDKafkaErrorCode::MessageSizeTooLarge);
+        assert!(policy.should_retry(1, &timed_out));
+        assert!(policy.should_retry(2, &timed_out));
+        assert!(!policy.should_retry(3, &timed_out));
+        assert!(!policy.should_retry(1, &too_large));
+
+        let policy = policy.retry_if(|_| true).max_attempts(0);
+        assert!(!policy.should_retry(1, &too_large));
+
+        let policy = RetryPolicy::new().multiplier(3.0);
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(3), Duration::from_millis(900));
+        assert_eq!(policy.backoff(u32::MAX), Duration::from_secs(10));
+    }
+}
@@ -0,0 +1,291 @@
+//! Topic names resolved from templates.
+//!
+//! See the [`TopicTemplate`] for details.
+
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash};
+use std::sync::{Arc, Mutex};
+
+use crate::error::{KafkaError, KafkaResult};
+
+/// The maximum length of a topic name accepted by Kafka.
+pub const MAX_TOPIC_NAME_LEN: usize = 249;
+
+const DEFAULT_CACHE_CAPACITY: usize = 1024;
+
+/// A set of named parameters, used to resolve a [`TopicTemplate`].
+///
+/// This trait is implemented for maps and slices of string pairs, and can be
+/// implemented by the domain types of an application, e.g. a struct that
+/// identifies a tenant.
+pub trait TopicParams {
+    /// Returns the value of the parameter with the specified name, if any.
+    fn param(&self, name: &str) -> Option<&str>;
+}
+
+impl<K, V, S> TopicParams for HashMap<K, V, S>
+where
+    K: Borrow<str> + Hash + Eq,
+    V: AsRef<str>,
+    S: BuildHasher,
+{
+    fn param(&self, name: &str) -> Option<&str> {
+        self.get(name).map(AsRef::as_ref)
+    }
+}
+
+impl<K: AsRef<str>, V: AsRef<str>> TopicParams for [(K, V)] {
+    fn param(&self, name: &str) -> Option<&str> {
+        self.iter()
+            .find(|(k, _)| k.as_ref() == name)
+            .map(|(_, v)| v.as_ref())
+    }
+}
+
+#[derive(Debug)]
+enum Segment {
+    Literal(String),
+    Param(String),
+}
+
+/// A template for topic names, such as `events.{tenant}.v1`.
+///
+/// Parameters are enclosed in braces, and are replaced with the values of a
+/// [`TopicParams`] when the template is resolved. The literal parts of the
+/// template and the values of the parameters may only contain the
+/// characters allowed in topic names: ASCII alphanumerics, `.`, `_` and `-`.
+///
+/// Resolved topic names are validated once and cached, so that producers that
+/// send to many topics derived from the same template, e.g. one per tenant,
+/// do not need to format and validate the topic name of every record. The
+/// cache holds up to 1024 names by default, and is emptied when it is full.
+///
+/// ```
+/// use rdkafka::producer::TopicTemplate;
+///
+/// let template = TopicTemplate::new("events.{tenant}.v1").unwrap();
+/// let topic = template.resolve(&[("tenant", "acme")][..]).unwrap();
+/// assert_eq!(&*topic, "events.acme.v1");
+/// ```
+#[derive(Debug)]
+pub struct TopicTemplate {
+    template: String,
+    segments: Vec<Segment>,
+    cache: Mutex<HashMap<String, Arc<str>>>,
+    cache_capacity: usize,
+}
+
+impl TopicTemplate {
+    /// Parses the template.
+    ///
+    /// Returns a [`KafkaError::TopicTemplate`] error if the braces of the
+    /// template are unbalanced, if a parameter name is empty or is not made of
+    /// ASCII alphanumerics and `_`, or if the literal parts of the template
+    /// contain characters that are not allowed in topic names.
+    pub fn new(template: &str) -> KafkaResult<TopicTemplate> {
+        let invalid = |reason: &str| {
+            KafkaError::TopicTemplate(format!("invalid template {:?}: {}", template, reason))
+        };
+        let mut segments = Vec::new();
+        let mut rest = template;
+        while !rest.is_empty() {
+            match rest.find('{') {
+                Some(0) => {
+                    let end = rest.find('}').ok_or_else(|| invalid("unclosed brace"))?;
+                    let name = &rest[1..end];
+                    if name.is_empty()
+                        || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                    {
+                        return Err(invalid("invalid parameter name"));
+                    }
+                    segments.push(Segment::Param(name.to_owned()));
+                    rest = &rest[end + 1..];
+                }
+                start => {
+                    let end = start.unwrap_or(rest.len());
+                    let literal = &rest[..end];
+                    if !is_legal(literal) {
+                        return Err(invalid("illegal characters"));
+                    }
+                    segments.push(Segment::Literal(literal.to_owned()));
+                    rest = &rest[end..];
+                }
+            }
+        }
+        Ok(TopicTemplate {
+            template: template.to_owned(),
+            segments,
+            cache: Mutex::new(HashMap::new()),
+            cache_capacity: DEFAULT_CACHE_CAPACITY,
+        })
+    }
+
+    /// Sets the maximum number of resolved topic names to cache. A capacity
+    /// of zero disables the cache.
+    pub fn cache_capacity(mut self, cache_capacity: usize) -> TopicTemplate {
+        self.cache_capacity = cache_capacity;
+        self
+    }
+
+    /// Returns the template, as it was parsed.
+    pub fn as_str(&self) -> &str {
+        &self.template
+    }
+
+    /// Returns the names of the parameters of the template, in order.
+    pub fn params(&self) -> impl Iterator<Item = &str> {
+        self.segments.iter().filter_map(|segment| match segment {
+            Segment::Param(name) => Some(name.as_str()),
+            Segment::Literal(_) => None,
+        })
+    }
+
+    /// Resolves the template with the specified parameters.
+    ///
+    /// Returns a [`KafkaError::TopicTemplate`] error if a parameter is
+    /// missing, if the value of a parameter is empty or contains characters
+    /// that are not allowed in topic names, or if the resolved name is not a
+    /// valid topic name.
+    pub fn resolve<P: TopicParams + ?Sized>(&self, params: &P) -> KafkaResult<Arc<str>> {
+        // Parameter values cannot contain NUL characters, which makes the
+        // joined values a unique cache key.
+        let mut key = String::new();
+        for name in self.params() {
+            let value = params.param(name).ok_or_else(|| {
+                KafkaError::TopicTemplate(format!(
+                    "missing parameter {} for template {:?}",
+                    name, self.template
+                ))
+            })?;
+            if value.is_empty() || !is_legal(value) {
+                return Err(KafkaError::TopicTemplate(format!(
+                    "invalid value {:?} for parameter {} of template {:?}",
+                    value, name, self.template
+                )));
+            }
+            key.push_str(value);
+            key.push('\0');
+        }
+
+        let mut cache = self.cache.lock().unwrap();
+        if let Some(topic) = cache.get(&key) {
+            return Ok(topic.clone());
+        }
+        let topic: Arc<str> = Arc::from(self.format(params));
+        validate_topic_name(&topic)?;
+        if self.cache_capacity > 0 {
+            if cache.len() >= self.cache_capacity {
+                cache.clear();
+            }
+            cache.insert(key, topic.clone());
+        }
+        Ok(topic)
+    }
+
+    fn format<P: TopicParams + ?Sized>(&self, params: &P) -> String {
+        let mut topic = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(literal) => topic.push_str(literal),
+                // Presence was checked by the caller.
+                Segment::Param(name) => topic.push_str(params.param(name).unwrap_or_default()),
+            }
+        }
+        topic
+    }
+}
+
+/// Checks that the name is a valid topic name: a non-empty string of up to
+/// [`MAX_TOPIC_NAME_LEN`] ASCII alphanumerics, `.`, `_` and `-`, other than
+/// `.` and `..`.
+pub fn validate_topic_name(name: &str) -> KafkaResult<()> {
+    let reason = if name.is_empty() {
+        "empty name"
+    } else if name.len() > MAX_TOPIC_NAME_LEN {
+        "name too long"
+    } else if name == "." || name == ".." {
+        "reserved name"
+    } else if !is_legal(name) {
+        "illegal characters"
+    } else {
+        return Ok(());
+    };
+    Err(KafkaError::TopicTemplate(format!(
+        "invalid topic name {:?}: {}",
+        name, reason
+    )))
+}
+
+fn is_legal(s: &str) -> bool {
+    s.chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '_' || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_topic_template() {
+        let template = TopicTemplate::new("events.{tenant}.{kind}-v1").unwrap();
+        assert_eq!(template.params().collect::<Vec<_>>(), ["tenant", "kind"]);
+
+        let mut params = HashMap::new();
+        params.insert("tenant", "acme");
+        params.insert("kind", "orders");
+        let first = template.resolve(&params).unwrap();
+        assert_eq!(&*first, "events.acme.orders-v1");
+        let second = template.resolve(&params).unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+
+        let topic = template
+            .resolve(&[("tenant", "initech"), ("kind", "users")][..])
+            .unwrap();
+        assert_eq!(&*topic, "events.initech.users-v1");
+
+        for params in &[
+            &[("tenant", "acme")][..],
+            &[("tenant", "acme"), ("kind", "")][..],
+            &[("tenant", "a/b"), ("kind", "orders")][..],
+        ] {
+            assert!(matches!(
+                template.resolve(*params),
+                Err(KafkaError::TopicTemplate(_))
+            ));
+        }
+
+        let long = "x".repeat(MAX_TOPIC_NAME_LEN);
+        assert!(TopicTemplate::new("{name}")
+            .unwrap()
+            .resolve(&[("name", long.as_str())][..])
+            .is_ok());
+        assert!(TopicTemplate::new("a{name}")
+            .unwrap()
+            .resolve(&[("name", long.as_str())][..])
+            .is_err());
+    }
+
+    #[test]
+    fn test_topic_template_parsing() {
+        assert_eq!(TopicTemplate::new("plain").unwrap().params().count(), 0);
+        for template in &[
+            "events.{tenant",
+            "events.{}",
+            "events.{a-b}",
+            "a b.{c}",
+            "a}",
+        ] {
+            assert!(
+                matches!(
+                    TopicTemplate::new(template),
+                    Err(KafkaError::TopicTemplate(_))
+                ),
+                "{}",
+                template
+            );
+        }
+        assert!(validate_topic_name("..").is_err());
+        assert!(validate_topic_name("a..b").is_ok());
+    }
+}
@@ -0,0 +1,187 @@
+//! Producer of domain types.
+//!
+//! See the [`TypedProducer`] for details.
+
+use std::marker::PhantomData;
+
+use crate::error::KafkaResult;
+use crate::message::{KeyedBy, OwnedHeaders};
+use crate::producer::{BaseRecord, DefaultProducerContext, ProducerContext, ThreadedProducer};
+use crate::serialization::{BytesSerializer, Serializer};
+
+/// A record for the typed producer.
+///
+/// Like [`BaseRecord`], but its key and payload are domain types, that are
+/// serialized by the [`TypedProducer`] when the record is sent.
+#[derive(Debug)]
+pub struct TypedRecord<'a, K: ?Sized, V: ?Sized> {
+    /// Required destination topic.
+    pub topic: &'a str,
+    /// Optional destination partition.
+    pub partition: Option<i32>,
+    /// Optional payload.
+    pub payload: Option<&'a V>,
+    /// Optional key.
+    pub key: Option<&'a K>,
+    /// Optional timestamp.
+    pub timestamp: Option<i64>,
+    /// Optional message headers.
+    pub headers: Option<OwnedHeaders>,
+}
+
+impl<'a, K: ?Sized, V: ?Sized> TypedRecord<'a, K, V> {
+    /// Creates a new record with the specified topic name.
+    pub fn to(topic: &'a str) -> TypedRecord<'a, K, V> {
+        TypedRecord {
+            topic,
+            partition: None,
+            payload: None,
+            key: None,
+            timestamp: None,
+            headers: None,
+        }
+    }
+
+    /// Sets the destination partition of the record.
+    pub fn partition(mut self, partition: i32) -> TypedRecord<'a, K, V> {
+        self.partition = Some(partition);
+        self
+    }
+
+    /// Sets the destination payload of the record.
+    pub fn payload(mut self, payload: &'a V) -> TypedRecord<'a, K, V> {
+        self.payload = Some(payload);
+        self
+    }
+
+    /// Sets the destination key of the record.
+    pub fn key(mut self, key: &'a K) -> TypedRecord<'a, K, V> {
+        self.key = Some(key);
+        self
+    }
+
+    /// Sets the destination timestamp of the record.
+    pub fn timestamp(mut self, timestamp: i64) -> TypedRecord<'a, K, V> {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Sets the headers of the record.
+    pub fn headers(mut self, headers: OwnedHeaders) -> TypedRecord<'a, K, V> {
+        self.headers = Some(headers);
+        self
+    }
+}
+
+/// A producer of messages whose keys and payloads are domain types.
+///
+/// The keys are serialized with a [`Serializer`] of type `KS`, and the
+/// payloads with a [`Serializer`] of type `VS`. Both default to the
+/// [`BytesSerializer`], which supports the types that implement
+/// [`ToBytes`](crate::message::ToBytes). Payloads are serialized with
+/// [`Serializer::serialize_with_headers`], so that a
+/// [`CodecRegistry`](crate::codec::CodecRegistry) can record their content
+/// type in the headers of the message.
+///
+/// The messages are sent with a [`ThreadedProducer`], which is polled
+/// automatically.
+pub struct TypedProducer<
+    K: ?Sized,
+    V: ?Sized,
+    KS = BytesSerializer,
+    VS = BytesSerializer,
+    C = DefaultProducerContext,
+> where
+    C: ProducerContext + 'static,
+{
+    producer: ThreadedProducer<C>,
+    key_serializer: KS,
+    value_serializer: VS,
+    _types: PhantomData<fn(&K, &V)>,
+}
+
+impl<K, V, KS, VS, C> TypedProducer<K, V, KS, VS, C>
+where
+    K: ?Sized,
+    V: ?Sized,
+    KS: Serializer<K>,
+    VS: Serializer<V>,
+    C: ProducerContext + 'static,
+{
+    /// Creates a typed producer that sends messages with `producer`.
+    pub fn new(
+        producer: ThreadedProducer<C>,
+        key_serializer: KS,
+        value_serializer: VS,
+    ) -> TypedProducer<K, V, KS, VS, C> {
+        TypedProducer {
+            producer,
+            key_serializer,
+            value_serializer,
+            _types: PhantomData,
+        }
+    }
+
+    /// Returns the underlying producer.
+    pub fn producer(&self) -> &ThreadedProducer<C> {
+        &self.producer
+    }
+
+    /// Serializes the key and payload of the record and sends it.
+    ///
+    /// The message is sent with the default delivery opaque of the producer
+    /// context. Serialization errors are returned as is, while enqueuing
+    /// errors are returned like with [`ThreadedProducer::send`], without the
+    /// failed record.
+    pub fn send(&self, record: TypedRecord<'_, K, V>) -> KafkaResult<()>
+    where
+        C::DeliveryOpaque: Default,
+    {
+        let key = match record.key {
+            Some(key) => Some(self.key_serializer.serialize(key)?),
+            None => None,
+        };
+        let (payload, headers) = match record.payload {
+            Some(payload) => {
+                let (payload, headers) = self
+                    .value_serializer
+                    .serialize_with_headers(payload, record.headers)?;
+                (Some(payload), headers)
+            }
+            None => (None, record.headers),
+        };
+        let base_record = BaseRecord::<Vec<u8>, Vec<u8>, C::DeliveryOpaque> {
+            topic: record.topic,
+            partition: record.partition,
+            payload: payload.as_ref(),
+            key: key.as_ref(),
+            timestamp: record.timestamp,
+            headers,
+            delivery_opaque: C::DeliveryOpaque::default(),
+        };
+        self.producer.send(base_record).map_err(|(e, _)| e)
+    }
+
+    /// Sends the record like [`send`](TypedProducer::send), deriving its key
+    /// from its payload with [`KeyedBy`] if the record has no key.
+    ///
+    /// An explicit key set with [`TypedRecord::key`] takes precedence over
+    /// the derived one.
+    pub fn send_keyed(&self, record: TypedRecord<'_, K, V>) -> KafkaResult<()>
+    where
+        K: Sized,
+        V: KeyedBy<Key = K>,
+        C::DeliveryOpaque: Default,
+    {
+        match (record.key, record.payload) {
+            (None, Some(payload)) => {
+                let key = payload.key();
+                self.send(TypedRecord {
+                    key: Some(&key),
+                    ..record
+                })
+            }
+            _ => self.send(record),
+        }
+    }
+}
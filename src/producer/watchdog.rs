@@ -0,0 +1,123 @@
+//! Detection of producers that are never polled.
+//!
+//! A producer that is never polled or flushed never serves its delivery
+//! callbacks, and loses all of its queued messages when it is dropped. The
+//! [`Watchdog`] of a producer runs a background thread that notices when
+//! messages keep being sent without the producer being polled, and reports it
+//! once through [`ProducerContext::queue_stalled`].
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::log::warn;
+use crate::producer::ProducerContext;
+
+/// The longest interval between two checks of the watchdog thread.
+const MAX_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+struct Probe {
+    threshold: Duration,
+    created_at: Instant,
+    // Milliseconds since `created_at`.
+    last_activity_ms: AtomicU64,
+    sent_since_activity: AtomicU64,
+    fired: AtomicBool,
+    // Cleared when the producer is dropped, so that the thread, which may
+    // hold the probe until its next check, does not keep the context alive.
+    notify: Mutex<Option<Notify>>,
+}
+
+type Notify = Box<dyn Fn(u64, Duration) + Send + Sync>;
+
+impl Probe {
+    fn elapsed_ms(&self) -> u64 {
+        self.created_at.elapsed().as_millis() as u64
+    }
+
+    /// Returns whether the probe fired, i.e. whether the thread can stop.
+    fn check(&self) -> bool {
+        let sent = self.sent_since_activity.load(Ordering::Relaxed);
+        let idle = self
+            .elapsed_ms()
+            .saturating_sub(self.last_activity_ms.load(Ordering::Relaxed));
+        let idle = Duration::from_millis(idle);
+        if sent > 0 && idle > self.threshold {
+            self.fired.store(true, Ordering::Relaxed);
+            if let Some(notify) = &*self.notify.lock().unwrap() {
+                notify(sent, idle);
+            }
+        }
+        self.fired.load(Ordering::Relaxed)
+    }
+}
+
+/// Tracks the sends and polls of a producer for its watchdog thread.
+///
+/// The thread only holds a weak reference to the state of the watchdog, and
+/// exits at its next check once the producer is dropped.
+pub(crate) struct Watchdog {
+    probe: Arc<Probe>,
+}
+
+impl Watchdog {
+    /// Starts the watchdog of a producer with the specified context, unless
+    /// [`ProducerContext::watchdog_threshold`] disables it.
+    pub(crate) fn start<C: ProducerContext + 'static>(context: &Arc<C>) -> Option<Watchdog> {
+        let threshold = context.watchdog_threshold()?;
+        let context = Arc::clone(context);
+        let notify: Notify = Box::new(move |sent, idle| context.queue_stalled(sent, idle));
+        let probe = Arc::new(Probe {
+            threshold,
+            created_at: Instant::now(),
+            last_activity_ms: AtomicU64::new(0),
+            sent_since_activity: AtomicU64::new(0),
+            fired: AtomicBool::new(false),
+            notify: Mutex::new(Some(notify)),
+        });
+        let interval = threshold.min(MAX_CHECK_INTERVAL);
+        let weak = Arc::downgrade(&probe);
+        let spawned = thread::Builder::new()
+            .name("rdkafka-watchdog".into())
+            .spawn(move || watch(weak, interval));
+        match spawned {
+            Ok(_) => Some(Watchdog { probe }),
+            Err(e) => {
+                warn!("Failed to start producer watchdog: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Records a message enqueued by the producer.
+    pub(crate) fn record_send(&self) {
+        self.probe
+            .sent_since_activity
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records a poll or flush of the producer.
+    pub(crate) fn record_poll(&self) {
+        self.probe
+            .last_activity_ms
+            .store(self.probe.elapsed_ms(), Ordering::Relaxed);
+        self.probe.sent_since_activity.store(0, Ordering::Relaxed);
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        *self.probe.notify.lock().unwrap() = None;
+    }
+}
+
+fn watch(probe: Weak<Probe>, interval: Duration) {
+    loop {
+        thread::sleep(interval);
+        match probe.upgrade() {
+            Some(probe) if !probe.check() => (),
+            _ => return,
+        }
+    }
+}
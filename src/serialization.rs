@@ -0,0 +1,146 @@
+//! Serialization of message keys and payloads.
+//!
+//! A [`Serializer`] converts values of a domain type into the bytes of a
+//! message key or payload, and a [`Deserializer`] converts them back. They are
+//! used by the [`TypedProducer`] and the [`TypedConsumer`] to produce and
+//! consume domain types directly.
+//!
+//! The [`BytesSerializer`] and the [`BytesDeserializer`] are the trivial
+//! implementations, based on the [`ToBytes`] and [`FromBytes`] traits. The
+//! [`JsonSerializer`] and the [`JsonDeserializer`] support any type that
+//! implements the corresponding [serde] trait.
+//!
+//! [`TypedConsumer`]: crate::consumer::TypedConsumer
+//! [`TypedProducer`]: crate::producer::TypedProducer
+//! [serde]: https://serde.rs
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::error::{KafkaError, KafkaResult};
+use crate::message::{BorrowedHeaders, FromBytes, OwnedHeaders, ToBytes};
+
+/// Converts values of type `T` into bytes.
+pub trait Serializer<T: ?Sized>: Send + Sync {
+    /// Serializes the value.
+    fn serialize(&self, value: &T) -> KafkaResult<Vec<u8>>;
+
+    /// Serializes the payload of a message with the specified headers, and
+    /// returns the headers the message should be sent with.
+    ///
+    /// This allows serializers to pick the encoding from the headers, or to
+    /// record it in them, like the [`CodecRegistry`]. The default
+    /// implementation calls [`Serializer::serialize`] and returns the headers
+    /// unchanged.
+    ///
+    /// [`CodecRegistry`]: crate::codec::CodecRegistry
+    fn serialize_with_headers(
+        &self,
+        value: &T,
+        headers: Option<OwnedHeaders>,
+    ) -> KafkaResult<(Vec<u8>, Option<OwnedHeaders>)> {
+        Ok((self.serialize(value)?, headers))
+    }
+}
+
+/// Converts bytes into values of type `T`.
+pub trait Deserializer<T>: Send + Sync {
+    /// Deserializes the bytes.
+    fn deserialize(&self, bytes: &[u8]) -> KafkaResult<T>;
+
+    /// Deserializes the payload of a message with the specified headers.
+    ///
+    /// The default implementation ignores the headers and calls
+    /// [`Deserializer::deserialize`].
+    fn deserialize_with_headers(
+        &self,
+        bytes: &[u8],
+        _headers: Option<&BorrowedHeaders>,
+    ) -> KafkaResult<T> {
+        self.deserialize(bytes)
+    }
+}
+
+/// A [`Serializer`] for the types that implement [`ToBytes`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BytesSerializer;
+
+impl<T: ToBytes + ?Sized> Serializer<T> for BytesSerializer {
+    fn serialize(&self, value: &T) -> KafkaResult<Vec<u8>> {
+        Ok(value.to_bytes().to_vec())
+    }
+}
+
+/// A [`Deserializer`] for the owned counterparts of the types that implement
+/// [`FromBytes`], i.e. `Vec<u8>` and `String`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BytesDeserializer;
+
+impl Deserializer<Vec<u8>> for BytesDeserializer {
+    fn deserialize(&self, bytes: &[u8]) -> KafkaResult<Vec<u8>> {
+        Ok(bytes.to_vec())
+    }
+}
+
+impl Deserializer<String> for BytesDeserializer {
+    fn deserialize(&self, bytes: &[u8]) -> KafkaResult<String> {
+        str::from_bytes(bytes)
+            .map(str::to_owned)
+            .map_err(|e| KafkaError::PayloadDecoding(e.to_string()))
+    }
+}
+
+/// A [`Serializer`] that encodes values as JSON, based on [`serde_json`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JsonSerializer;
+
+impl<T: Serialize + ?Sized> Serializer<T> for JsonSerializer {
+    fn serialize(&self, value: &T) -> KafkaResult<Vec<u8>> {
+        serde_json::to_vec(value).map_err(|e| KafkaError::PayloadEncoding(e.to_string()))
+    }
+}
+
+/// A [`Deserializer`] that decodes values from JSON, based on [`serde_json`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JsonDeserializer;
+
+impl<T: DeserializeOwned> Deserializer<T> for JsonDeserializer {
+    fn deserialize(&self, bytes: &[u8]) -> KafkaResult<T> {
+        serde_json::from_slice(bytes).map_err(|e| KafkaError::PayloadDecoding(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bytes_serialization() {
+        assert_eq!(BytesSerializer.serialize("key").unwrap(), b"key");
+        assert_eq!(BytesSerializer.serialize(&b"\xff"[..]).unwrap(), b"\xff");
+        let string: String = BytesDeserializer.deserialize(b"key").unwrap();
+        assert_eq!(string, "key");
+        let bytes: Vec<u8> = BytesDeserializer.deserialize(b"\xff").unwrap();
+        assert_eq!(bytes, b"\xff");
+        assert!(matches!(
+            Deserializer::<String>::deserialize(&BytesDeserializer, b"\xff"),
+            Err(KafkaError::PayloadDecoding(_))
+        ));
+    }
+
+    #[test]
+    fn test_json_serialization() {
+        use std::collections::HashMap;
+
+        let mut value = HashMap::new();
+        value.insert("a".to_string(), vec![1, 2]);
+        let bytes = JsonSerializer.serialize(&value).unwrap();
+        assert_eq!(bytes, br#"{"a":[1,2]}"#);
+        let decoded: HashMap<String, Vec<u32>> = JsonDeserializer.deserialize(&bytes).unwrap();
+        assert_eq!(decoded["a"], [1, 2]);
+        assert!(matches!(
+            Deserializer::<u32>::deserialize(&JsonDeserializer, b"{"),
+            Err(KafkaError::PayloadDecoding(_))
+        ));
+    }
+}
@@ -344,6 +344,79 @@ pub struct ExactlyOnceSemantics {
     pub epoch_cnt: i64,
 }
 
+/// The connection state of a broker.
+///
+/// This is a subset of the [`Broker`] statistics, which is also tracked by
+/// clients for [`Client::broker_states`].
+///
+/// [`Client::broker_states`]: crate::client::Client::broker_states
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BrokerState {
+    /// The broker hostname, port, and ID, in the form `HOSTNAME:PORT/ID`.
+    pub name: String,
+    /// The broker ID (-1 for bootstraps).
+    pub nodeid: i32,
+    /// The broker state (INIT, DOWN, CONNECT, AUTH, APIVERSION_QUERY,
+    /// AUTH_HANDSHAKE, UP, UPDATE).
+    pub state: String,
+    /// The time since the last broker state change, in microseconds.
+    pub stateage: i64,
+    /// The number of connection attempts, including successful and failed
+    /// attempts, and name resolution failures.
+    pub connects: Option<i64>,
+    /// The number of disconnections, whether triggered by the broker, the
+    /// network, the load balancer, or something else.
+    pub disconnects: Option<i64>,
+}
+
+impl BrokerState {
+    /// Reports whether the client is connected to the broker.
+    pub fn is_up(&self) -> bool {
+        self.state == "UP"
+    }
+}
+
+impl From<&Broker> for BrokerState {
+    fn from(broker: &Broker) -> BrokerState {
+        BrokerState {
+            name: broker.name.clone(),
+            nodeid: broker.nodeid,
+            state: broker.state.clone(),
+            stateage: broker.stateage,
+            connects: broker.connects,
+            disconnects: broker.disconnects,
+        }
+    }
+}
+
+impl Statistics {
+    /// Returns the connection state of every broker, sorted by name.
+    pub fn broker_states(&self) -> Vec<BrokerState> {
+        let mut states = self
+            .brokers
+            .values()
+            .map(BrokerState::from)
+            .collect::<Vec<_>>();
+        states.sort_by(|a, b| a.name.cmp(&b.name));
+        states
+    }
+}
+
+/// The broker states extracted from the statistics JSON, without decoding the
+/// rest of the statistics.
+#[derive(Deserialize)]
+pub(crate) struct BrokerStates {
+    brokers: HashMap<String, BrokerState>,
+}
+
+impl BrokerStates {
+    pub(crate) fn to_sorted_vec(&self) -> Vec<BrokerState> {
+        let mut states = self.brokers.values().cloned().collect::<Vec<_>>();
+        states.sort_by(|a, b| a.name.cmp(&b.name));
+        states
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use maplit::hashmap;
@@ -367,6 +440,12 @@ mod tests {
 
         assert_eq!(stats.brokers.len(), 1);
 
+        let broker_states = stats.broker_states();
+        assert_eq!(broker_states.len(), 1);
+        assert!(broker_states[0].is_up());
+        let raw_broker_states: BrokerStates = serde_json::from_str(EXAMPLE).unwrap();
+        assert_eq!(raw_broker_states.to_sorted_vec(), broker_states);
+
         let broker = stats.brokers.values().into_iter().collect::<Vec<_>>()[0];
 
         assert_eq!(
@@ -0,0 +1,282 @@
+//! Exactly-once consume-transform-produce processing.
+//!
+//! The [`ExactlyOnceProcessor`] reads batches of messages with a consumer,
+//! hands each batch to a user-provided closure that produces the resulting
+//! messages, and commits both the produced messages and the consumer offsets
+//! in a single transaction. Downstream consumers with `isolation.level` set to
+//! `read_committed` will see the output of each batch exactly once, even if
+//! the processor crashes or a transaction fails halfway.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//!
+//! use rdkafka::config::ClientConfig;
+//! use rdkafka::consumer::{BaseConsumer, Consumer};
+//! use rdkafka::message::Message;
+//! use rdkafka::producer::{BaseProducer, BaseRecord};
+//! use rdkafka::transactions::ExactlyOnceProcessor;
+//!
+//! let consumer: BaseConsumer = ClientConfig::new()
+//!     .set("bootstrap.servers", "localhost:9092")
+//!     .set("group.id", "uppercase")
+//!     .set("enable.auto.commit", "false")
+//!     .create()
+//!     .unwrap();
+//! consumer.subscribe(&["input"]).unwrap();
+//! let producer: BaseProducer = ClientConfig::new()
+//!     .set("bootstrap.servers", "localhost:9092")
+//!     .set("transactional.id", "uppercase-0")
+//!     .create()
+//!     .unwrap();
+//!
+//! let processor = ExactlyOnceProcessor::new(consumer, producer, Duration::from_secs(10))
+//!     .unwrap()
+//!     .batch_size(100);
+//! loop {
+//!     processor
+//!         .process_batch(|messages, producer| {
+//!             for message in messages {
+//!                 let payload = message.payload().unwrap_or(&[]).to_ascii_uppercase();
+//!                 producer
+//!                     .send(BaseRecord::<[u8], _>::to("output").payload(&payload[..]))
+//!                     .map_err(|(e, _)| e)?;
+//!             }
+//!             Ok::<_, rdkafka::error::KafkaError>(())
+//!         })
+//!         .unwrap();
+//! }
+//! ```
+
+use std::cmp;
+use std::collections::HashMap;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rdkafka_sys::RDKafkaConfRes;
+
+use crate::consumer::{BaseConsumer, Consumer, ConsumerContext, DefaultConsumerContext};
+use crate::error::{KafkaError, KafkaResult};
+use crate::log::warn;
+use crate::message::{BorrowedMessage, Message};
+use crate::producer::{BaseProducer, DefaultProducerContext, Producer, ProducerContext};
+use crate::topic_partition_list::{Offset, TopicPartitionList};
+use crate::util::Timeout;
+
+/// The delay before the first retry of a transaction commit, which doubles
+/// with every retry.
+const COMMIT_RETRY_INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+
+/// The maximum delay between retries of a transaction commit.
+const COMMIT_RETRY_MAX_BACKOFF: Duration = Duration::from_secs(1);
+
+/// A processor that transforms messages from a consumer into messages sent
+/// by a transactional producer, with exactly-once semantics.
+///
+/// The consumer must have a `group.id`, and should have `enable.auto.commit`
+/// set to `false`, since offsets are committed as part of the transactions.
+/// The producer must have a `transactional.id`.
+///
+/// See the [module-level documentation](crate::transactions) for an example.
+pub struct ExactlyOnceProcessor<C = DefaultConsumerContext, P = DefaultProducerContext>
+where
+    C: ConsumerContext,
+    P: ProducerContext,
+{
+    consumer: BaseConsumer<C>,
+    producer: BaseProducer<P>,
+    timeout: Duration,
+    batch_size: usize,
+    batch_timeout: Duration,
+}
+
+impl<C, P> ExactlyOnceProcessor<C, P>
+where
+    C: ConsumerContext,
+    P: ProducerContext,
+{
+    /// Creates a new processor, initializing the transactions of the
+    /// producer.
+    ///
+    /// The `timeout` applies to each of the transactional operations
+    /// performed by the processor, and bounds the total time spent retrying
+    /// the commit of a transaction.
+    pub fn new(
+        consumer: BaseConsumer<C>,
+        producer: BaseProducer<P>,
+        timeout: Duration,
+    ) -> KafkaResult<ExactlyOnceProcessor<C, P>> {
+        if consumer.group_metadata().is_none() {
+            return Err(KafkaError::ClientConfig(
+                RDKafkaConfRes::RD_KAFKA_CONF_INVALID,
+                "exactly-once processing requires a consumer group".into(),
+                "group.id".into(),
+                "".into(),
+            ));
+        }
+        producer.init_transactions(timeout)?;
+        Ok(ExactlyOnceProcessor {
+            consumer,
+            producer,
+            timeout,
+            batch_size: 1000,
+            batch_timeout: Duration::from_secs(1),
+        })
+    }
+
+    /// Sets the maximum number of messages in a batch. The default is 1000.
+    pub fn batch_size(mut self, batch_size: usize) -> ExactlyOnceProcessor<C, P> {
+        self.batch_size = cmp::max(batch_size, 1);
+        self
+    }
+
+    /// Sets how long to wait for messages to fill up a batch. The default is
+    /// one second.
+    pub fn batch_timeout(mut self, batch_timeout: Duration) -> ExactlyOnceProcessor<C, P> {
+        self.batch_timeout = batch_timeout;
+        self
+    }
+
+    /// Returns the underlying consumer.
+    pub fn consumer(&self) -> &BaseConsumer<C> {
+        &self.consumer
+    }
+
+    /// Returns the underlying producer.
+    pub fn producer(&self) -> &BaseProducer<P> {
+        &self.producer
+    }
+
+    /// Processes a batch of messages in a transaction.
+    ///
+    /// Waits for up to the batch size of messages, or for the batch timeout,
+    /// whichever comes first, and passes the messages to `f` along with the
+    /// producer that the resulting messages must be sent with. If `f`
+    /// succeeds, the next offsets of the consumed partitions are added to the
+    /// transaction, which is then committed. Returns the number of messages
+    /// processed, which is zero if no messages arrived. Consumer errors that
+    /// are not fatal, such as [`KafkaError::PartitionEOF`] when
+    /// `enable.partition.eof` is set, are logged and do not interrupt the
+    /// batch.
+    ///
+    /// If `f` fails, or the transaction fails with an error that requires it
+    /// to be aborted, the transaction is aborted, the consumer is rewound to
+    /// the beginning of the batch so that its messages are processed again by
+    /// the next call, and the error is returned. Retriable errors while
+    /// committing the transaction are retried with an exponential backoff,
+    /// for up to the timeout of the processor in total; if the commit still
+    /// fails, the transaction is aborted too. Fatal errors, such as the
+    /// producer being fenced by another instance with the same
+    /// `transactional.id`, are returned immediately; after a fatal error the
+    /// processor is no longer usable and must be recreated.
+    pub fn process_batch<F, E>(&self, mut f: F) -> Result<usize, E>
+    where
+        F: FnMut(&[BorrowedMessage<'_>], &BaseProducer<P>) -> Result<(), E>,
+        E: From<KafkaError>,
+    {
+        let messages = self.poll_batch()?;
+        if messages.is_empty() {
+            return Ok(0);
+        }
+
+        self.producer.begin_transaction()?;
+        if let Err(e) = f(&messages, &self.producer) {
+            self.abort(&messages)?;
+            return Err(e);
+        }
+        match self.commit(&messages) {
+            Ok(()) => Ok(messages.len()),
+            // A retriable error means that the retries were exhausted.
+            Err(KafkaError::Transaction(e)) if e.txn_requires_abort() || e.is_retriable() => {
+                self.abort(&messages)?;
+                Err(KafkaError::Transaction(e).into())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn poll_batch(&self) -> KafkaResult<Vec<BorrowedMessage<'_>>> {
+        let start = Instant::now();
+        let mut messages = Vec::new();
+        while messages.len() < self.batch_size {
+            let remaining = self.batch_timeout.saturating_sub(start.elapsed());
+            match self.consumer.poll(remaining) {
+                Some(Ok(message)) => messages.push(message),
+                Some(Err(KafkaError::PartitionEOF(_))) => (),
+                Some(Err(e)) if !e.is_fatal() => {
+                    warn!("Ignoring consumer error while polling a batch: {}", e);
+                }
+                Some(Err(e)) => {
+                    self.rewind(&messages)?;
+                    return Err(e);
+                }
+                None if remaining == Duration::from_secs(0) => break,
+                None => (),
+            }
+        }
+        Ok(messages)
+    }
+
+    fn commit(&self, messages: &[BorrowedMessage<'_>]) -> KafkaResult<()> {
+        let mut offsets = TopicPartitionList::new();
+        for (&(topic, partition), &(_, last)) in &batch_offsets(messages) {
+            offsets.add_partition_offset(topic, partition, Offset::Offset(last + 1))?;
+        }
+        let cgm = self
+            .consumer
+            .group_metadata()
+            .expect("group metadata checked on creation");
+        self.producer
+            .send_offsets_to_transaction(&offsets, &cgm, self.timeout)?;
+        let deadline = Instant::now() + self.timeout;
+        let mut backoff = COMMIT_RETRY_INITIAL_BACKOFF;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match self.producer.commit_transaction(remaining) {
+                Err(KafkaError::Transaction(e))
+                    if e.is_retriable()
+                        && !e.txn_requires_abort()
+                        && Instant::now() + backoff < deadline =>
+                {
+                    thread::sleep(backoff);
+                    backoff = cmp::min(backoff * 2, COMMIT_RETRY_MAX_BACKOFF);
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn abort(&self, messages: &[BorrowedMessage<'_>]) -> KafkaResult<()> {
+        self.producer.abort_transaction(self.timeout)?;
+        self.rewind(messages)
+    }
+
+    /// Seeks the consumer back to the first message of the batch in each
+    /// partition.
+    fn rewind(&self, messages: &[BorrowedMessage<'_>]) -> KafkaResult<()> {
+        for (&(topic, partition), &(first, _)) in &batch_offsets(messages) {
+            self.consumer.seek(
+                topic,
+                partition,
+                Offset::Offset(first),
+                Timeout::from(self.timeout),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the first and last offsets of the messages of each partition.
+fn batch_offsets<'a>(messages: &'a [BorrowedMessage<'_>]) -> HashMap<(&'a str, i32), (i64, i64)> {
+    let mut offsets = HashMap::new();
+    for message in messages {
+        let offset = message.offset();
+        offsets
+            .entry((message.topic(), message.partition()))
+            .and_modify(|(first, last): &mut (i64, i64)| {
+                *first = cmp::min(*first, offset);
+                *last = cmp::max(*last, offset);
+            })
+            .or_insert((offset, offset));
+    }
+    offsets
+}
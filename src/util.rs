@@ -3,23 +3,28 @@
 use std::ffi::CStr;
 use std::fmt;
 use std::future::Future;
+use std::io;
 use std::ops::Deref;
 use std::os::raw::c_char;
 use std::os::raw::c_void;
+#[cfg(any(feature = "async-std", feature = "smol"))]
+use std::panic::{self, AssertUnwindSafe};
 use std::ptr;
 use std::ptr::NonNull;
 use std::slice;
-use std::sync::Arc;
-#[cfg(feature = "naive-runtime")]
-use std::thread;
+use std::sync::{mpsc, Arc};
+use std::thread::{self, JoinHandle};
+#[cfg(feature = "smol")]
+use std::time::Instant;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-#[cfg(feature = "naive-runtime")]
 use futures_channel::oneshot;
-#[cfg(feature = "naive-runtime")]
-use futures_util::future::{FutureExt, Map};
+#[cfg(any(feature = "naive-runtime", feature = "smol"))]
+use futures_util::future::Map;
+use futures_util::future::{BoxFuture, FutureExt};
 
-use crate::log::trace;
+use crate::error::{KafkaError, KafkaResult};
+use crate::log::{trace, warn};
 
 use rdkafka_sys as rdsys;
 
@@ -335,6 +340,116 @@ where
     }
 }
 
+/// The configuration of a thread owned by the crate that serves callbacks.
+///
+/// Naming the thread makes it easy to spot in profilers and debuggers, and
+/// pinning it to specific CPUs can reduce the jitter of latency-sensitive
+/// applications.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CallbackThread {
+    name: String,
+    cpu_affinity: Option<Vec<usize>>,
+}
+
+impl CallbackThread {
+    /// Creates the configuration of a thread with the specified name.
+    pub fn new<S: Into<String>>(name: S) -> CallbackThread {
+        CallbackThread {
+            name: name.into(),
+            cpu_affinity: None,
+        }
+    }
+
+    /// Restricts the thread to run on the specified CPUs.
+    ///
+    /// CPU affinity is only supported on Linux. On other platforms, and if
+    /// the affinity cannot be set, e.g. because a CPU does not exist, the
+    /// thread fails to start, and so does the client that owns it.
+    pub fn cpu_affinity<I>(mut self, cpus: I) -> CallbackThread
+    where
+        I: IntoIterator<Item = usize>,
+    {
+        self.cpu_affinity = Some(cpus.into_iter().collect());
+        self
+    }
+
+    /// Returns the name of the thread.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the CPUs that the thread is restricted to, if any.
+    pub fn cpus(&self) -> Option<&[usize]> {
+        self.cpu_affinity.as_deref()
+    }
+
+    /// Spawns a thread with this configuration.
+    ///
+    /// The affinity can only be set from the thread itself, so the thread
+    /// reports whether it succeeded before running `f`. If it failed, `f` is
+    /// not run and the error is returned.
+    pub(crate) fn spawn<F>(&self, f: F) -> io::Result<JoinHandle<()>>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let cpu_affinity = match &self.cpu_affinity {
+            Some(cpus) => cpus.clone(),
+            None => return thread::Builder::new().name(self.name.clone()).spawn(f),
+        };
+        let (tx, rx) = mpsc::channel();
+        let handle = thread::Builder::new()
+            .name(self.name.clone())
+            .spawn(move || {
+                let affinity = set_cpu_affinity(&cpu_affinity);
+                let failed = affinity.is_err();
+                let _ = tx.send(affinity);
+                if !failed {
+                    f()
+                }
+            })?;
+        // The channel is only closed early if the thread panicked.
+        let affinity = rx
+            .recv()
+            .unwrap_or_else(|_| Err(io::ErrorKind::Other.into()));
+        match affinity {
+            Ok(()) => Ok(handle),
+            Err(e) => {
+                let _ = handle.join();
+                Err(e)
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn set_cpu_affinity(cpus: &[usize]) -> io::Result<()> {
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_ZERO(&mut set);
+        for &cpu in cpus {
+            if cpu >= libc::CPU_SETSIZE as usize {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("CPU {} is out of range", cpu),
+                ));
+            }
+            libc::CPU_SET(cpu, &mut set);
+        }
+        if libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_cpu_affinity(_: &[usize]) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "CPU affinity is not supported on this platform",
+    ))
+}
+
 /// An abstraction over asynchronous runtimes.
 ///
 /// There are several asynchronous runtimes available for Rust. By default
@@ -366,6 +481,43 @@ pub trait AsyncRuntime: Send + Sync + 'static {
 
     /// Constructs a future that will resolve after `duration` has elapsed.
     fn delay_for(duration: Duration) -> Self::Delay;
+
+    /// Runs a blocking function without stalling the asynchronous tasks of
+    /// the runtime, e.g. on a dedicated thread pool.
+    ///
+    /// The returned future resolves to the result of the function, or to
+    /// `None` if the function panicked. The default implementation runs the
+    /// function on a new thread.
+    fn spawn_blocking<F, T>(f: F) -> BoxFuture<'static, Option<T>>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        let spawned = thread::Builder::new()
+            .name("rdkafka-blocking".into())
+            .spawn(move || {
+                let _ = tx.send(f());
+            });
+        if let Err(e) = spawned {
+            warn!("Failed to spawn blocking thread: {}", e);
+        }
+        rx.map(Result::ok).boxed()
+    }
+}
+
+/// Runs a blocking librdkafka call with [`AsyncRuntime::spawn_blocking`].
+///
+/// Returns [`KafkaError::Canceled`] if the call panicked.
+pub(crate) async fn run_blocking<R, F, T>(f: F) -> KafkaResult<T>
+where
+    R: AsyncRuntime,
+    F: FnOnce() -> KafkaResult<T> + Send + 'static,
+    T: Send + 'static,
+{
+    R::spawn_blocking(f)
+        .await
+        .unwrap_or(Err(KafkaError::Canceled))
 }
 
 /// The default [`AsyncRuntime`] used when one is not explicitly specified.
@@ -459,6 +611,75 @@ impl AsyncRuntime for TokioRuntime {
     fn delay_for(duration: Duration) -> Self::Delay {
         tokio::time::sleep(duration)
     }
+
+    fn spawn_blocking<F, T>(f: F) -> BoxFuture<'static, Option<T>>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        tokio::task::spawn_blocking(f).map(Result::ok).boxed()
+    }
+}
+
+/// An [`AsyncRuntime`] implementation backed by [async-std](async_std).
+#[cfg(feature = "async-std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async-std")))]
+pub struct AsyncStdRuntime;
+
+#[cfg(feature = "async-std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async-std")))]
+impl AsyncRuntime for AsyncStdRuntime {
+    type Delay = BoxFuture<'static, ()>;
+
+    fn spawn<T>(task: T)
+    where
+        T: Future<Output = ()> + Send + 'static,
+    {
+        async_std::task::spawn(task);
+    }
+
+    fn delay_for(duration: Duration) -> Self::Delay {
+        async_std::task::sleep(duration).boxed()
+    }
+
+    fn spawn_blocking<F, T>(f: F) -> BoxFuture<'static, Option<T>>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        async_std::task::spawn_blocking(move || panic::catch_unwind(AssertUnwindSafe(f)).ok())
+            .boxed()
+    }
+}
+
+/// An [`AsyncRuntime`] implementation backed by [smol](smol).
+#[cfg(feature = "smol")]
+#[cfg_attr(docsrs, doc(cfg(feature = "smol")))]
+pub struct SmolRuntime;
+
+#[cfg(feature = "smol")]
+#[cfg_attr(docsrs, doc(cfg(feature = "smol")))]
+impl AsyncRuntime for SmolRuntime {
+    type Delay = Map<smol::Timer, fn(Instant)>;
+
+    fn spawn<T>(task: T)
+    where
+        T: Future<Output = ()> + Send + 'static,
+    {
+        smol::spawn(task).detach();
+    }
+
+    fn delay_for(duration: Duration) -> Self::Delay {
+        smol::Timer::after(duration).map(|_| ())
+    }
+
+    fn spawn_blocking<F, T>(f: F) -> BoxFuture<'static, Option<T>>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        smol::unblock(move || panic::catch_unwind(AssertUnwindSafe(f)).ok()).boxed()
+    }
 }
 
 #[cfg(test)]
@@ -471,4 +692,39 @@ mod tests {
         let (version_int, _) = get_rdkafka_version();
         assert_eq!(rdk_version, version_int);
     }
+
+    #[test]
+    fn test_callback_thread() {
+        let config = CallbackThread::new("callback thread");
+        assert_eq!(config.cpus(), None);
+        let result = config
+            .spawn(|| assert_eq!(thread::current().name(), Some("callback thread")))
+            .unwrap()
+            .join();
+        assert!(result.is_ok());
+
+        let config = config.cpu_affinity(vec![usize::MAX]);
+        assert_eq!(config.cpus(), Some(&[usize::MAX][..]));
+        assert!(config.spawn(|| panic!("must not run")).is_err());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_callback_thread_cpu_affinity() {
+        let config = CallbackThread::new("callback thread").cpu_affinity(vec![0]);
+        assert!(config.spawn(|| ()).unwrap().join().is_ok());
+
+        let config = CallbackThread::new("callback thread").cpu_affinity(vec![usize::MAX]);
+        let error = config.spawn(|| panic!("must not run")).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[cfg(feature = "async-std")]
+    #[test]
+    fn test_async_std_spawn_blocking() {
+        let result = async_std::task::block_on(AsyncStdRuntime::spawn_blocking(|| 42));
+        assert_eq!(result, Some(42));
+        let result = async_std::task::block_on(AsyncStdRuntime::spawn_blocking(|| panic!()));
+        assert_eq!(result, None::<()>);
+    }
 }
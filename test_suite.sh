@@ -32,6 +32,11 @@ git submodule update --init
 cargo test --no-run
 docker-compose up -d
 
+# The Toxiproxy server started by docker-compose reaches the broker through
+# the network of the containers.
+export TOXIPROXY_HOST=${TOXIPROXY_HOST:-localhost:8474}
+export TOXIPROXY_UPSTREAM=${TOXIPROXY_UPSTREAM:-kafka:9092}
+
 # Run unit tests.
 
 echo_good "*** Run unit tests ***"
@@ -9,7 +9,7 @@ use futures::stream::StreamExt;
 use maplit::hashmap;
 use tokio::time::{self, Duration};
 
-use rdkafka::consumer::{CommitMode, Consumer, ConsumerContext, StreamConsumer};
+use rdkafka::consumer::{CommitMode, Consumer, ConsumerContext, ConsumerEvent, StreamConsumer};
 use rdkafka::error::KafkaError;
 use rdkafka::topic_partition_list::{Offset, TopicPartitionList};
 use rdkafka::util::current_time_millis;
@@ -190,6 +190,82 @@ async fn test_produce_consume_base_unassign() {
     assert_eq!(assignments.count(), 0);
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_stream_consumer_partition_eof_event() {
+    let _r = env_logger::try_init();
+
+    let topic_name = rand_test_topic();
+    populate_topic(&topic_name, 10, &value_fn, &key_fn, Some(0), None).await;
+    let consumer = create_stream_consumer(
+        &rand_test_group(),
+        Some(hashmap! { "enable.partition.eof" => "true" }),
+    );
+    consumer.subscribe(&[topic_name.as_str()]).unwrap();
+
+    let mut received = 0;
+    loop {
+        let event = time::timeout(Duration::from_secs(10), consumer.recv_event())
+            .await
+            .expect("Timed out waiting for the end of the partition");
+        match event {
+            ConsumerEvent::Message(_) => received += 1,
+            ConsumerEvent::PartitionEof {
+                topic,
+                partition: 0,
+                offset,
+            } => {
+                assert_eq!(topic, topic_name);
+                assert_eq!(offset, 10);
+                break;
+            }
+            ConsumerEvent::PartitionEof { .. } => {}
+            ConsumerEvent::Error(e) => panic!("Error receiving message: {:?}", e),
+        }
+    }
+    assert_eq!(received, 10);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_stream_consumer_async_queries() {
+    let _r = env_logger::try_init();
+
+    let topic_name = rand_test_topic();
+    populate_topic(&topic_name, 10, &value_fn, &key_fn, Some(0), None).await;
+    let consumer = Arc::new(create_stream_consumer(&rand_test_group(), None));
+    let timeout = Duration::from_secs(5);
+
+    let metadata = consumer
+        .fetch_metadata_async(Some(&topic_name), timeout)
+        .await
+        .unwrap();
+    assert_eq!(metadata.topics()[0].name(), topic_name);
+    let (low, high) = consumer
+        .fetch_watermarks_async(&topic_name, 0, timeout)
+        .await
+        .unwrap();
+    assert_eq!((low, high), (0, 10));
+
+    let mut tpl = TopicPartitionList::new();
+    tpl.add_partition_offset(&topic_name, 0, Offset::Beginning)
+        .unwrap();
+    consumer.assign(&tpl).unwrap();
+    let committed = consumer.committed_async(timeout).await.unwrap();
+    assert_eq!(committed.count(), 1);
+
+    let mut timestamps = TopicPartitionList::new();
+    timestamps
+        .add_partition_offset(&topic_name, 0, Offset::Offset(0))
+        .unwrap();
+    let offsets = consumer
+        .offsets_for_times_async(timestamps, timeout)
+        .await
+        .unwrap();
+    assert_eq!(
+        offsets.find_partition(&topic_name, 0).unwrap().offset(),
+        Offset::Offset(0)
+    );
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn test_produce_consume_base_incremental_assign_and_unassign() {
     let _r = env_logger::try_init();
@@ -348,6 +424,18 @@ async fn test_consumer_commit_message() {
         .add_partition_offset(&topic_name, 2, Offset::Offset(12))
         .unwrap();
     assert_eq!(position, consumer.position().unwrap());
+
+    let mut partitions = TopicPartitionList::new();
+    partitions.add_partition(&topic_name, 1);
+    let mut expected = TopicPartitionList::new();
+    expected
+        .add_partition_offset(&topic_name, 1, Offset::Offset(11))
+        .unwrap();
+    assert_eq!(expected, consumer.positions(partitions).unwrap());
+
+    let lag = consumer.lag(timeout).unwrap();
+    assert_eq!(lag.len(), 3);
+    assert!(lag.values().all(|&lag| lag == 0));
 }
 
 #[tokio::test(flavor = "multi_thread")]
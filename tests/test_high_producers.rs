@@ -1,15 +1,18 @@
 //! Test data production using high level producers.
 
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use futures::stream::{FuturesUnordered, StreamExt};
+use maplit::hashmap;
 
 use rdkafka::client::DefaultClientContext;
 use rdkafka::config::ClientConfig;
 use rdkafka::error::{KafkaError, RDKafkaErrorCode};
 use rdkafka::message::{Header, Headers, Message, OwnedHeaders};
-use rdkafka::producer::{FutureProducer, FutureRecord, Producer};
+use rdkafka::producer::{FutureProducer, FutureRecord, Producer, QueueDepth, RetryPolicy};
 use rdkafka::util::Timeout;
 
 use crate::utils::*;
@@ -93,6 +96,61 @@ async fn test_future_producer_send_full() {
     producer.flush(Timeout::Never).unwrap();
 }
 
+#[tokio::test]
+async fn test_future_producer_in_flight_limit() {
+    // As above, connect to a nonexistent Kafka broker so that messages stay in
+    // flight until they time out.
+    let mut config = HashMap::new();
+    config.insert("bootstrap.servers", "");
+    config.insert("message.timeout.ms", "2000");
+    let producer = future_producer(config);
+    let topic_name = rand_test_topic();
+    producer.set_in_flight_limit(Some(QueueDepth {
+        messages: 1,
+        bytes: u64::MAX,
+    }));
+
+    let delivery = producer
+        .send_result(FutureRecord::to(&topic_name).payload("A").key("B"))
+        .unwrap();
+    assert_eq!(
+        producer.in_flight_depth(),
+        QueueDepth {
+            messages: 1,
+            bytes: 2,
+        }
+    );
+
+    // The librdkafka queue has space, but the in-flight limit is reached, so
+    // the send waits until its timeout.
+    let start = Instant::now();
+    match producer
+        .send(
+            FutureRecord::to(&topic_name).payload("A").key("B"),
+            Duration::from_millis(500),
+        )
+        .await
+    {
+        Err((KafkaError::MessageProduction(RDKafkaErrorCode::QueueFull), _)) => (),
+        _ => panic!("expected a QueueFull error"),
+    }
+    assert!(start.elapsed() > Duration::from_millis(400));
+
+    // Without a timeout, the send waits for the first message to time out.
+    match producer
+        .send(
+            FutureRecord::to(&topic_name).payload("A").key("B"),
+            Timeout::Never,
+        )
+        .await
+    {
+        Err((KafkaError::MessageProduction(RDKafkaErrorCode::MessageTimedOut), _)) => (),
+        _ => panic!("expected a MessageTimedOut error"),
+    }
+    assert!(delivery.await.unwrap().is_err());
+    assert_eq!(producer.in_flight_depth(), QueueDepth::default());
+}
+
 #[tokio::test]
 async fn test_future_producer_send_fail() {
     let producer = future_producer(HashMap::new());
@@ -157,6 +215,47 @@ async fn test_future_producer_send_fail() {
     }
 }
 
+#[tokio::test]
+async fn test_future_producer_send_with_retry() {
+    // There's no server running there, so every attempt times out.
+    let producer = future_producer(hashmap! {
+        "bootstrap.servers" => "localhost:47021",
+        "message.timeout.ms" => "100",
+    });
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let policy = RetryPolicy::new()
+        .max_attempts(3)
+        .initial_backoff(Duration::from_millis(10))
+        .retry_if({
+            let attempts = attempts.clone();
+            move |e| {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                e.is_retriable()
+            }
+        });
+
+    let result = producer
+        .send_with_retry(
+            FutureRecord::to("topic").payload("payload").key("key"),
+            Duration::from_secs(10),
+            &policy,
+        )
+        .await;
+
+    match result {
+        Err((kafka_error, owned_message)) => {
+            assert_eq!(
+                kafka_error,
+                KafkaError::MessageProduction(RDKafkaErrorCode::MessageTimedOut)
+            );
+            assert_eq!(owned_message.payload_view::<str>(), Some(Ok("payload")));
+        }
+        e => panic!("Unexpected return value: {:?}", e),
+    }
+    // The predicate is not consulted once the attempts are exhausted.
+    assert_eq!(attempts.load(Ordering::SeqCst), 2);
+}
+
 #[tokio::test]
 async fn test_future_undelivered() {
     let delivery_future = {
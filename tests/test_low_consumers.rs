@@ -1,13 +1,23 @@
 //! Test data consumption using low level consumers.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant};
 
-use rdkafka::consumer::{BaseConsumer, Consumer, ConsumerContext};
+use rdkafka::consumer::{
+    BaseConsumer, Consumer, ConsumerContext, ConsumerEvent, DefaultConsumerContext,
+    ShardedConsumer, TypedConsumer,
+};
 use rdkafka::error::{KafkaError, RDKafkaErrorCode};
+use rdkafka::message::{Headers, KeyedBy, ToBytes};
+use rdkafka::producer::dlq::{DLQ_ERROR_HEADER, DLQ_OFFSET_HEADER, DLQ_TOPIC_HEADER};
+use rdkafka::producer::{
+    DefaultProducerContext, DlqProducer, FutureProducer, Producer, ThreadedProducer, TypedProducer,
+    TypedRecord,
+};
+use rdkafka::serialization::{BytesDeserializer, BytesSerializer};
 use rdkafka::topic_partition_list::{Offset, TopicPartitionList};
 use rdkafka::util::{current_time_millis, Timeout};
 use rdkafka::{ClientConfig, Message, Timestamp};
@@ -112,12 +122,101 @@ async fn test_produce_consume_iter() {
                 assert_eq!(m.payload_view::<str>().unwrap().unwrap(), value_fn(id));
                 assert_eq!(m.key_view::<str>().unwrap().unwrap(), key_fn(id));
                 assert_eq!(m.topic(), topic_name.as_str());
+                assert!(m.broker_id().is_some());
             }
             Err(e) => panic!("Error receiving message: {:?}", e),
         }
     }
 }
 
+#[tokio::test]
+async fn test_produce_consume_batch() {
+    let _r = env_logger::try_init();
+
+    let topic_name = rand_test_topic();
+    let message_map = populate_topic(&topic_name, 100, &value_fn, &key_fn, None, None).await;
+    let consumer = create_base_consumer(&rand_test_group(), None);
+    consumer.subscribe(&[topic_name.as_str()]).unwrap();
+
+    assert!(consumer
+        .consume_batch(0, Duration::from_secs(1))
+        .unwrap()
+        .is_empty());
+    let mut ids = HashSet::new();
+    while ids.len() < 100 {
+        let batch = consumer.consume_batch(30, Duration::from_secs(5)).unwrap();
+        assert!(batch.len() <= 30);
+        for message in batch {
+            let m = message.unwrap();
+            let id = message_map[&(m.partition(), m.offset())];
+            assert_eq!(m.payload_view::<str>().unwrap().unwrap(), value_fn(id));
+            ids.insert(id);
+        }
+    }
+    assert!(consumer
+        .consume_batch(10, Duration::from_millis(100))
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn test_produce_consume_partition_eof_event() {
+    let _r = env_logger::try_init();
+
+    let topic_name = rand_test_topic();
+    populate_topic(&topic_name, 10, &value_fn, &key_fn, Some(0), None).await;
+    let mut config = HashMap::new();
+    config.insert("enable.partition.eof", "true");
+    let consumer = create_base_consumer(&rand_test_group(), Some(config));
+    consumer.subscribe(&[topic_name.as_str()]).unwrap();
+
+    let mut received = 0;
+    loop {
+        match consumer.poll_event(Duration::from_secs(10)) {
+            Some(ConsumerEvent::Message(_)) => received += 1,
+            Some(ConsumerEvent::PartitionEof {
+                topic,
+                partition: 0,
+                offset,
+            }) => {
+                assert_eq!(topic, topic_name);
+                assert_eq!(offset, 10);
+                break;
+            }
+            Some(ConsumerEvent::PartitionEof { .. }) => {}
+            Some(ConsumerEvent::Error(e)) => panic!("Error receiving message: {:?}", e),
+            None => panic!("Timed out waiting for the end of the partition"),
+        }
+    }
+    assert_eq!(received, 10);
+}
+
+// Closing the consumer should revoke its assignment, and be idempotent.
+#[tokio::test]
+async fn test_consumer_close() {
+    let _r = env_logger::try_init();
+
+    let topic_name = rand_test_topic();
+    populate_topic(&topic_name, 3, &value_fn, &key_fn, None, None).await;
+    let group_name = rand_test_group();
+    let consumer = create_base_consumer(&group_name, None);
+    assert_eq!(consumer.member_id(), None);
+    consumer.subscribe(&[topic_name.as_str()]).unwrap();
+    consumer.iter().next().unwrap().unwrap();
+    assert!(consumer.assignment().unwrap().count() > 0);
+    assert!(consumer.member_id().is_some());
+    assert!(!consumer.assignment_lost());
+
+    let metadata = consumer.group_metadata().unwrap();
+    assert_eq!(metadata.group_id(), Some(group_name.as_str()));
+    assert_eq!(metadata.member_id(), consumer.member_id().as_deref());
+    assert_eq!(metadata.group_instance_id(), None);
+
+    consumer.close().unwrap();
+    assert_eq!(consumer.assignment().unwrap().count(), 0);
+    consumer.close().unwrap();
+}
+
 fn ensure_empty<C: ConsumerContext>(consumer: &BaseConsumer<C>, err_msg: &str) {
     const MAX_TRY_TIME: Duration = Duration::from_secs(2);
     let start = Instant::now();
@@ -367,3 +466,197 @@ async fn test_invalid_consumer_position() {
         Err(KafkaError::MetadataFetch(RDKafkaErrorCode::UnknownGroup))
     );
 }
+
+#[tokio::test]
+async fn test_skip_to_quarantine() {
+    let _r = env_logger::try_init();
+
+    let topic_name = rand_test_topic();
+    let quarantine_topic_name = rand_test_topic();
+    create_topic(&quarantine_topic_name, 1).await;
+    populate_topic(&topic_name, 1, &value_fn, &key_fn, Some(0), None).await;
+    let group_id = rand_test_group();
+    let consumer = create_base_consumer(&group_id, None);
+    consumer.subscribe(&[topic_name.as_str()]).unwrap();
+    let producer: FutureProducer = ClientConfig::new()
+        .set("bootstrap.servers", get_bootstrap_server())
+        .create()
+        .unwrap();
+    let dlq = DlqProducer::new(producer, &quarantine_topic_name);
+
+    let message = consumer.poll(Timeout::Never).unwrap().unwrap();
+    consumer
+        .skip_to_quarantine(&message, &dlq, "invalid payload", Timeout::Never)
+        .unwrap();
+    let committed = consumer.committed(Timeout::Never).unwrap();
+    assert_eq!(
+        committed.find_partition(&topic_name, 0).unwrap().offset(),
+        Offset::Offset(message.offset() + 1)
+    );
+
+    let quarantine_consumer = create_base_consumer(&rand_test_group(), None);
+    quarantine_consumer
+        .subscribe(&[quarantine_topic_name.as_str()])
+        .unwrap();
+    let quarantined = quarantine_consumer.poll(Timeout::Never).unwrap().unwrap();
+    assert_eq!(quarantined.payload(), message.payload());
+    assert_eq!(quarantined.key(), message.key());
+    let headers = quarantined.headers().unwrap();
+    let header = |key| {
+        (0..headers.count())
+            .map(|i| headers.get_as::<str>(i).unwrap())
+            .find(|header| header.key == key)
+            .and_then(|header| header.value)
+    };
+    assert_eq!(header(DLQ_TOPIC_HEADER), Some(topic_name.as_str()));
+    assert_eq!(
+        header(DLQ_OFFSET_HEADER),
+        Some(message.offset().to_string().as_str())
+    );
+    assert_eq!(header(DLQ_ERROR_HEADER), Some("invalid payload"));
+}
+
+#[tokio::test]
+async fn test_typed_produce_consume() {
+    let _r = env_logger::try_init();
+
+    let topic_name = rand_test_topic();
+    let producer: ThreadedProducer<DefaultProducerContext> = ClientConfig::new()
+        .set("bootstrap.servers", get_bootstrap_server())
+        .create()
+        .unwrap();
+    let producer: TypedProducer<String, String> =
+        TypedProducer::new(producer, BytesSerializer, BytesSerializer);
+    for i in 0..10 {
+        producer
+            .send(
+                TypedRecord::to(&topic_name)
+                    .key(&key_fn(i))
+                    .payload(&value_fn(i))
+                    .partition(0),
+            )
+            .unwrap();
+    }
+    producer.producer().flush(Timeout::Never).unwrap();
+
+    let consumer = create_base_consumer(&rand_test_group(), None);
+    consumer.subscribe(&[topic_name.as_str()]).unwrap();
+    let consumer: TypedConsumer<String, String, _, _, _> =
+        TypedConsumer::new(consumer, BytesDeserializer, BytesDeserializer);
+    for i in 0..10 {
+        let message = consumer.poll(Timeout::Never).unwrap().unwrap();
+        assert_eq!(message.offset(), i as i64);
+        assert_eq!(message.key(), Some(&key_fn(i)));
+        assert_eq!(message.payload(), Some(&value_fn(i)));
+    }
+}
+
+struct Order {
+    customer: String,
+    description: String,
+}
+
+impl ToBytes for Order {
+    fn to_bytes(&self) -> &[u8] {
+        self.description.as_bytes()
+    }
+}
+
+impl KeyedBy for Order {
+    type Key = String;
+
+    fn key(&self) -> String {
+        format!("customer-{}", self.customer)
+    }
+}
+
+// Records without a key should be keyed by their payload, unless they have an
+// explicit key.
+#[tokio::test]
+async fn test_typed_produce_consume_keyed() {
+    let _r = env_logger::try_init();
+
+    let topic_name = rand_test_topic();
+    let producer: ThreadedProducer<DefaultProducerContext> = ClientConfig::new()
+        .set("bootstrap.servers", get_bootstrap_server())
+        .create()
+        .unwrap();
+    let producer: TypedProducer<String, Order> =
+        TypedProducer::new(producer, BytesSerializer, BytesSerializer);
+    let order = Order {
+        customer: "42".into(),
+        description: "two pizzas".into(),
+    };
+    producer
+        .send_keyed(TypedRecord::to(&topic_name).payload(&order).partition(0))
+        .unwrap();
+    producer
+        .send_keyed(
+            TypedRecord::to(&topic_name)
+                .key(&"explicit".to_string())
+                .payload(&order)
+                .partition(0),
+        )
+        .unwrap();
+    producer.producer().flush(Timeout::Never).unwrap();
+
+    let consumer = create_base_consumer(&rand_test_group(), None);
+    consumer.subscribe(&[topic_name.as_str()]).unwrap();
+    let consumer: TypedConsumer<String, String, _, _, _> =
+        TypedConsumer::new(consumer, BytesDeserializer, BytesDeserializer);
+    for expected_key in &["customer-42", "explicit"] {
+        let message = consumer.poll(Timeout::Never).unwrap().unwrap();
+        assert_eq!(message.key().map(String::as_str), Some(*expected_key));
+        assert_eq!(message.payload().map(String::as_str), Some("two pizzas"));
+    }
+}
+
+// Every message should be handled by one of the shards, a panicking handler
+// should not stop its shard, and shutting down should join the threads of
+// the shards, which releases the handler.
+#[tokio::test]
+async fn test_sharded_consumer() {
+    let _r = env_logger::try_init();
+
+    let topic_name = rand_test_topic();
+    let message_map = populate_topic(&topic_name, 30, &value_fn, &key_fn, None, None).await;
+    let received = Arc::new(Mutex::new(HashSet::new()));
+    let consumer = ShardedConsumer::new(
+        &consumer_config(&rand_test_group(), None),
+        3,
+        &[topic_name.as_str()],
+        |_| DefaultConsumerContext,
+        {
+            let received = Arc::clone(&received);
+            move |_, result| {
+                let message = result.expect("Error receiving message");
+                received
+                    .lock()
+                    .unwrap()
+                    .insert((message.partition(), message.offset()));
+                if message.offset() == 0 {
+                    panic!("handler failure");
+                }
+            }
+        },
+    )
+    .unwrap();
+    assert_eq!(consumer.shard_count(), 3);
+
+    let deadline = Instant::now() + Duration::from_secs(30);
+    while received.lock().unwrap().len() < message_map.len() {
+        assert!(Instant::now() < deadline, "Timed out waiting for messages");
+        thread::sleep(Duration::from_millis(100));
+    }
+
+    let stats = consumer.shutdown();
+    assert_eq!(stats.shards.len(), 3);
+    assert_eq!(stats.messages(), 30);
+    let partitions = message_map.keys().map(|&(p, _)| p).collect::<HashSet<_>>();
+    assert_eq!(stats.panics(), partitions.len() as u64);
+    assert_eq!(
+        *received.lock().unwrap(),
+        message_map.keys().cloned().collect::<HashSet<_>>()
+    );
+    assert_eq!(Arc::strong_count(&received), 1);
+}
@@ -9,11 +9,16 @@ use std::time::Duration;
 
 use maplit::hashmap;
 
-use rdkafka::config::ClientConfig;
+use rdkafka::config::{ClientConfig, TopicConfig};
 use rdkafka::error::{KafkaError, RDKafkaErrorCode};
-use rdkafka::message::{Header, Headers, Message, OwnedHeaders, OwnedMessage};
+use rdkafka::interceptor::{InterceptedRecord, Interceptor};
+use rdkafka::message::{
+    Header, Headers, Message, MessageStatus, OwnedHeaders, OwnedMessage, Timestamp,
+};
+use rdkafka::producer::delivery_handle::DeliveryHandleContext;
 use rdkafka::producer::{
-    BaseProducer, BaseRecord, DeliveryResult, Producer, ProducerContext, ThreadedProducer,
+    BaseProducer, BaseRecord, DeliveryResult, Producer, ProducerContext, QueueDepth,
+    QueueWatermark, QueueWatermarks, ThreadedProducer, TopicMetrics,
 };
 use rdkafka::types::RDKafkaRespErr;
 use rdkafka::util::current_time_millis;
@@ -98,7 +103,7 @@ fn base_producer(config_overrides: HashMap<&str, &str>) -> BaseProducer<Printing
     base_producer_with_context(PrintingContext { _n: 123 }, config_overrides)
 }
 
-fn base_producer_with_context<C: ProducerContext>(
+fn base_producer_with_context<C: ProducerContext + 'static>(
     context: C,
     config_overrides: HashMap<&str, &str>,
 ) -> BaseProducer<C> {
@@ -160,6 +165,27 @@ fn test_base_producer_queue_full() {
     assert_eq!(errors, 20);
 }
 
+#[test]
+fn test_base_producer_send_with_timeout() {
+    let producer = base_producer(hashmap! { "queue.buffering.max.messages" => "10" });
+    let topic_name = rand_test_topic();
+
+    let results = (0..30)
+        .map(|id| {
+            producer.send_with_timeout(
+                BaseRecord::with_opaque_to(&topic_name, id)
+                    .payload("payload")
+                    .key("key"),
+                Duration::from_secs(10),
+            )
+        })
+        .collect::<Vec<_>>();
+    producer.flush(Duration::from_secs(10)).unwrap();
+
+    assert_eq!(results.len(), 30);
+    assert!(results.iter().all(|r| r.is_ok()));
+}
+
 #[test]
 fn test_base_producer_timeout() {
     let context = CollectingContext::new();
@@ -348,6 +374,119 @@ fn test_threaded_producer_send() {
     }
 }
 
+#[test]
+fn test_threaded_producer_send_blocking() {
+    let context = CollectingContext::new();
+    let producer = threaded_producer_with_context(
+        context.clone(),
+        hashmap! { "queue.buffering.max.messages" => "10" },
+    );
+    let topic_name = rand_test_topic();
+
+    // The queue only holds 10 messages, so the later sends have to wait for
+    // the polling thread to serve deliveries.
+    for id in 0..30 {
+        producer
+            .send_blocking(
+                BaseRecord::with_opaque_to(&topic_name, id)
+                    .payload("payload")
+                    .key("key"),
+            )
+            .unwrap();
+    }
+    producer.flush(Duration::from_secs(10)).unwrap();
+
+    let delivery_results = context.results.lock().unwrap();
+    assert_eq!(delivery_results.len(), 30);
+    assert!(delivery_results.iter().all(|(_, error, _)| error.is_none()));
+}
+
+#[test]
+fn test_producer_send_with_handle() {
+    let topic_name = rand_test_topic();
+
+    let producer = threaded_producer_with_context(DeliveryHandleContext, HashMap::new());
+    let handles = (0..10)
+        .map(|_| {
+            producer
+                .send_with_handle(BaseRecord::<str, str>::to(&topic_name).payload("payload"))
+                .unwrap()
+        })
+        .collect::<Vec<_>>();
+    for handle in handles {
+        let (partition, _) = handle.wait(Duration::from_secs(10)).unwrap().unwrap();
+        assert!(partition >= 0);
+    }
+
+    let producer = base_producer_with_context(DeliveryHandleContext, HashMap::new());
+    let handle = producer
+        .send_with_handle(BaseRecord::<str, str>::to(&topic_name).payload("payload"))
+        .unwrap();
+    assert!(handle.try_result().is_none());
+    producer.flush(Duration::from_secs(10)).unwrap();
+    assert!(handle.try_result().unwrap().is_ok());
+}
+
+#[test]
+fn test_base_producer_delivery_metadata() {
+    type DeliveryMetadata = (
+        String,
+        Timestamp,
+        Option<Duration>,
+        MessageStatus,
+        Option<i32>,
+        OwnedMessage,
+    );
+
+    struct MetadataContext {
+        results: Mutex<Vec<DeliveryMetadata>>,
+    }
+
+    impl ClientContext for MetadataContext {}
+
+    impl ProducerContext for MetadataContext {
+        type DeliveryOpaque = ();
+
+        fn delivery(&self, delivery_result: &DeliveryResult, _: Self::DeliveryOpaque) {
+            let message = delivery_result.as_ref().unwrap();
+            self.results.lock().unwrap().push((
+                message.topic().to_owned(),
+                message.timestamp(),
+                message.latency(),
+                message.status(),
+                message.broker_id(),
+                message.detach(),
+            ));
+        }
+    }
+
+    let context = MetadataContext {
+        results: Mutex::new(Vec::new()),
+    };
+    let producer = base_producer_with_context(context, HashMap::new());
+    let topic_name = rand_test_topic();
+    producer
+        .send::<str, str>(
+            BaseRecord::to(&topic_name)
+                .payload("A")
+                .timestamp(1_600_000_000_000),
+        )
+        .unwrap();
+    producer.flush(Duration::from_secs(10)).unwrap();
+
+    let results = producer.context().results.lock().unwrap();
+    assert_eq!(results.len(), 1);
+    let (topic, timestamp, latency, status, broker_id, detached) = &results[0];
+    assert_eq!(topic, &topic_name);
+    assert_eq!(*timestamp, Timestamp::CreateTime(1_600_000_000_000));
+    assert!(latency.is_some());
+    assert_eq!(*status, MessageStatus::Persisted);
+    assert!(broker_id.is_some());
+    assert_eq!(detached.latency(), *latency);
+    assert_eq!(detached.status(), Some(*status));
+    assert_eq!(detached.broker_id(), *broker_id);
+}
+
 #[test]
 fn test_base_producer_opaque_arc() -> Result<(), Box<dyn Error>> {
     struct OpaqueArcContext {}
@@ -404,5 +543,549 @@ fn test_fatal_errors() {
             RDKafkaErrorCode::OutOfOrderSequenceNumber,
             "test_fatal_error: fake error".into()
         ))
-    )
+    );
+    assert_eq!(producer.fatal_error(), producer.client().fatal_error());
+}
+
+#[test]
+fn test_base_producer_poll_overdue() {
+    struct PollOverdueContext {
+        overdue: Arc<Mutex<usize>>,
+    }
+
+    impl ClientContext for PollOverdueContext {}
+
+    impl ProducerContext for PollOverdueContext {
+        type DeliveryOpaque = ();
+
+        fn delivery(&self, _: &DeliveryResult, _: Self::DeliveryOpaque) {}
+
+        fn poll_overdue_threshold(&self) -> Option<Duration> {
+            Some(Duration::from_millis(10))
+        }
+
+        fn poll_overdue(&self, _: Duration) {
+            *self.overdue.lock().unwrap() += 1;
+        }
+    }
+
+    let overdue = Arc::new(Mutex::new(0));
+    let context = PollOverdueContext {
+        overdue: overdue.clone(),
+    };
+    let producer = base_producer_with_context(context, HashMap::new());
+    let topic_name = rand_test_topic();
+
+    producer.poll(Duration::ZERO);
+    producer
+        .send::<str, str>(BaseRecord::to(&topic_name).payload("A"))
+        .unwrap();
+    assert_eq!(*overdue.lock().unwrap(), 0);
+
+    std::thread::sleep(Duration::from_millis(50));
+    producer
+        .send::<str, str>(BaseRecord::to(&topic_name).payload("A"))
+        .unwrap();
+    assert_eq!(*overdue.lock().unwrap(), 1);
+
+    producer.flush(Duration::from_secs(10)).unwrap();
+}
+
+#[test]
+fn test_base_producer_queue_watermarks() {
+    struct WatermarkContext {
+        crossed: Arc<Mutex<Vec<(QueueWatermark, QueueDepth)>>>,
+    }
+
+    impl ClientContext for WatermarkContext {}
+
+    impl ProducerContext for WatermarkContext {
+        type DeliveryOpaque = ();
+
+        fn delivery(&self, _: &DeliveryResult, _: Self::DeliveryOpaque) {}
+
+        fn queue_watermarks(&self) -> Option<QueueWatermarks> {
+            Some(QueueWatermarks {
+                high: QueueDepth {
+                    messages: 3,
+                    bytes: u64::MAX,
+                },
+                low: QueueDepth {
+                    messages: 1,
+                    bytes: u64::MAX,
+                },
+            })
+        }
+
+        fn queue_watermark(&self, watermark: QueueWatermark, depth: QueueDepth) {
+            self.crossed.lock().unwrap().push((watermark, depth));
+        }
+    }
+
+    let crossed = Arc::new(Mutex::new(Vec::new()));
+    let context = WatermarkContext {
+        crossed: crossed.clone(),
+    };
+    let producer = base_producer_with_context(context, HashMap::new());
+    let topic_name = rand_test_topic();
+
+    for _ in 0..4 {
+        producer
+            .send::<str, str>(BaseRecord::to(&topic_name).payload("AB").key("C"))
+            .unwrap();
+    }
+    assert_eq!(
+        producer.queue_depth(),
+        QueueDepth {
+            messages: 4,
+            bytes: 12
+        }
+    );
+    assert_eq!(
+        *crossed.lock().unwrap(),
+        vec![(
+            QueueWatermark::High,
+            QueueDepth {
+                messages: 3,
+                bytes: 9
+            }
+        )]
+    );
+
+    producer.flush(Duration::from_secs(10)).unwrap();
+    assert_eq!(producer.queue_depth(), QueueDepth::default());
+    let crossed = crossed.lock().unwrap();
+    assert_eq!(crossed.len(), 2);
+    assert_eq!(crossed[1].0, QueueWatermark::Low);
+}
+
+#[test]
+fn test_base_producer_custom_partitioner() {
+    type PartitionerCall = (String, Option<Vec<u8>>, i32);
+
+    #[derive(Default)]
+    struct PartitionerContext {
+        calls: Mutex<Vec<PartitionerCall>>,
+        partitions: Mutex<Vec<i32>>,
+    }
+
+    impl ClientContext for PartitionerContext {}
+
+    impl ProducerContext for PartitionerContext {
+        type DeliveryOpaque = ();
+
+        const ENABLE_CUSTOM_PARTITIONER: bool = true;
+
+        fn delivery(&self, delivery_result: &DeliveryResult, _: Self::DeliveryOpaque) {
+            let message = delivery_result.as_ref().unwrap();
+            self.partitions.lock().unwrap().push(message.partition());
+        }
+
+        fn partition(&self, topic: &str, key: Option<&[u8]>, partition_count: i32) -> i32 {
+            self.calls.lock().unwrap().push((
+                topic.to_string(),
+                key.map(|k| k.to_vec()),
+                partition_count,
+            ));
+            partition_count - 1
+        }
+    }
+
+    let producer = base_producer_with_context(PartitionerContext::default(), HashMap::new());
+    let topic_name = rand_test_topic();
+
+    producer
+        .send(BaseRecord::to(&topic_name).payload("A").key("tenant-1"))
+        .unwrap();
+    producer.flush(Duration::from_secs(10)).unwrap();
+
+    let calls = producer.context().calls.lock().unwrap();
+    assert_eq!(calls.len(), 1);
+    let (topic, key, partition_count) = &calls[0];
+    assert_eq!(topic, &topic_name);
+    assert_eq!(key.as_deref(), Some(&b"tenant-1"[..]));
+    assert_eq!(
+        *producer.context().partitions.lock().unwrap(),
+        vec![partition_count - 1]
+    );
+}
+
+#[test]
+fn test_base_producer_configure_topic() {
+    let producer = base_producer(HashMap::new());
+    let topic_name = rand_test_topic();
+
+    let mut topic_config = TopicConfig::new();
+    topic_config.set("message.timeout.ms", "not a number");
+    assert!(matches!(
+        producer.configure_topic(&topic_name, &topic_config),
+        Err(KafkaError::ClientConfig(..))
+    ));
+
+    topic_config
+        .set("message.timeout.ms", "10000")
+        .set("partitioner", "murmur2");
+    producer
+        .configure_topic(&topic_name, &topic_config)
+        .unwrap();
+    producer
+        .send::<str, str>(
+            BaseRecord::with_opaque_to(&topic_name, 0)
+                .payload("A")
+                .key("B"),
+        )
+        .unwrap();
+    producer.flush(Duration::from_secs(10)).unwrap();
+}
+
+#[test]
+fn test_base_producer_register_topic() {
+    let context = CollectingContext::new();
+    let producer = base_producer_with_context(context.clone(), HashMap::new());
+    let topic_name = rand_test_topic();
+
+    producer.register_topic(&topic_name).unwrap();
+    producer.register_topic(&topic_name).unwrap();
+    for id in 0..10 {
+        producer
+            .send::<str, str>(BaseRecord::with_opaque_to(&topic_name, id).payload("A"))
+            .unwrap();
+    }
+    let batch = (10..20)
+        .map(|id| BaseRecord::<str, str, usize>::with_opaque_to(&topic_name, id).payload("A"))
+        .collect();
+    let results = producer.send_batch(&topic_name, None, batch);
+    assert!(results.iter().all(Result::is_ok));
+    producer.flush(Duration::from_secs(10)).unwrap();
+
+    let delivery_results = context.results.lock().unwrap();
+    assert_eq!(delivery_results.len(), 20);
+    for (message, error, _) in delivery_results.iter() {
+        assert_eq!(message.topic(), topic_name);
+        assert!(error.is_none());
+    }
+}
+
+#[test]
+fn test_base_producer_metrics() {
+    let context = CollectingContext::new();
+    let producer = base_producer_with_context(context, HashMap::new());
+    let topic_name = rand_test_topic();
+    assert!(producer.metrics().is_empty());
+
+    for id in 0..10 {
+        producer
+            .send::<str, str>(
+                BaseRecord::with_opaque_to(&topic_name, id)
+                    .key("K")
+                    .payload("AB"),
+            )
+            .unwrap();
+    }
+    producer.flush(Duration::from_secs(10)).unwrap();
+
+    let metrics = producer.metrics();
+    assert_eq!(metrics.len(), 1);
+    assert_eq!(
+        metrics[&topic_name],
+        TopicMetrics {
+            messages_sent: 10,
+            messages_delivered: 10,
+            messages_failed: 0,
+            bytes_sent: 30,
+            bytes_delivered: 30,
+        }
+    );
+}
+
+#[test]
+fn test_base_producer_flush_on_drop() {
+    let context = CollectingContext::new();
+    let topic_name = rand_test_topic();
+    {
+        let producer = base_producer_with_context(context.clone(), HashMap::new());
+        producer.set_flush_on_drop(Some(Duration::from_secs(10)));
+        for id in 0..10 {
+            producer
+                .send::<str, str>(BaseRecord::with_opaque_to(&topic_name, id).payload("A"))
+                .unwrap();
+        }
+    }
+
+    let delivery_results = context.results.lock().unwrap();
+    assert_eq!(delivery_results.len(), 10);
+    for (_, error, _) in delivery_results.iter() {
+        assert!(error.is_none());
+    }
+}
+
+#[test]
+fn test_base_producer_send_zero_copy() {
+    struct ZeroCopyContext {
+        payloads: Mutex<Vec<Vec<u8>>>,
+    }
+
+    impl ClientContext for ZeroCopyContext {}
+
+    impl ProducerContext for ZeroCopyContext {
+        type DeliveryOpaque = ();
+
+        fn delivery(&self, delivery_result: &DeliveryResult, _: Self::DeliveryOpaque) {
+            let message = delivery_result.as_ref().unwrap();
+            self.payloads
+                .lock()
+                .unwrap()
+                .push(message.payload().unwrap().to_vec());
+        }
+    }
+
+    let context = ZeroCopyContext {
+        payloads: Mutex::new(Vec::new()),
+    };
+    let producer = base_producer_with_context(context, HashMap::new());
+    let topic_name = rand_test_topic();
+    let payload = Arc::new(b"zero copy".to_vec());
+
+    for _ in 0..3 {
+        producer
+            .send_zero_copy::<str, _>(BaseRecord::to(&topic_name), payload.clone())
+            .unwrap();
+    }
+    producer.flush(Duration::from_secs(10)).unwrap();
+
+    // The payloads were still readable from the delivery callback, and the
+    // producer released them afterwards.
+    let payloads = producer.context().payloads.lock().unwrap();
+    assert_eq!(*payloads, vec![b"zero copy".to_vec(); 3]);
+    assert_eq!(Arc::strong_count(&payload), 1);
+}
+
+// Sending a zero-copy record from a callback that runs while another record
+// is enqueued must not deadlock.
+#[test]
+fn test_base_producer_send_zero_copy_from_queue_watermark() {
+    struct ReentrantContext {
+        producer: Mutex<Option<Arc<BaseProducer<ReentrantContext>>>>,
+        topic: String,
+        payload: Arc<Vec<u8>>,
+    }
+
+    impl ClientContext for ReentrantContext {}
+
+    impl ProducerContext for ReentrantContext {
+        type DeliveryOpaque = ();
+
+        fn delivery(&self, _: &DeliveryResult, _: Self::DeliveryOpaque) {}
+
+        fn queue_watermarks(&self) -> Option<QueueWatermarks> {
+            Some(QueueWatermarks {
+                high: QueueDepth {
+                    messages: 2,
+                    bytes: u64::MAX,
+                },
+                low: QueueDepth {
+                    messages: 0,
+                    bytes: u64::MAX,
+                },
+            })
+        }
+
+        fn queue_watermark(&self, watermark: QueueWatermark, _: QueueDepth) {
+            if watermark == QueueWatermark::High {
+                let producer = self.producer.lock().unwrap().clone().unwrap();
+                producer
+                    .send_zero_copy::<str, _>(BaseRecord::to(&self.topic), self.payload.clone())
+                    .unwrap();
+            }
+        }
+    }
+
+    let topic_name = rand_test_topic();
+    let payload = Arc::new(b"zero copy".to_vec());
+    let context = ReentrantContext {
+        producer: Mutex::new(None),
+        topic: topic_name.clone(),
+        payload: payload.clone(),
+    };
+    let producer = Arc::new(base_producer_with_context(context, HashMap::new()));
+    *producer.context().producer.lock().unwrap() = Some(producer.clone());
+
+    for _ in 0..2 {
+        producer
+            .send_zero_copy::<str, _>(BaseRecord::to(&topic_name), payload.clone())
+            .unwrap();
+    }
+    assert_eq!(producer.queue_depth().messages, 3);
+    producer.flush(Duration::from_secs(10)).unwrap();
+    assert_eq!(producer.queue_depth(), QueueDepth::default());
+
+    // Break the reference cycle between the producer and its context.
+    producer.context().producer.lock().unwrap().take();
+    drop(producer);
+    assert_eq!(Arc::strong_count(&payload), 1);
+}
+
+#[test]
+fn test_base_producer_partition_availability_check() {
+    struct AvailabilityContext;
+
+    impl ClientContext for AvailabilityContext {}
+
+    impl ProducerContext for AvailabilityContext {
+        type DeliveryOpaque = ();
+
+        fn delivery(&self, _: &DeliveryResult, _: Self::DeliveryOpaque) {}
+
+        fn partition_availability_check(&self) -> Option<Duration> {
+            Some(Duration::ZERO)
+        }
+    }
+
+    let producer = base_producer_with_context(AvailabilityContext, HashMap::new());
+    let topic_name = rand_test_topic();
+
+    // The topic does not exist yet, so the check lets the message through and
+    // the topic is created automatically.
+    producer
+        .send::<str, str>(BaseRecord::to(&topic_name).payload("A"))
+        .unwrap();
+    producer.flush(Duration::from_secs(10)).unwrap();
+
+    producer
+        .send::<str, str>(BaseRecord::to(&topic_name).payload("A").partition(0))
+        .unwrap();
+    match producer.send::<str, str>(BaseRecord::to(&topic_name).payload("A").partition(1000)) {
+        Err((KafkaError::PartitionUnavailable(topic, 1000), _)) if topic == topic_name => (),
+        _ => panic!("expected a PartitionUnavailable error"),
+    }
+    producer.flush(Duration::from_secs(10)).unwrap();
+}
+
+#[test]
+fn test_base_producer_send_batch() {
+    let context = CollectingContext::new();
+    let producer = base_producer_with_context(context.clone(), HashMap::new());
+    let topic_name = rand_test_topic();
+
+    let records = (0..10)
+        .map(|id| {
+            BaseRecord::with_opaque_to(&topic_name, id)
+                .payload("payload")
+                .key("key")
+        })
+        .chain(vec![
+            BaseRecord::with_opaque_to("other_topic", 10).payload("payload"),
+            BaseRecord::with_opaque_to(&topic_name, 11)
+                .payload("payload")
+                .timestamp(1_600_000_000_000),
+        ])
+        .collect::<Vec<BaseRecord<str, str, usize>>>();
+    let results = producer.send_batch(&topic_name, None, records);
+    assert_eq!(results.len(), 12);
+    assert!(results.iter().all(|r| r.is_ok()));
+    producer.flush(Duration::from_secs(10)).unwrap();
+
+    let delivery_results = context.results.lock().unwrap();
+    let mut ids = HashSet::new();
+    for &(ref message, ref error, id) in &(*delivery_results) {
+        assert_eq!(error, &None);
+        assert_eq!(message.payload_view::<str>(), Some(Ok("payload")));
+        let expected_topic = if id == 10 {
+            "other_topic"
+        } else {
+            topic_name.as_str()
+        };
+        assert_eq!(message.topic(), expected_topic);
+        if id == 11 {
+            assert_eq!(
+                message.timestamp(),
+                Timestamp::CreateTime(1_600_000_000_000)
+            );
+        }
+        ids.insert(id);
+    }
+    assert_eq!(ids, (0..12).collect());
+}
+
+#[derive(Default)]
+struct AuditInterceptor {
+    sent: Mutex<Vec<String>>,
+    acknowledged: Mutex<Vec<Option<String>>>,
+}
+
+impl Interceptor for AuditInterceptor {
+    fn on_send(&self, record: &mut InterceptedRecord<'_>) {
+        let id = String::from_utf8(record.payload().unwrap().to_vec()).unwrap();
+        record.add_header(Header {
+            key: "audit-id",
+            value: Some(id.as_str()),
+        });
+        self.sent.lock().unwrap().push(id);
+    }
+
+    fn on_acknowledgement(&self, result: &DeliveryResult<'_>) {
+        let message = result.as_ref().unwrap();
+        let header = message
+            .headers()
+            .and_then(|headers| headers.get_as::<str>(0).ok())
+            .and_then(|header| header.value.map(str::to_owned));
+        self.acknowledged.lock().unwrap().push(header);
+    }
+}
+
+struct InterceptingContext {
+    interceptors: Vec<Arc<dyn Interceptor>>,
+}
+
+impl ClientContext for InterceptingContext {
+    fn interceptors(&self) -> &[Arc<dyn Interceptor>] {
+        &self.interceptors
+    }
+}
+
+impl ProducerContext for InterceptingContext {
+    type DeliveryOpaque = ();
+
+    fn delivery(&self, _: &DeliveryResult<'_>, _: ()) {}
+}
+
+#[test]
+fn test_base_producer_interceptors() {
+    let audit = Arc::new(AuditInterceptor::default());
+    let context = InterceptingContext {
+        interceptors: vec![audit.clone()],
+    };
+    let producer = base_producer_with_context(context, HashMap::new());
+    let topic_name = rand_test_topic();
+
+    for i in 0..3 {
+        let payload = i.to_string();
+        producer
+            .send(BaseRecord::<str, _>::to(&topic_name).payload(&payload))
+            .unwrap();
+    }
+    // Batched records are intercepted too, and keep the headers added by the
+    // interceptor.
+    let records = vec![
+        BaseRecord::<str, _>::to(&topic_name).payload("3"),
+        BaseRecord::<str, _>::to(&topic_name).payload("4"),
+    ];
+    for result in producer.send_batch(&topic_name, None, records) {
+        result.unwrap();
+    }
+    producer.flush(Duration::from_secs(10)).unwrap();
+
+    assert_eq!(*audit.sent.lock().unwrap(), ["0", "1", "2", "3", "4"]);
+    let mut acknowledged = audit.acknowledged.lock().unwrap().clone();
+    acknowledged.sort();
+    assert_eq!(
+        acknowledged,
+        [
+            Some("0".into()),
+            Some("1".into()),
+            Some("2".into()),
+            Some("3".into()),
+            Some("4".into())
+        ]
+    );
 }
@@ -87,6 +87,23 @@ async fn test_metadata() {
     assert_eq!(metadata_one_topic.topics().len(), 1);
 }
 
+#[tokio::test]
+async fn test_add_brokers() {
+    let _r = env_logger::try_init();
+
+    let consumer: StreamConsumer = ClientConfig::new()
+        .set("group.id", rand_test_group())
+        .create()
+        .expect("Failed to create StreamConsumer");
+    let bootstrap_server = get_bootstrap_server();
+    assert_eq!(consumer.client().add_brokers(&bootstrap_server).unwrap(), 1);
+
+    let metadata = consumer
+        .fetch_metadata(None, Duration::from_secs(5))
+        .expect("Failed to fetch metadata");
+    assert!(!metadata.brokers().is_empty());
+}
+
 #[tokio::test]
 async fn test_subscription() {
     let _r = env_logger::try_init();
@@ -159,6 +176,13 @@ async fn test_group_membership() {
         consumer_member.client_id(),
         "rdkafka_integration_test_client"
     );
+    assert_eq!(
+        consumer_member.subscribed_topics(),
+        Some(vec![topic_name.clone()])
+    );
+    let assigned = consumer_member.assigned_partitions().unwrap();
+    assert_eq!(assigned.count(), 3);
+    assert!(assigned.elements().iter().all(|e| e.topic() == topic_name));
 }
 
 #[tokio::test]
@@ -0,0 +1,79 @@
+//! Test client behavior against network failures injected with Toxiproxy.
+//!
+//! These tests require a Toxiproxy server, as started by `docker-compose`.
+//! The address of its HTTP API is read from the `TOXIPROXY_HOST` environment
+//! variable, and the address at which it reaches the Kafka broker from
+//! `TOXIPROXY_UPSTREAM`. The proxies listen on ports 29092 and 29093, which
+//! must be reachable from the tests at `localhost`.
+
+use std::env;
+use std::time::Duration;
+
+use rdkafka::client::{Client, DefaultClientContext};
+use rdkafka::config::ClientConfig;
+use rdkafka::error::KafkaError;
+use rdkafka::types::RDKafkaType;
+
+use crate::toxiproxy::{Proxy, Toxic, Toxiproxy};
+use crate::utils::*;
+
+mod toxiproxy;
+mod utils;
+
+fn create_proxy(port: u16) -> Proxy {
+    let toxiproxy =
+        Toxiproxy::new(env::var("TOXIPROXY_HOST").unwrap_or_else(|_| "localhost:8474".into()));
+    let upstream = env::var("TOXIPROXY_UPSTREAM").unwrap_or_else(|_| "kafka:9092".into());
+    toxiproxy
+        .create_proxy(&rand_test_topic(), &format!("0.0.0.0:{}", port), &upstream)
+        .expect("Failed to create proxy")
+}
+
+fn proxied_client(port: u16) -> Client {
+    let mut config = ClientConfig::new();
+    config.set("bootstrap.servers", format!("localhost:{}", port));
+    Client::new(
+        &config,
+        config.create_native_config().unwrap(),
+        RDKafkaType::RD_KAFKA_PRODUCER,
+        DefaultClientContext,
+    )
+    .expect("Failed to create client")
+}
+
+#[test]
+fn test_metadata_through_disabled_proxy() {
+    let proxy = create_proxy(29092);
+    proxy.disable().unwrap();
+
+    let client = proxied_client(29092);
+    assert!(matches!(
+        client.fetch_metadata(None, Duration::from_secs(2)),
+        Err(KafkaError::MetadataFetch(_))
+    ));
+
+    proxy.enable().unwrap();
+    client
+        .fetch_metadata(None, Duration::from_secs(10))
+        .expect("Metadata fetch failed once the proxy was enabled");
+
+    proxy.delete().unwrap();
+}
+
+#[test]
+fn test_metadata_with_latency() {
+    let proxy = create_proxy(29093);
+    proxy
+        .add_toxic(&Toxic::latency("latency", Duration::from_secs(3)))
+        .unwrap();
+
+    let client = proxied_client(29093);
+    assert!(client.fetch_metadata(None, Duration::from_secs(1)).is_err());
+
+    proxy.remove_toxic("latency").unwrap();
+    client
+        .fetch_metadata(None, Duration::from_secs(10))
+        .expect("Metadata fetch failed once the toxic was removed");
+
+    proxy.delete().unwrap();
+}
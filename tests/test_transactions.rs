@@ -2,6 +2,7 @@
 
 use std::collections::HashMap;
 use std::error::Error;
+use std::time::Duration;
 
 use maplit::hashmap;
 
@@ -9,8 +10,10 @@ use rdkafka::config::ClientConfig;
 use rdkafka::config::RDKafkaLogLevel;
 use rdkafka::consumer::{BaseConsumer, CommitMode, Consumer};
 use rdkafka::error::KafkaError;
+use rdkafka::message::{BorrowedMessage, Message};
 use rdkafka::producer::{BaseProducer, BaseRecord, Producer};
 use rdkafka::topic_partition_list::{Offset, TopicPartitionList};
+use rdkafka::transactions::ExactlyOnceProcessor;
 use rdkafka::util::Timeout;
 
 use utils::*;
@@ -195,3 +198,115 @@ async fn test_transaction_commit() -> Result<(), Box<dyn Error>> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_exactly_once_processor() -> Result<(), Box<dyn Error>> {
+    let consume_topic = rand_test_topic();
+    let produce_topic = rand_test_topic();
+
+    populate_topic(&consume_topic, 30, &value_fn, &key_fn, Some(0), None).await;
+
+    let consumer = create_consumer(None)?;
+    consumer.subscribe(&[&consume_topic])?;
+    let processor =
+        ExactlyOnceProcessor::new(consumer, create_producer()?, Duration::from_secs(30))?
+            .batch_size(10)
+            .batch_timeout(Duration::from_secs(10));
+
+    let forward = |messages: &[BorrowedMessage<'_>], producer: &BaseProducer| {
+        for message in messages {
+            producer
+                .send(
+                    BaseRecord::to(&produce_topic)
+                        .payload(message.payload().unwrap())
+                        .key(message.key().unwrap())
+                        .partition(0),
+                )
+                .map_err(|(e, _)| e)?;
+        }
+        Ok::<_, KafkaError>(())
+    };
+
+    // A failing batch is aborted, and its messages are processed again by the
+    // next batch.
+    let result = processor.process_batch(|messages, producer| {
+        forward(messages, producer)?;
+        Err(KafkaError::Canceled)
+    });
+    assert_eq!(result, Err(KafkaError::Canceled));
+    let mut first_offset = None;
+    let processed = processor.process_batch(|messages, producer| {
+        first_offset = Some(messages[0].offset());
+        forward(messages, producer)
+    })?;
+    assert_eq!(processed, 10);
+    assert_eq!(first_offset, Some(0));
+
+    assert_eq!(
+        count_records(&produce_topic, IsolationLevel::ReadUncommitted)?,
+        20,
+    );
+    assert_eq!(
+        count_records(&produce_topic, IsolationLevel::ReadCommitted)?,
+        10,
+    );
+    let committed = processor.consumer().committed(Timeout::Never)?;
+    assert_eq!(
+        committed
+            .find_partition(&consume_topic, 0)
+            .unwrap()
+            .offset(),
+        Offset::Offset(10)
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_exactly_once_processor_partition_eof() -> Result<(), Box<dyn Error>> {
+    let consume_topic = rand_test_topic();
+    let produce_topic = rand_test_topic();
+
+    populate_topic(&consume_topic, 30, &value_fn, &key_fn, Some(0), None).await;
+
+    let consumer = create_consumer(Some(hashmap! { "enable.partition.eof" => "true" }))?;
+    consumer.subscribe(&[&consume_topic])?;
+    let processor =
+        ExactlyOnceProcessor::new(consumer, create_producer()?, Duration::from_secs(30))?
+            .batch_size(100)
+            .batch_timeout(Duration::from_secs(5));
+
+    // Reaching the end of the partition does not abort the batch.
+    let mut processed = 0;
+    while processed < 30 {
+        processed += processor.process_batch(|messages, producer| {
+            for message in messages {
+                producer
+                    .send(
+                        BaseRecord::to(&produce_topic)
+                            .payload(message.payload().unwrap())
+                            .key(message.key().unwrap())
+                            .partition(0),
+                    )
+                    .map_err(|(e, _)| e)?;
+            }
+            Ok::<_, KafkaError>(())
+        })?;
+    }
+    assert_eq!(processed, 30);
+
+    assert_eq!(
+        count_records(&produce_topic, IsolationLevel::ReadCommitted)?,
+        30,
+    );
+    let committed = processor.consumer().committed(Timeout::Never)?;
+    assert_eq!(
+        committed
+            .find_partition(&consume_topic, 0)
+            .unwrap()
+            .offset(),
+        Offset::Offset(30)
+    );
+
+    Ok(())
+}
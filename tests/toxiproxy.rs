@@ -0,0 +1,296 @@
+//! A minimal client for the HTTP API of Toxiproxy.
+//!
+//! [Toxiproxy] is a TCP proxy that can simulate network failures, such as
+//! latency, dropped connections and network partitions. The Toxiproxy tests
+//! use it to check the retry and timeout behavior of the clients against
+//! realistic failures.
+//!
+//! To route a client through Toxiproxy, create a [`Proxy`] whose upstream is
+//! a Kafka broker, and use its listen address as `bootstrap.servers`. Note
+//! that librdkafka connects to the addresses advertised by the brokers after
+//! the initial connection, so only the bootstrap connection goes through the
+//! proxy unless the brokers advertise the proxy addresses.
+//!
+//! [Toxiproxy]: https://github.com/Shopify/toxiproxy
+
+#![allow(dead_code)]
+
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+/// A client for the HTTP API of a Toxiproxy server.
+#[derive(Clone, Debug)]
+pub struct Toxiproxy {
+    addr: String,
+}
+
+impl Toxiproxy {
+    /// Creates a client for the Toxiproxy server listening on `addr`, in the
+    /// form `host:port`. Toxiproxy listens on port 8474 by default.
+    pub fn new<A: Into<String>>(addr: A) -> Toxiproxy {
+        Toxiproxy { addr: addr.into() }
+    }
+
+    /// Creates a proxy that listens on `listen` and forwards connections to
+    /// `upstream`, both in the form `host:port`.
+    pub fn create_proxy(&self, name: &str, listen: &str, upstream: &str) -> io::Result<Proxy> {
+        let body = json!({
+            "name": name,
+            "listen": listen,
+            "upstream": upstream,
+            "enabled": true,
+        });
+        self.request("POST", "/proxies", Some(&body))?;
+        Ok(Proxy {
+            toxiproxy: self.clone(),
+            name: name.to_string(),
+        })
+    }
+
+    /// Returns a handle to an existing proxy.
+    pub fn proxy(&self, name: &str) -> Proxy {
+        Proxy {
+            toxiproxy: self.clone(),
+            name: name.to_string(),
+        }
+    }
+
+    /// Enables all the proxies and removes all their toxics.
+    pub fn reset(&self) -> io::Result<()> {
+        self.request("POST", "/reset", None).map(|_| ())
+    }
+
+    fn request(&self, method: &str, path: &str, body: Option<&Value>) -> io::Result<String> {
+        let body = body.map(Value::to_string).unwrap_or_default();
+        let mut stream = TcpStream::connect(&self.addr)?;
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            method,
+            path,
+            self.addr,
+            body.len(),
+            body
+        )?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+        let status = response
+            .split_whitespace()
+            .nth(1)
+            .and_then(|status| status.parse::<u16>().ok())
+            .ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, "invalid Toxiproxy response")
+            })?;
+        let response_body = match response.find("\r\n\r\n") {
+            Some(i) => response[i + 4..].to_string(),
+            None => String::new(),
+        };
+        if (200..300).contains(&status) {
+            Ok(response_body)
+        } else {
+            let kind = match status {
+                404 => io::ErrorKind::NotFound,
+                409 => io::ErrorKind::AlreadyExists,
+                400..=499 => io::ErrorKind::InvalidInput,
+                _ => io::ErrorKind::Other,
+            };
+            Err(io::Error::new(
+                kind,
+                format!(
+                    "Toxiproxy request {} {} failed with status {}: {}",
+                    method,
+                    path,
+                    status,
+                    response_body.trim()
+                ),
+            ))
+        }
+    }
+}
+
+/// A proxy managed by a Toxiproxy server.
+#[derive(Clone, Debug)]
+pub struct Proxy {
+    toxiproxy: Toxiproxy,
+    name: String,
+}
+
+impl Proxy {
+    /// Returns the name of the proxy.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Adds a toxic to the proxy.
+    pub fn add_toxic(&self, toxic: &Toxic) -> io::Result<()> {
+        self.toxiproxy
+            .request(
+                "POST",
+                &format!("/proxies/{}/toxics", self.name),
+                Some(&toxic.to_json()),
+            )
+            .map(|_| ())
+    }
+
+    /// Removes the toxic with the specified name from the proxy.
+    pub fn remove_toxic(&self, name: &str) -> io::Result<()> {
+        self.toxiproxy
+            .request(
+                "DELETE",
+                &format!("/proxies/{}/toxics/{}", self.name, name),
+                None,
+            )
+            .map(|_| ())
+    }
+
+    /// Disables the proxy, closing all its connections and refusing new ones.
+    ///
+    /// This simulates the upstream going down, or a network partition that
+    /// is detected immediately.
+    pub fn disable(&self) -> io::Result<()> {
+        self.set_enabled(false)
+    }
+
+    /// Enables the proxy again after a call to [`Proxy::disable`].
+    pub fn enable(&self) -> io::Result<()> {
+        self.set_enabled(true)
+    }
+
+    /// Deletes the proxy.
+    pub fn delete(&self) -> io::Result<()> {
+        self.toxiproxy
+            .request("DELETE", &format!("/proxies/{}", self.name), None)
+            .map(|_| ())
+    }
+
+    fn set_enabled(&self, enabled: bool) -> io::Result<()> {
+        self.toxiproxy
+            .request(
+                "POST",
+                &format!("/proxies/{}", self.name),
+                Some(&json!({ "enabled": enabled })),
+            )
+            .map(|_| ())
+    }
+}
+
+/// The direction of the traffic affected by a toxic.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ToxicStream {
+    /// Traffic from the client to the upstream.
+    Upstream,
+    /// Traffic from the upstream to the client.
+    Downstream,
+}
+
+/// A network fault that can be added to a [`Proxy`].
+#[derive(Clone, Debug)]
+pub struct Toxic {
+    name: String,
+    kind: &'static str,
+    attributes: Value,
+    stream: ToxicStream,
+    toxicity: f32,
+}
+
+impl Toxic {
+    fn new(name: &str, kind: &'static str, attributes: Value) -> Toxic {
+        Toxic {
+            name: name.to_string(),
+            kind,
+            attributes,
+            stream: ToxicStream::Downstream,
+            toxicity: 1.0,
+        }
+    }
+
+    /// Delays all data by `latency`.
+    pub fn latency(name: &str, latency: Duration) -> Toxic {
+        Toxic::new(
+            name,
+            "latency",
+            json!({ "latency": latency.as_millis() as u64, "jitter": 0 }),
+        )
+    }
+
+    /// Stops all data, and closes the connection after `timeout`, or never if
+    /// `timeout` is zero.
+    ///
+    /// This simulates a network partition that is not detected by the TCP
+    /// stack.
+    pub fn timeout(name: &str, timeout: Duration) -> Toxic {
+        Toxic::new(
+            name,
+            "timeout",
+            json!({ "timeout": timeout.as_millis() as u64 }),
+        )
+    }
+
+    /// Resets the connection after `timeout`, or immediately if `timeout` is
+    /// zero.
+    pub fn reset_peer(name: &str, timeout: Duration) -> Toxic {
+        Toxic::new(
+            name,
+            "reset_peer",
+            json!({ "timeout": timeout.as_millis() as u64 }),
+        )
+    }
+
+    /// Limits the bandwidth to `rate` kilobytes per second.
+    pub fn bandwidth(name: &str, rate: u64) -> Toxic {
+        Toxic::new(name, "bandwidth", json!({ "rate": rate }))
+    }
+
+    /// Sets the direction of the traffic affected by the toxic. Toxics affect
+    /// the downstream traffic by default.
+    pub fn stream(mut self, stream: ToxicStream) -> Toxic {
+        self.stream = stream;
+        self
+    }
+
+    /// Sets the probability, between 0 and 1, that the toxic applies to a
+    /// connection. Toxics apply to all connections by default.
+    pub fn toxicity(mut self, toxicity: f32) -> Toxic {
+        self.toxicity = toxicity;
+        self
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "name": self.name,
+            "type": self.kind,
+            "stream": match self.stream {
+                ToxicStream::Upstream => "upstream",
+                ToxicStream::Downstream => "downstream",
+            },
+            "toxicity": self.toxicity,
+            "attributes": self.attributes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toxic_json() {
+        let toxic = Toxic::latency("slow", Duration::from_millis(250))
+            .stream(ToxicStream::Upstream)
+            .toxicity(0.5);
+        assert_eq!(
+            toxic.to_json(),
+            json!({
+                "name": "slow",
+                "type": "latency",
+                "stream": "upstream",
+                "toxicity": 0.5,
+                "attributes": { "latency": 250, "jitter": 0 },
+            })
+        );
+    }
+}